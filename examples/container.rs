@@ -110,6 +110,8 @@ enum Cmd {
         /// Example `SIGINT`
         signal: Option<String>,
     },
+    /// Resize the TTY of the container.
+    Resize { id: String, width: u64, height: u64 },
     /// Returns usage statistics of the container.
     Stats { id: String },
     /// Returns information about running processes in the container.
@@ -303,8 +305,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 container.logs(&LogsOpts::builder().stdout(stdout).stderr(stderr).build());
 
             let logs: Vec<_> = logs_stream
-                .map(|chunk| match chunk {
-                    Ok(chunk) => chunk.to_vec(),
+                .map(|line| match line {
+                    Ok(line) => line.message,
                     Err(e) => {
                         eprintln!("Error: {e}");
                         vec![]
@@ -339,7 +341,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Cmd::Stats { id } => {
             while let Some(result) = docker.containers().get(&id).stats().next().await {
                 match result {
-                    Ok(stat) => println!("{stat:?}"),
+                    Ok(stats) => {
+                        let (rx, tx) = stats.network_io();
+                        let (read, write) = stats.block_io();
+                        println!(
+                            "CPU {:.2}% MEM {} ({:.2}%) NET {rx}/{tx} BLOCK {read}/{write} PIDS {}",
+                            stats.cpu_percent(),
+                            stats.memory_usage(),
+                            stats.memory_percent(),
+                            stats.pids_stats.current.unwrap_or_default()
+                        )
+                    }
                     Err(e) => eprintln!("Error: {e}"),
                 }
             }
@@ -382,6 +394,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Err(e) => eprintln!("Error: {e}"),
             };
         }
+        Cmd::Resize { id, width, height } => {
+            use docker_api::opts::ContainerResizeOpts;
+
+            let opts = ContainerResizeOpts::builder()
+                .width(width)
+                .height(height)
+                .build();
+
+            if let Err(e) = docker.containers().get(&id).resize(&opts).await {
+                eprintln!("Error: {e}")
+            }
+        }
     }
 
     Ok(())
@@ -83,8 +83,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 service.logs(&LogsOpts::builder().stdout(stdout).stderr(stderr).build());
 
             let logs: Vec<_> = logs_stream
-                .map(|chunk| match chunk {
-                    Ok(chunk) => chunk.to_vec(),
+                .map(|line| match line {
+                    Ok(line) => line.message,
                     Err(e) => {
                         eprintln!("Error: {e}");
                         vec![]
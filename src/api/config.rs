@@ -5,7 +5,7 @@ use crate::{
     conn::{Headers, Payload},
     models,
     opts::{ConfigCreateOpts, ConfigListOpts},
-    Result,
+    trace, Result,
 };
 
 impl_api_ty!(Config => name);
@@ -29,17 +29,20 @@ impl Configs {
     |
     /// Create a new config.
     pub async fn create(&self, opts: &ConfigCreateOpts) -> Result<Config> {
-        use serde::Deserialize;
-        #[derive(Deserialize)]
-        struct ConfigCreateResponse {
-            #[serde(rename = "Id")]
-            pub id: String,
-        }
-        self.docker
-            .post_json("/configs/create", Payload::Json(opts.serialize_vec()?), Headers::none())
-            .await
-            .map(|resp: ConfigCreateResponse| {
-                Config::new(self.docker.clone(), resp.id)
-            })
+        trace::future("config.create", None, async {
+            use serde::Deserialize;
+            #[derive(Deserialize)]
+            struct ConfigCreateResponse {
+                #[serde(rename = "Id")]
+                pub id: String,
+            }
+            self.docker
+                .post_json("/configs/create", Payload::Json(opts.serialize_vec()?), Headers::none())
+                .await
+                .map(|resp: ConfigCreateResponse| {
+                    Config::new(self.docker.clone(), resp.id)
+                })
+        })
+        .await
     }}
 }
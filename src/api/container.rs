@@ -1,19 +1,33 @@
 //! Create and manage containers.
 use crate::opts::{
-    ContainerCommitOpts, ContainerCreateOpts, ContainerListOpts, ContainerPruneOpts,
-    ContainerRemoveOpts, ContainerRestartOpts, ContainerStopOpts, ExecStartOpts,
+    ContainerAttachOpts, ContainerCommitOpts, ContainerCreateOpts, ContainerListOpts,
+    ContainerPruneOpts, ContainerRemoveOpts, ContainerResizeOpts, ContainerRestartOpts,
+    ContainerStatsOpts, ContainerStopOpts, ContainerUpdateOpts, ContainerWaitOpts, ExecOutputOpts,
+    ExecStartOpts, LogsOpts, RunOpts, WaitCondition,
 };
-use crate::{models, stream};
+#[cfg(feature = "chrono")]
+use crate::opts::{EventFilter, EventFilterType, EventsOpts};
+use crate::{models, stream, trace};
 
-use std::{io, path::Path, str};
+#[cfg(feature = "chrono")]
+use std::pin::Pin;
+use std::{
+    collections::hash_map::RandomState,
+    collections::HashMap,
+    hash::{BuildHasher, Hasher},
+    io,
+    path::Path,
+    str,
+    time::Duration,
+};
 
-use futures_util::{Stream, TryStreamExt};
+use futures_util::{AsyncWriteExt, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use hyper::Body;
 use serde::Deserialize;
 
 use crate::{
-    api::Exec,
-    conn::{tty, Headers, Payload},
+    api::{Exec, Image, Images},
+    conn::{tty, Headers, Payload, TtyChunk},
     opts::ExecCreateOpts,
     Error, Result,
 };
@@ -28,37 +42,178 @@ impl Container {
         DeleteWithOpts -> &format!("/containers/{}", container.id), String, delete
     }
 
+    /// Whether the container has a TTY, in which case its output is not multiplexed.
+    async fn is_tty(&self) -> Result<bool> {
+        let inspect = self.inspect().await?;
+        Ok(inspect.config.and_then(|c| c.tty).unwrap_or_default())
+    }
+
+    #[cfg(feature = "chrono")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+    /// Returns a stream of log lines that keeps following the logs when the container restarts
+    /// or the connection drops, ending only once the container is removed.
+    ///
+    /// Every reconnection requests the logs since the timestamp of the last received line, lines
+    /// received again are skipped. Timestamps are always requested, so every line has one.
+    pub fn follow_forever<'docker>(
+        &'docker self,
+        opts: &LogsOpts,
+    ) -> impl Stream<Item = Result<models::LogLine>> + Unpin + 'docker {
+        let opts = opts.follow_since(None);
+        let follow = FollowState {
+            container: self,
+            logs: Some(Box::pin(self.logs(&opts))),
+            opts,
+            received: false,
+            last: None,
+            seen: vec![],
+            done: false,
+        };
+        Box::pin(trace::stream(
+            "container.follow_forever",
+            self.trace_id(),
+            futures_util::stream::unfold(follow, |mut follow| async move {
+                let next = follow.next().await;
+                next.map(|line| (line, follow))
+            }),
+        ))
+    }
+
+    #[cfg(feature = "chrono")]
+    /// Waits until the container runs again. Returns `false` if it was removed instead.
+    async fn wait_for_start(&self) -> Result<bool> {
+        loop {
+            if let Some(started) = self.wait_for_start_event().await? {
+                return Ok(started);
+            }
+            // the daemon closed the event stream, check the state again
+            tokio::time::sleep(FOLLOW_RETRY_DELAY).await;
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    /// Checks the state of the container and waits for it to be started or removed. Returns
+    /// `None` if the event stream ended before either happened.
+    async fn wait_for_start_event(&self) -> Result<Option<bool>> {
+        let state = match self.inspect().await {
+            Ok(inspect) => inspect.state,
+            Err(e) if e.is_not_found() => return Ok(Some(false)),
+            Err(e) => return Err(e),
+        };
+        let (running, finished_at) = state
+            .map(|s| {
+                let running = s.running.unwrap_or_default() && !s.restarting.unwrap_or_default();
+                (running, s.finished_at)
+            })
+            .unwrap_or_default();
+        if running {
+            return Ok(Some(true));
+        }
+
+        // replay the events since the container stopped so that a restart in the meantime
+        // isn't missed
+        let finished_at = finished_at
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .unwrap_or_else(chrono::Utc::now);
+        let finished_at_nanos = finished_at.timestamp_nanos_opt().unwrap_or_default();
+        let opts = EventsOpts::builder()
+            .since(&finished_at)
+            .filter(vec![
+                EventFilter::Type(EventFilterType::Container),
+                EventFilter::Container(self.id.to_string()),
+                EventFilter::Event("start".into()),
+                EventFilter::Event("destroy".into()),
+            ])
+            .build();
+        let mut events = self.docker.events(&opts);
+        while let Some(event) = events.next().await {
+            let event = event?;
+            if event.time_nano.unwrap_or_default() <= finished_at_nanos {
+                continue;
+            }
+            match event.action.as_deref() {
+                Some("start") => return Ok(Some(true)),
+                Some("destroy") => return Ok(Some(false)),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
     api_doc! { Container => Top
     |
     /// Returns a `top` view of information about the container process.
     /// On Unix systems, this is done by running the ps command. This endpoint is not supported on Windows.
     pub async fn top(&self, psargs: Option<&str>) -> Result<models::ContainerTop200Response> {
-        let mut ep = format!("/containers/{}/top", self.id);
-        if let Some(ref args) = psargs {
-            append_query(&mut ep, encoded_pair("ps_args", args));
-        }
-        self.docker.get_json(&ep).await
+        trace::future("container.top", self.trace_id(), async {
+            let mut ep = format!("/containers/{}/top", self.id);
+            if let Some(ref args) = psargs {
+                append_query(&mut ep, encoded_pair("ps_args", args));
+            }
+            self.docker.get_json(&ep).await
+        })
+        .await
     }}
 
     api_doc! { Container => Attach
     |
-    /// Attaches a [`TtyMultiplexer`](TtyMultiplexer) to the container.
+    /// Attaches a [`TtyMultiplexer`](TtyMultiplexer) to the container's stdin, stdout and stderr.
     ///
     /// The [`TtyMultiplexer`](TtyMultiplexer) implements Stream for returning Stdout and Stderr chunks. It also implements [`AsyncWrite`](futures_util::io::AsyncWrite) for writing to Stdin.
     ///
     /// The multiplexer can be split into its read and write halves with the [`split`](TtyMultiplexer::split) method
     pub async fn attach(&self) -> Result<tty::Multiplexer> {
-        let inspect = self.inspect().await?;
-        let is_tty = inspect.config.and_then(|c| c.tty).unwrap_or_default();
-        stream::attach(
-            self.docker.clone(),
-            format!(
-                "/containers/{}/attach?stream=1&stdout=1&stderr=1&stdin=1",
-                self.id
-            ),
-            Payload::empty(),
-            is_tty,
-        )
+        let opts = ContainerAttachOpts::builder()
+            .stream(true)
+            .stdin(true)
+            .stdout(true)
+            .stderr(true)
+            .build();
+        self.attach_with(&opts).await
+    }}
+
+    api_doc! { Container => Attach
+    |
+    /// Attaches a [`TtyMultiplexer`](TtyMultiplexer) to the container with the given options.
+    ///
+    /// If stdin is attached, writing the detach sequence detaches from the container and ends
+    /// the stream.
+    pub async fn attach_with(&self, opts: &ContainerAttachOpts) -> Result<tty::Multiplexer> {
+        trace::future("container.attach", self.trace_id(), async {
+            let detach_keys = opts.detach_sequence()?;
+            let is_tty = self.is_tty().await?;
+            stream::attach(
+                self.docker.clone(),
+                construct_ep(format!("/containers/{}/attach", self.id), opts.serialize()),
+                Payload::empty(),
+                is_tty,
+                detach_keys,
+            )
+            .await
+        })
+        .await
+    }}
+
+    #[cfg(feature = "websocket")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+    api_doc! { Container => AttachWebsocket
+    |
+    /// Attaches to the container over a websocket. The returned [`WebSocket`](crate::conn::WebSocket)
+    /// is a Stream of messages with the raw output of the container and a Sink for messages
+    /// written to its stdin.
+    ///
+    /// Unlike [`attach_with`](Container::attach_with) the output is not multiplexed, stdout and
+    /// stderr arrive in the same messages.
+    pub async fn attach_ws(&self, opts: &ContainerAttachOpts) -> Result<crate::conn::WebSocket> {
+        trace::future("container.attach_ws", self.trace_id(), async {
+            self.docker
+                .get_upgrade_websocket(&construct_ep(
+                    format!("/containers/{}/attach/ws", self.id),
+                    opts.serialize(),
+                ))
+                .await
+        })
         .await
     }}
 
@@ -66,150 +221,278 @@ impl Container {
     |
     /// Returns a set of changes made to the container instance.
     pub async fn changes(&self) -> Result<Option<models::ContainerChanges200Response>> {
-        self.docker
-            .get_json(&format!("/containers/{}/changes", self.id))
-            .await
+        trace::future("container.changes", self.trace_id(), async {
+            self.docker
+                .get_json(&format!("/containers/{}/changes", self.id))
+                .await
+        })
+        .await
     }}
 
     api_doc! { Container => Export
     |
     /// Exports the current docker container into a tarball.
     pub fn export(&self) -> impl Stream<Item = Result<Vec<u8>>> + '_ {
-        self.docker
-            .get_stream(format!("/containers/{}/export", self.id))
-            .map_ok(|c| c.to_vec())
+        trace::stream(
+            "container.export",
+            self.trace_id(),
+            self.docker
+                .get_stream(format!("/containers/{}/export", self.id))
+                .map_ok(|c| c.to_vec()),
+        )
     }}
 
     api_doc! { Container => Stats
     |
-    /// Returns a stream of stats specific to this container instance.
-    pub fn stats(&self) -> impl Stream<Item = Result<serde_json::Value>> + Unpin + '_ {
+    /// Returns a stream of resource usage statistics of this container instance.
+    pub fn stats(&self) -> impl Stream<Item = Result<models::ContainerStats>> + Unpin + '_ {
+        self.stats_with(&ContainerStatsOpts::default())
+    }}
+
+    api_doc! { Container => Stats
+    |
+    /// Returns resource usage statistics of this container instance with the given options. With
+    /// `stream` set to false the stream ends after a single item.
+    pub fn stats_with<'docker>(
+        &'docker self,
+        opts: &ContainerStatsOpts,
+    ) -> impl Stream<Item = Result<models::ContainerStats>> + Unpin + 'docker {
         let codec = asynchronous_codec::LinesCodec {};
+        let ep = construct_ep(format!("/containers/{}/stats", self.id), opts.serialize());
+        let required_version = opts.required_api_version();
 
         let reader = Box::pin(
-            self.docker
-                .get_stream(format!("/containers/{}/stats", self.id))
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+            async move {
+                self.docker
+                    .require_opts_api_version(required_version)
+                    .await?;
+                Ok(self.docker.get_stream(ep))
+            }
+            .try_flatten_stream()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
         )
         .into_async_read();
 
-        Box::pin(
+        Box::pin(trace::stream(
+            "container.stats",
+            self.trace_id(),
             asynchronous_codec::FramedRead::new(reader, codec)
-                .map_err(Error::IO)
+                .map_err(|e| {
+                    // errors of the request were wrapped to read the body, unwrap them again
+                    match e.get_ref().and_then(|e| e.downcast_ref::<Error>()) {
+                        Some(_) => *e.into_inner().unwrap().downcast::<Error>().unwrap(),
+                        None => Error::IO(e),
+                    }
+                })
                 .and_then(|s: String| async move {
                     log::trace!("{}", s);
                     serde_json::from_str(&s).map_err(Error::SerdeJsonError)
                 }),
-        )
+        ))
     }}
 
     api_doc! { Container => Start
     |
     /// Start the container instance.
     pub async fn start(&self) -> Result<()> {
-        self.docker
-            .post_string(
-                &format!("/containers/{}/start", self.id),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
-            .map(|_| ())
+        trace::future("container.start", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &format!("/containers/{}/start", self.id),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => Stop
     |
     /// Stop the container instance.
     pub async fn stop(&self, opts: &ContainerStopOpts) -> Result<()> {
-        let ep = construct_ep(format!("/containers/{}/stop", self.id), opts.serialize());
-        self.docker
-            .post_string(&ep, Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("container.stop", self.trace_id(), async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            let ep = construct_ep(format!("/containers/{}/stop", self.id), opts.serialize());
+            self.docker
+                .post_string(&ep, Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => Restart
     |
     /// Restart the container instance.
     pub async fn restart(&self, opts: &ContainerRestartOpts) -> Result<()> {
-        let ep = construct_ep(format!("/containers/{}/restart", self.id), opts.serialize());
-        self.docker
-            .post_string(&ep, Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("container.restart", self.trace_id(), async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            let ep = construct_ep(format!("/containers/{}/restart", self.id), opts.serialize());
+            self.docker
+                .post_string(&ep, Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => Kill
     |
     /// Kill the container instance.
     pub async fn kill(&self, signal: Option<&str>) -> Result<()> {
-        let mut ep = format!("/containers/{}/kill", self.id);
-        if let Some(sig) = signal {
-            append_query(&mut ep, encoded_pair("signal", sig));
-        }
-        self.docker
-            .post_string(&ep, Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("container.kill", self.trace_id(), async {
+            let mut ep = format!("/containers/{}/kill", self.id);
+            if let Some(sig) = signal {
+                append_query(&mut ep, encoded_pair("signal", sig));
+            }
+            self.docker
+                .post_string(&ep, Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
+    }}
+
+    api_doc! { Container => Update
+    |
+    /// Change various configuration options of a container without having to recreate it, like
+    /// resource limits or the restart policy. Returns the warnings of the daemon.
+    pub async fn update(&self, opts: &ContainerUpdateOpts) -> Result<Vec<String>> {
+        trace::future("container.update", self.trace_id(), async {
+            self.docker
+                .post_json(
+                    format!("/containers/{}/update", self.id),
+                    Payload::Json(opts.serialize_vec()?),
+                    Headers::none(),
+                )
+                .await
+                .map(|resp: models::ContainerUpdate200Response| resp.warnings.unwrap_or_default())
+        })
+        .await
     }}
 
     api_doc! { Container => Rename
     |
     /// Rename the container instance.
     pub async fn rename(&self, name: &str) -> Result<()> {
-        self.docker
-            .post_string(
-                &format!(
-                    "/containers/{}/rename?{}",
-                    self.id,
-                    encoded_pair("name", name)
-                ),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
-            .map(|_| ())
+        trace::future("container.rename", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &format!(
+                        "/containers/{}/rename?{}",
+                        self.id,
+                        encoded_pair("name", name)
+                    ),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
+    }}
+
+    api_doc! { Container => Resize
+    |
+    /// Resize the TTY of the container. This only works if the container was created with `tty`
+    /// enabled.
+    pub async fn resize(&self, opts: &ContainerResizeOpts) -> Result<()> {
+        trace::future("container.resize", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &construct_ep(format!("/containers/{}/resize", self.id), opts.serialize()),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => Pause
     |
     /// Pause the container instance.
     pub async fn pause(&self) -> Result<()> {
-        self.docker
-            .post_string(
-                &format!("/containers/{}/pause", self.id),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
-            .map(|_| ())
+        trace::future("container.pause", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &format!("/containers/{}/pause", self.id),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => Unpause
     |
     /// Unpause the container instance.
     pub async fn unpause(&self) -> Result<()> {
-        self.docker
-            .post_string(
-                &format!("/containers/{}/unpause", self.id),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
-            .map(|_| ())
+        trace::future("container.unpause", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &format!("/containers/{}/unpause", self.id),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => Wait
     |
     /// Wait until the container stops.
     pub async fn wait(&self) -> Result<models::ContainerWaitResponse> {
-        self.docker
-            .post_json(
-                format!("/containers/{}/wait", self.id),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
+        self.wait_with(&ContainerWaitOpts::default()).await
+    }}
+
+    api_doc! { Container => Wait
+    |
+    /// Wait until the container reaches the condition of the options.
+    ///
+    /// Use [`check_exit`](check_exit) to turn a failed exit into an error. Like
+    /// [`register_wait`](Container::register_wait) only the response head is bounded by the
+    /// timeout of the client, however long the container runs.
+    pub async fn wait_with(&self, opts: &ContainerWaitOpts) -> Result<models::ContainerWaitResponse> {
+        trace::future("container.wait", self.trace_id(), async {
+            self.register_wait(opts).await?.wait().await
+        })
+        .await
+    }}
+
+    api_doc! { Container => Wait
+    |
+    /// Registers a wait for the condition of the options and returns once the daemon accepted
+    /// it, the returned [`ContainerWait`](ContainerWait) resolves once the condition is met.
+    ///
+    /// Registering a wait with [`WaitCondition::NextExit`](crate::opts::WaitCondition::NextExit)
+    /// before starting the container makes sure its exit isn't missed, however short it runs.
+    pub async fn register_wait(&self, opts: &ContainerWaitOpts) -> Result<ContainerWait> {
+        trace::future("container.register_wait", self.trace_id(), async {
+            self.docker
+                .require_opts_api_version(opts.required_api_version())
+                .await?;
+            // the daemon sends the response head as soon as the wait is registered
+            let response = self
+                .docker
+                .post(
+                    &construct_ep(format!("/containers/{}/wait", self.id), opts.serialize()),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await?;
+            Ok(ContainerWait {
+                body: response.into_body(),
+            })
+        })
+        .await
     }}
 
     api_doc! { Exec
@@ -220,7 +503,73 @@ impl Container {
         create_opts: &ExecCreateOpts,
         start_opts: &ExecStartOpts,
     ) ->  Result<tty::Multiplexer> {
-        Exec::create_and_start(self.docker.clone(), &self.id, create_opts, start_opts).await
+        trace::future("container.exec", self.trace_id(), Exec::create_and_start(self.docker.clone(), &self.id, create_opts, start_opts)).await
+    }}
+
+    api_doc! { Exec
+    |
+    /// Executes a command in this container and waits for it to exit, returning its output and
+    /// exit code.
+    pub async fn exec_output(&self, opts: &ExecCreateOpts) -> Result<models::ExecOutput> {
+        self.exec_output_with(opts, &ExecOutputOpts::default()).await
+    }}
+
+    api_doc! { Exec
+    |
+    /// Executes a command in this container and waits for it to exit, returning its output and
+    /// exit code. The options can limit how long the command runs and provide input that is
+    /// written to its stdin, which is closed afterwards.
+    ///
+    /// The daemon can't kill exec processes, so once the timeout elapsed they are found by an
+    /// environment variable set for them and killed by another exec instance running `sh` with
+    /// `tr` and `grep`. That fails in containers without these tools, like distroless or
+    /// `scratch` images, and when the command runs as another `user` than the container, in
+    /// which case the process keeps running. [`Error::Timeout`](Error::Timeout) is returned
+    /// either way.
+    pub async fn exec_output_with(
+        &self,
+        create_opts: &ExecCreateOpts,
+        opts: &ExecOutputOpts,
+    ) -> Result<models::ExecOutput> {
+        trace::future("container.exec_output", self.trace_id(), async {
+            // tags the process so that it can be found and killed once the timeout elapsed
+            let tag = format!(
+                "DOCKER_API_EXEC={:016x}",
+                RandomState::new().build_hasher().finish()
+            );
+            let create_opts = create_opts.for_output(opts.get_stdin().is_some(), &tag);
+            let exec = Exec::create(self.docker.clone(), &self.id, &create_opts).await?;
+            let output = collect_exec_output(&exec, opts.get_stdin());
+
+            let timeout = match opts.get_timeout() {
+                Some(timeout) => timeout,
+                None => return output.await,
+            };
+            match tokio::time::timeout(timeout, output).await {
+                Ok(output) => output,
+                Err(_) => {
+                    let kill_opts = ExecCreateOpts::builder()
+                        .command(["sh", "-c", KILL_TAGGED_PROCESSES, tag.as_str()])
+                        .build();
+                    let killed = async {
+                        let kill = Exec::create(self.docker.clone(), &self.id, &kill_opts).await?;
+                        collect_exec_output(&kill, None).await
+                    };
+                    match killed.await {
+                        Ok(output) if output.exit_code == 0 => {}
+                        Ok(output) => log::warn!(
+                            "failed to kill timed out exec process in {}, exit code {}: {}",
+                            self.id,
+                            output.exit_code,
+                            output.stderr_lossy().trim()
+                        ),
+                        Err(e) => log::warn!("failed to kill timed out exec process in {}: {e}", self.id),
+                    }
+                    Err(Error::Timeout(timeout))
+                }
+            }
+        })
+        .await
     }}
 
     api_doc! { Container => Archive
@@ -234,13 +583,17 @@ impl Container {
     /// ends in `/.`  then this indicates that only the contents of the path directory should be
     /// copied.  A symlink is always resolved to its target.
     pub fn copy_from(&self, path: impl AsRef<Path>) -> impl Stream<Item = Result<Vec<u8>>> + '_ {
-        self.docker
-            .get_stream(format!(
-                "/containers/{}/archive?{}",
-                self.id,
-                encoded_pair("path", path.as_ref().to_string_lossy())
-            ))
-            .map_ok(|c| c.to_vec())
+        trace::stream(
+            "container.copy_from",
+            self.trace_id(),
+            self.docker
+                .get_stream(format!(
+                    "/containers/{}/archive?{}",
+                    self.id,
+                    encoded_pair("path", path.as_ref().to_string_lossy())
+                ))
+                .map_ok(|c| c.to_vec()),
+        )
     }}
 
     api_doc! { PutContainer => Archive
@@ -250,23 +603,26 @@ impl Container {
     /// The file will be copied at the given location (see `path`) and will be owned by root
     /// with access mask 644.
     pub async fn copy_file_into<P: AsRef<Path>>(&self, path: P, bytes: &[u8]) -> Result<()> {
-        let path = path.as_ref();
-
-        let mut ar = tar::Builder::new(Vec::new());
-        let mut header = tar::Header::new_gnu();
-        header.set_size(bytes.len() as u64);
-        header.set_mode(0o0644);
-        ar.append_data(
-            &mut header,
-            path.to_path_buf()
-                .iter()
-                .skip(1)
-                .collect::<std::path::PathBuf>(),
-            bytes,
-        )?;
-        let data = ar.into_inner()?;
-
-        self.copy_to(Path::new("/"), data.into()).await.map(|_| ())
+        trace::future("container.copy_file_into", self.trace_id(), async {
+            let path = path.as_ref();
+
+            let mut ar = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o0644);
+            ar.append_data(
+                &mut header,
+                path.to_path_buf()
+                    .iter()
+                    .skip(1)
+                    .collect::<std::path::PathBuf>(),
+                bytes,
+            )?;
+            let data = ar.into_inner()?;
+
+            self.copy_to(Path::new("/"), data.into()).await.map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { PutContainer => Archive
@@ -275,17 +631,20 @@ impl Container {
     ///
     /// The tarball will be copied to the container and extracted at the given location (see `path`).
     pub async fn copy_to(&self, path: &Path, body: Body) -> Result<()> {
-        self.docker
-            .put(
-                &format!(
-                    "/containers/{}/archive?{}",
-                    self.id,
-                    encoded_pair("path", path.to_string_lossy())
-                ),
-                Payload::XTar(body),
-            )
-            .await
-            .map(|_| ())
+        trace::future("container.copy_to", self.trace_id(), async {
+            self.docker
+                .put(
+                    &format!(
+                        "/containers/{}/archive?{}",
+                        self.id,
+                        encoded_pair("path", path.to_string_lossy())
+                    ),
+                    Payload::XTar(body),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Container => ArchiveInfo
@@ -295,68 +654,236 @@ impl Container {
     where
         P: AsRef<Path>,
     {
-        static PATH_STAT_HEADER: &str = "X-Docker-Container-Path-Stat";
-        let resp = self
-            .docker
-            .head(&format!(
-                "/containers/{}/archive?{}",
-                self.id,
-                encoded_pair("path", path.as_ref().to_string_lossy())
-            ))
-            .await?;
-        if let Some(header) = resp.headers().get(PATH_STAT_HEADER) {
-            let header = header.to_str().map_err(|e| {
-                Error::InvalidResponse(format!("response header was invalid - {e}"))
-            })?;
-
-            base64::decode(header)
-                .map_err(|e| {
-                    Error::InvalidResponse(format!("expected header to be valid base64 - {e}"))
-                })
-                .and_then(|s| {
-                    str::from_utf8(s.as_slice())
-                        .map(str::to_string)
-                        .map_err(|e| {
-                            Error::InvalidResponse(format!(
-                                "expected header to be valid utf8 - {e}"
-                            ))
-                        })
-                })
-        } else {
-            Err(Error::InvalidResponse(format!("missing `{PATH_STAT_HEADER}` header")))
-        }
+        trace::future("container.stat_file", self.trace_id(), async {
+            static PATH_STAT_HEADER: &str = "X-Docker-Container-Path-Stat";
+            let resp = self
+                .docker
+                .head(&format!(
+                    "/containers/{}/archive?{}",
+                    self.id,
+                    encoded_pair("path", path.as_ref().to_string_lossy())
+                ))
+                .await?;
+            if let Some(header) = resp.headers().get(PATH_STAT_HEADER) {
+                let header = header.to_str().map_err(|e| {
+                    Error::InvalidResponse(format!("response header was invalid - {e}"))
+                })?;
+
+                base64::decode(header)
+                    .map_err(|e| {
+                        Error::InvalidResponse(format!("expected header to be valid base64 - {e}"))
+                    })
+                    .and_then(|s| {
+                        str::from_utf8(s.as_slice())
+                            .map(str::to_string)
+                            .map_err(|e| {
+                                Error::InvalidResponse(format!(
+                                    "expected header to be valid utf8 - {e}"
+                                ))
+                            })
+                    })
+            } else {
+                Err(Error::InvalidResponse(format!("missing `{PATH_STAT_HEADER}` header")))
+            }
+        })
+        .await
     }}
 
     api_doc! { Image => Commit
     |
     /// Create a new image from this container
     pub async fn commit(&self, opts: &ContainerCommitOpts, config: Option<&models::ContainerConfig>) -> Result<String> {
-        #[derive(Deserialize)]
-        struct IdStruct {
-            #[serde(rename = "Id")]
-            id: String,
+        trace::future("container.commit", self.trace_id(), async {
+            #[derive(Deserialize)]
+            struct IdStruct {
+                #[serde(rename = "Id")]
+                id: String,
+            }
+
+            let payload = if let Some(config) = config {
+                Payload::Json(serde_json::to_string(config)?)
+            } else {
+                Payload::Json("{}".into()) // empty json
+            };
+
+            self.docker
+                .post_json(
+                    format!(
+                        "/commit?{}",
+                        opts.with_container(self.id().as_ref())
+                            .serialize()
+                            .unwrap_or_default()
+                    ),
+                    payload,
+                    Headers::none(),
+                )
+                .await
+                .map(|id: IdStruct| id.id)
+        })
+        .await
+    }}
+}
+
+#[derive(Debug)]
+/// A wait registered with [`Container::register_wait`](Container::register_wait).
+pub struct ContainerWait {
+    body: Body,
+}
+
+impl ContainerWait {
+    /// Waits until the condition is met.
+    pub async fn wait(self) -> Result<models::ContainerWaitResponse> {
+        let body = hyper::body::to_bytes(self.body).await?;
+        serde_json::from_slice(&body).map_err(Error::from)
+    }
+}
+
+/// Returns [`Error::ContainerExited`](Error::ContainerExited) if the container exited with a
+/// non-zero status code or waiting failed, otherwise the response.
+pub fn check_exit(
+    response: models::ContainerWaitResponse,
+) -> Result<models::ContainerWaitResponse> {
+    let message = response
+        .error
+        .as_ref()
+        .and_then(|e| e.message.clone())
+        .filter(|m| !m.is_empty());
+    if response.status_code != 0 || message.is_some() {
+        return Err(Error::ContainerExited {
+            code: response.status_code,
+            message,
+        });
+    }
+    Ok(response)
+}
+
+#[cfg(feature = "chrono")]
+/// Delay before reconnecting to the logs of a running container after the previous connection
+/// failed without returning any lines.
+const FOLLOW_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[cfg(feature = "chrono")]
+/// State of a [`Container::follow_forever`](Container::follow_forever) stream.
+struct FollowState<'docker> {
+    container: &'docker Container,
+    opts: LogsOpts,
+    logs: Option<Pin<Box<dyn Stream<Item = Result<models::LogLine>> + 'docker>>>,
+    /// Whether the current connection returned any lines.
+    received: bool,
+    /// Timestamp of the last returned line.
+    last: Option<chrono::DateTime<chrono::Utc>>,
+    /// Messages returned with the timestamp of the last line, used to skip them after
+    /// reconnecting.
+    seen: Vec<Vec<u8>>,
+    done: bool,
+}
+
+#[cfg(feature = "chrono")]
+impl<'docker> FollowState<'docker> {
+    async fn next(&mut self) -> Option<Result<models::LogLine>> {
+        while !self.done {
+            if let Some(logs) = &mut self.logs {
+                match logs.next().await {
+                    Some(Ok(line)) => {
+                        self.received = true;
+                        if self.is_new(&line) {
+                            return Some(Ok(line));
+                        }
+                        continue;
+                    }
+                    Some(Err(e)) if should_reconnect(&e) => {
+                        log::debug!("following logs of {} failed: {e}", self.container.id);
+                        if !self.received {
+                            tokio::time::sleep(FOLLOW_RETRY_DELAY).await;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                    None => {}
+                }
+                self.logs = None;
+            }
+
+            match self.container.wait_for_start().await {
+                Ok(true) => {
+                    let opts = self.opts.follow_since(self.last.as_ref());
+                    self.logs = Some(Box::pin(self.container.logs(&opts)));
+                    self.received = false;
+                }
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
         }
+        None
+    }
 
-        let payload = if let Some(config) = config {
-            Payload::Json(serde_json::to_string(config)?)
-        } else {
-            Payload::Json("{}".into()) // empty json
+    /// Returns whether the line wasn't returned before and remembers it.
+    fn is_new(&mut self, line: &models::LogLine) -> bool {
+        let timestamp = match line.timestamp {
+            Some(timestamp) => timestamp,
+            None => return true,
         };
+        match self.last {
+            Some(last) if timestamp < last => return false,
+            Some(last) if timestamp == last => {
+                if self.seen.contains(&line.message) {
+                    return false;
+                }
+            }
+            _ => {
+                self.last = Some(timestamp);
+                self.seen.clear();
+            }
+        }
+        self.seen.push(line.message.clone());
+        true
+    }
+}
 
-        self.docker
-            .post_json(
-                format!(
-                    "/commit?{}",
-                    opts.with_container(self.id().as_ref())
-                        .serialize()
-                        .unwrap_or_default()
-                ),
-                payload,
-                Headers::none(),
-            )
-            .await
-            .map(|id: IdStruct| id.id)
-    }}
+#[cfg(feature = "chrono")]
+/// Returns whether following logs should continue after `e`, that is when the connection to the
+/// daemon failed or dropped, the daemon failed temporarily or the container is gone, which
+/// [`Container::wait_for_start`](Container::wait_for_start) finds out. Errors of the log stream
+/// are wrapped, so they are unwrapped until the underlying error is found.
+fn should_reconnect(e: &Error) -> bool {
+    use containers_api::conn::Error as ConnError;
+
+    let mut error: Option<&(dyn std::error::Error + 'static)> = Some(e);
+    while let Some(e) = error {
+        if let Some(e) = e.downcast_ref::<Error>() {
+            error = match e {
+                e if e.is_retryable() || e.is_not_found() => return true,
+                Error::Error(ConnError::Any(e)) | Error::Any(e) => Some(e.as_ref()),
+                Error::Error(ConnError::IO(e)) | Error::IO(e) => Some(e),
+                Error::Error(ConnError::Hyper(e)) => Some(e),
+                _ => return false,
+            };
+        } else if let Some(e) = e.downcast_ref::<io::Error>() {
+            if matches!(
+                e.kind(),
+                io::ErrorKind::UnexpectedEof
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+            ) {
+                return true;
+            }
+            error = e.get_ref().map(|e| e as _);
+        } else if let Some(e) = e.downcast_ref::<hyper::Error>() {
+            if e.is_closed() || e.is_incomplete_message() || e.is_canceled() {
+                return true;
+            }
+            error = std::error::Error::source(e);
+        } else {
+            error = e.source();
+        }
+    }
+    false
 }
 
 impl Containers {
@@ -369,16 +896,601 @@ impl Containers {
     |
     /// Create a container
     pub async fn create(&self, opts: &ContainerCreateOpts) -> Result<Container> {
-        let ep = if let Some(name) = opts.name() {
-            construct_ep("/containers/create", Some(encoded_pair("name", name)))
-        } else {
-            "/containers/create".to_owned()
-        };
-        self.docker
-            .post_json(&ep, Payload::Json(opts.serialize_vec()?), Headers::none())
-            .await
-            .map(|resp: models::ContainerCreateResponse| {
-                Container::new(self.docker.clone(), resp.id)
-            })
+        trace::future("container.create", opts.name(), async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            let ep = if let Some(name) = opts.name() {
+                construct_ep("/containers/create", Some(encoded_pair("name", name)))
+            } else {
+                "/containers/create".to_owned()
+            };
+            self.docker
+                .post_json(&ep, Payload::Json(opts.serialize_vec()?), Headers::none())
+                .await
+                .map(|resp: models::ContainerCreateResponse| {
+                    Container::new(self.docker.clone(), resp.id)
+                })
+        })
+        .await
     }}
+
+    /// Returns a single sample of the statistics of every container matching the list options,
+    /// mapped by container id. Containers removed in the meantime are skipped.
+    ///
+    /// The stats are requested with `stream` set to false, so `precpu_stats` are set and
+    /// [`ContainerStats::cpu_percent`](models::ContainerStats::cpu_percent) can be computed. At
+    /// most 16 containers are queried concurrently.
+    pub async fn stats_snapshot(
+        &self,
+        opts: &ContainerListOpts,
+    ) -> Result<HashMap<String, models::ContainerStats>> {
+        trace::future("container.stats_snapshot", None, async {
+            let stats_opts = ContainerStatsOpts::builder().stream(false).build();
+            let containers = self.list(opts).await?;
+
+            futures_util::stream::iter(containers.into_iter().filter_map(|c| c.id))
+                .map(|id| {
+                    let stats_opts = &stats_opts;
+                    async move {
+                        let container = self.get(id.as_str());
+                        let mut stats = container.stats_with(stats_opts);
+                        match stats.next().await {
+                            Some(Ok(stats)) => Ok(Some((id, stats))),
+                            Some(Err(e)) if e.is_not_found() => Ok(None),
+                            Some(Err(e)) => Err(e),
+                            None => Ok(None),
+                        }
+                    }
+                })
+                .buffer_unordered(STATS_SNAPSHOT_CONCURRENCY)
+                .try_filter_map(|stats| async move { Ok(stats) })
+                .try_collect()
+                .await
+        })
+        .await
+    }
+
+    /// Runs a container like `docker run` without detaching: pulls the image if it's missing,
+    /// creates and starts the container, collects its output and waits for it to exit.
+    ///
+    /// A non-zero exit code is returned in the output rather than as an error. The container is
+    /// removed if any step fails or the returned future is dropped, and once it exited unless
+    /// disabled with [`RunOpts::remove`](crate::opts::RunOpts::remove).
+    pub async fn run(
+        &self,
+        opts: &ContainerCreateOpts,
+        run_opts: &RunOpts,
+    ) -> Result<models::ContainerRunOutput> {
+        trace::future("container.run", None, async {
+            if let Some(image) = opts.image().filter(|_| run_opts.pulls()) {
+                self.pull_missing(image, run_opts).await?;
+            }
+
+            let container = self.create(opts).await?;
+            let mut guard = RemoveOnDrop(Some(Container::new(
+                self.docker.clone(),
+                container.id.clone(),
+            )));
+            let output = run_created(&container).await;
+
+            if output.is_ok() && !run_opts.removes() {
+                guard.0 = None;
+                return output;
+            }
+            let removed = match container
+                .remove(&ContainerRemoveOpts::builder().force(true).build())
+                .await
+            {
+                // the container might be removed already if it was created with `auto_remove`
+                Err(e) if e.is_not_found() || e.is_conflict() => Ok(()),
+                removed => removed.map(|_| ()),
+            };
+            // keep the guard if removing failed, so removing is tried once more when it's dropped
+            if removed.is_ok() {
+                guard.0 = None;
+            }
+            output.and_then(|output| removed.map(|_| output))
+        })
+        .await
+    }
+
+    async fn pull_missing(&self, image: &str, run_opts: &RunOpts) -> Result<()> {
+        match Image::new(self.docker.clone(), image).inspect().await {
+            Ok(_) => return Ok(()),
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(e),
+        }
+        let images = Images::new(self.docker.clone());
+        let mut pull = images.pull(&run_opts.pull_opts(image));
+        while let Some(chunk) = pull.next().await {
+            if let models::ImageBuildChunk::Error { error, .. } = chunk? {
+                return Err(Error::Any(error.into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Maximum number of concurrent requests of
+/// [`Containers::stats_snapshot`](Containers::stats_snapshot).
+const STATS_SNAPSHOT_CONCURRENCY: usize = 16;
+
+/// Starts a created container and collects its output until it exits.
+async fn run_created(container: &Container) -> Result<models::ContainerRunOutput> {
+    // register the wait before starting so that an early exit isn't missed
+    let wait = container
+        .register_wait(
+            &ContainerWaitOpts::builder()
+                .condition(WaitCondition::NextExit)
+                .build(),
+        )
+        .await?;
+    container.start().await?;
+
+    let mut output = models::ContainerRunOutput::default();
+    let logs_opts = LogsOpts::builder()
+        .follow(true)
+        .stdout(true)
+        .stderr(true)
+        .build();
+    let mut logs = container.logs(&logs_opts);
+    while let Some(line) = logs.next().await {
+        let line = line?;
+        match line.stream {
+            models::LogStream::Stdout => output.stdout.extend(line.message),
+            models::LogStream::Stderr => output.stderr.extend(line.message),
+        }
+    }
+
+    let response = wait.wait().await?;
+    if let Some(message) = response.error.and_then(|e| e.message) {
+        if !message.is_empty() {
+            return Err(Error::ContainerExited {
+                code: response.status_code,
+                message: Some(message),
+            });
+        }
+    }
+    output.status_code = response.status_code;
+    Ok(output)
+}
+
+/// Shell script killing every process whose environment contains the variable passed as `$0`.
+const KILL_TAGGED_PROCESSES: &str = r#"for p in /proc/[0-9]*; do
+    if tr '\0' '\n' < "$p/environ" 2>/dev/null | grep -qxF "$0"; then kill -KILL "${p#/proc/}"; fi
+done"#;
+
+/// Interval of inspecting an exec instance until its exit code is available.
+const EXEC_EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Number of times an exec instance is inspected for its exit code after its output ended.
+const EXEC_EXIT_POLL_ATTEMPTS: usize = 100;
+
+/// Starts the exec instance, writing `stdin` to the process before closing it, and collects
+/// the output until the process exited.
+async fn collect_exec_output(exec: &Exec, stdin: Option<&[u8]>) -> Result<models::ExecOutput> {
+    let (reader, writer) = exec.start(&ExecStartOpts::default()).await?.split();
+    futures_util::pin_mut!(reader, writer);
+
+    let write = async {
+        if let Some(stdin) = stdin {
+            let written = match writer.write_all(stdin).await {
+                Ok(()) => writer.close().await,
+                written => written,
+            };
+            match written {
+                // the process exited without reading all of its input, collect its output anyway
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+                written => written?,
+            }
+        }
+        Ok::<_, Error>(())
+    };
+    let read = async {
+        let mut output = models::ExecOutput::default();
+        while let Some(chunk) = reader.next().await {
+            match chunk? {
+                TtyChunk::StdOut(bytes) => output.stdout.extend(bytes),
+                TtyChunk::StdErr(bytes) => output.stderr.extend(bytes),
+                TtyChunk::StdIn(_) => {}
+            }
+        }
+        Ok::<_, Error>(output)
+    };
+    let ((), mut output) = futures_util::future::try_join(write, read).await?;
+
+    // the output might end before the daemon recorded the exit code
+    for _ in 0..EXEC_EXIT_POLL_ATTEMPTS {
+        let inspect = exec.inspect().await?;
+        match inspect.exit_code {
+            Some(code) if inspect.running != Some(true) => {
+                output.exit_code = code as i64;
+                return Ok(output);
+            }
+            None if inspect.running == Some(false) => break,
+            _ => tokio::time::sleep(EXEC_EXIT_POLL_INTERVAL).await,
+        }
+    }
+    Err(Error::InvalidResponse(
+        "the daemon didn't report the exit code of the exec instance".into(),
+    ))
+}
+
+/// Force removes the container when dropped while it's still set, for example when the future
+/// of [`Containers::run`](Containers::run) is cancelled.
+struct RemoveOnDrop(Option<Container>);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        if let (Some(container), Ok(handle)) =
+            (self.0.take(), tokio::runtime::Handle::try_current())
+        {
+            handle.spawn(async move {
+                let opts = ContainerRemoveOpts::builder().force(true).build();
+                let _ = container.remove(&opts).await;
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{docker::tests::serve, Docker};
+
+    #[tokio::test]
+    async fn waits_for_container_exit() {
+        let (addr, requests) = serve(vec![
+            (200, r#"{"StatusCode":0}"#),
+            (200, r#"{"StatusCode":1,"Error":{"Message":"oops"}}"#),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let container = docker.containers().get("web");
+        let opts = ContainerWaitOpts::builder()
+            .condition(WaitCondition::NextExit)
+            .build();
+
+        let response = container.wait_with(&opts).await.unwrap();
+        assert!(check_exit(response).is_ok());
+        assert!(requests
+            .recv()
+            .unwrap()
+            .starts_with("POST /containers/web/wait?condition=next-exit "));
+
+        let wait = container.register_wait(&opts).await.unwrap();
+        match check_exit(wait.wait().await.unwrap()).unwrap_err() {
+            Error::ContainerExited { code, message } => {
+                assert_eq!(code, 1);
+                assert_eq!(message.as_deref(), Some("oops"));
+            }
+            e => panic!("Expected Error::ContainerExited, got {e}"),
+        }
+
+        let docker = Docker::new_versioned(format!("tcp://{addr}"), (1, 29)).unwrap();
+        let container = docker.containers().get("web");
+        assert!(matches!(
+            container.wait_with(&opts).await,
+            Err(Error::UnsupportedApiVersion { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn runs_container() {
+        let (addr, requests) = serve(vec![
+            (200, r#"{"Id":"sha256:abc"}"#),
+            (201, r#"{"Id":"web","Warnings":[]}"#),
+            (200, r#"{"StatusCode":3}"#),
+            (204, ""),
+            (200, r#"{"Id":"web","Config":{"Tty":false}}"#),
+            (200, "\x01\0\0\0\0\0\0\x04out\n\x02\0\0\0\0\0\0\x04err\n"),
+            (204, ""),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let opts = ContainerCreateOpts::builder().image("alpine").build();
+        let output = docker
+            .containers()
+            .run(&opts, &RunOpts::default())
+            .await
+            .unwrap();
+
+        assert_eq!(output.status_code, 3);
+        assert!(!output.success());
+        assert_eq!(output.stdout_lossy(), "out\n");
+        assert_eq!(output.stderr_lossy(), "err\n");
+
+        let requests: Vec<_> = requests
+            .iter()
+            .map(|r| r.lines().next().unwrap().to_owned())
+            .collect();
+        assert_eq!(requests[0], "GET /images/alpine/json HTTP/1.1");
+        assert_eq!(requests[1], "POST /containers/create HTTP/1.1");
+        assert_eq!(
+            requests[2],
+            "POST /containers/web/wait?condition=next-exit HTTP/1.1"
+        );
+        assert_eq!(requests[3], "POST /containers/web/start HTTP/1.1");
+        assert!(requests[5].starts_with("GET /containers/web/logs?"));
+        assert_eq!(requests[6], "DELETE /containers/web?force=true HTTP/1.1");
+    }
+
+    // the second removal is spawned while the requests are received blocking
+    #[tokio::test(flavor = "multi_thread")]
+    async fn removes_container_again_if_removing_failed() {
+        let (addr, requests) = serve(vec![
+            (201, r#"{"Id":"web","Warnings":[]}"#),
+            (200, r#"{"StatusCode":0}"#),
+            (204, ""),
+            (200, r#"{"Id":"web","Config":{"Tty":false}}"#),
+            (200, ""),
+            (500, r#"{"message":"removal failed"}"#),
+            (204, ""),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let opts = ContainerCreateOpts::builder().image("alpine").build();
+        let run_opts = RunOpts::builder().pull(false).build();
+        let err = docker.containers().run(&opts, &run_opts).await.unwrap_err();
+        assert!(matches!(err, Error::ServerError { .. }));
+
+        let requests: Vec<_> = (0..7)
+            .map(|_| requests.recv_timeout(Duration::from_secs(5)).unwrap())
+            .map(|r| r.lines().next().unwrap().to_owned())
+            .collect();
+        assert_eq!(requests[5], "DELETE /containers/web?force=true HTTP/1.1");
+        assert_eq!(requests[6], "DELETE /containers/web?force=true HTTP/1.1");
+    }
+
+    /// Reply of [`serve_upgrades`](serve_upgrades) to a request.
+    enum Reply {
+        Json(u16, &'static str),
+        /// Upgrades the connection and writes the output, then reads stdin until it's closed or
+        /// idle unless `hang` is set, in which case stdin is read until the client disconnects.
+        Upgrade {
+            output: &'static [u8],
+            hang: bool,
+        },
+    }
+
+    /// Serves every connection with the next reply and returns the address of the server along
+    /// with the received requests, including their body and the stdin of upgraded connections.
+    async fn serve_upgrades(
+        replies: Vec<Reply>,
+    ) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            for reply in replies {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                let head_len = loop {
+                    if let Some(i) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                };
+                let head = String::from_utf8_lossy(&request[..head_len]).to_lowercase();
+                let body_len: usize = head
+                    .lines()
+                    .find_map(|l| l.strip_prefix("content-length:"))
+                    .map(|l| l.trim().parse().unwrap())
+                    .unwrap_or_default();
+                while request.len() < head_len + body_len {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                received
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).into_owned());
+
+                match reply {
+                    Reply::Json(status, body) => {
+                        let response = format!(
+                            "HTTP/1.1 {status} -\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                            body.len()
+                        );
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    }
+                    Reply::Upgrade { output, hang } => {
+                        stream
+                            .write_all(b"HTTP/1.1 101 UPGRADED\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n")
+                            .await
+                            .unwrap();
+                        stream.write_all(output).await.unwrap();
+                        let mut stdin = request[head_len + body_len..].to_vec();
+                        loop {
+                            let read = stream.read(&mut buf);
+                            let n = if hang {
+                                read.await.unwrap_or_default()
+                            } else {
+                                match tokio::time::timeout(Duration::from_millis(200), read).await {
+                                    Ok(n) => n.unwrap_or_default(),
+                                    Err(_) => 0,
+                                }
+                            };
+                            if n == 0 {
+                                break;
+                            }
+                            stdin.extend_from_slice(&buf[..n]);
+                        }
+                        received
+                            .lock()
+                            .unwrap()
+                            .push(String::from_utf8_lossy(&stdin).into_owned());
+                    }
+                }
+            }
+        });
+        (addr, requests)
+    }
+
+    #[tokio::test]
+    async fn collects_exec_output() {
+        let (addr, requests) = serve_upgrades(vec![
+            Reply::Json(201, r#"{"Id":"ex"}"#),
+            Reply::Json(200, r#"{"ProcessConfig":{"tty":false}}"#),
+            Reply::Upgrade {
+                output: b"\x01\0\0\0\0\0\0\x04out\n\x02\0\0\0\0\0\0\x04err\n",
+                hang: false,
+            },
+            Reply::Json(200, r#"{"Running":false,"ExitCode":2}"#),
+        ])
+        .await;
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let opts = ExecCreateOpts::builder().command(["cat"]).build();
+        let output = docker
+            .containers()
+            .get("web")
+            .exec_output_with(&opts, &ExecOutputOpts::builder().stdin("input").build())
+            .await
+            .unwrap();
+
+        assert_eq!(output.exit_code, 2);
+        assert_eq!(output.stdout_lossy(), "out\n");
+        assert_eq!(output.stderr_lossy(), "err\n");
+
+        let requests = requests.lock().unwrap();
+        assert!(requests[0].starts_with("POST /containers/web/exec "));
+        assert!(requests[0].contains(r#""AttachStdin":true"#));
+        assert!(requests[0].contains(r#""Env":["DOCKER_API_EXEC="#));
+        assert!(requests[2].starts_with("POST /exec/ex/start "));
+        assert_eq!(requests[3], "input");
+    }
+
+    #[tokio::test]
+    async fn kills_exec_after_timeout() {
+        let (addr, requests) = serve_upgrades(vec![
+            Reply::Json(201, r#"{"Id":"ex"}"#),
+            Reply::Json(200, r#"{"ProcessConfig":{"tty":false}}"#),
+            Reply::Upgrade {
+                output: b"",
+                hang: true,
+            },
+            Reply::Json(201, r#"{"Id":"kill"}"#),
+            Reply::Json(200, r#"{"ProcessConfig":{"tty":false}}"#),
+            Reply::Upgrade {
+                output: b"",
+                hang: false,
+            },
+            // killing the process failed, the timeout is returned anyway
+            Reply::Json(200, r#"{"Running":false,"ExitCode":1}"#),
+        ])
+        .await;
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let opts = ExecCreateOpts::builder().command(["sleep", "60"]).build();
+        let timeout = Duration::from_millis(100);
+        let result = docker
+            .containers()
+            .get("web")
+            .exec_output_with(&opts, &ExecOutputOpts::builder().timeout(timeout).build())
+            .await;
+        assert!(matches!(result, Err(Error::Timeout(t)) if t == timeout));
+
+        let requests = requests.lock().unwrap();
+        let (_, body) = requests[0].split_once("\r\n\r\n").unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        let tag = body["Env"][0].as_str().unwrap();
+        assert!(requests[4].starts_with("POST /containers/web/exec "));
+        assert!(requests[4].contains(&format!(r#""{tag}"]"#)));
+    }
+
+    #[tokio::test]
+    async fn fails_without_exec_exit_code() {
+        let (addr, _) = serve_upgrades(vec![
+            Reply::Json(201, r#"{"Id":"ex"}"#),
+            Reply::Json(200, r#"{"ProcessConfig":{"tty":false}}"#),
+            Reply::Upgrade {
+                output: b"",
+                hang: false,
+            },
+            Reply::Json(200, r#"{"Running":false}"#),
+        ])
+        .await;
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let opts = ExecCreateOpts::builder().command(["true"]).build();
+        let result = docker.containers().get("web").exec_output(&opts).await;
+        assert!(matches!(result, Err(Error::InvalidResponse(_))));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[tokio::test]
+    async fn follows_logs_across_restarts() {
+        let running = (200, r#"{"Config":{"Tty":true},"State":{"Running":true}}"#);
+        let (addr, requests) = serve(vec![
+            running,
+            (200, "2023-01-01T00:00:01Z one\n2023-01-01T00:00:02Z two\n"),
+            // the connection dropped while the container kept running
+            running,
+            running,
+            (
+                200,
+                "2023-01-01T00:00:02Z two\n2023-01-01T00:00:03Z three\n",
+            ),
+            (
+                200,
+                r#"{"State":{"Running":false,"FinishedAt":"2023-01-01T00:00:04Z"}}"#,
+            ),
+            (
+                200,
+                concat!(
+                    r#"{"Action":"start","timeNano":1672531203000000000}"#,
+                    "\n",
+                    r#"{"Action":"destroy","timeNano":1672531205000000000}"#,
+                    "\n"
+                ),
+            ),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let container = docker.containers().get("id");
+
+        let lines: Vec<_> = container
+            .follow_forever(&LogsOpts::builder().n_lines(10).build())
+            .map_ok(|line| line.message_lossy().into_owned())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(lines, ["one\n", "two\n", "three\n"]);
+
+        let requests: Vec<_> = requests.try_iter().map(|r| r.to_lowercase()).collect();
+        assert_eq!(requests.len(), 7);
+        assert!(
+            requests[1].starts_with("get /containers/id/logs?follow=true&tail=10&timestamps=true ")
+        );
+        assert!(requests[4].starts_with(
+            "get /containers/id/logs?follow=true&since=1672531202.000000000&timestamps=true "
+        ));
+        assert!(requests[6].starts_with("get /events?"));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[tokio::test]
+    async fn follows_logs_until_an_error() {
+        let running = (200, r#"{"Config":{"Tty":true},"State":{"Running":true}}"#);
+        let (addr, _) = serve(vec![
+            running,
+            (200, "2023-01-01T00:00:01Z one\n"),
+            (
+                200,
+                r#"{"State":{"Running":false,"FinishedAt":"2023-01-01T00:00:02Z"}}"#,
+            ),
+            // the event stream ended without the container being started or removed
+            (200, ""),
+            running,
+            running,
+            (400, r#"{"message":"bad parameter"}"#),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let container = docker.containers().get("id");
+
+        let mut logs = container.follow_forever(&LogsOpts::builder().n_lines(10).build());
+        let line = logs.next().await.unwrap().unwrap();
+        assert_eq!(line.message_lossy(), "one\n");
+        let err = logs.next().await.unwrap().unwrap_err();
+        assert!(!should_reconnect(&err), "{err:?}");
+        assert!(logs.next().await.is_none());
+    }
 }
@@ -5,8 +5,8 @@ use hyper::Body;
 use crate::{
     conn::{tty, Headers, Payload},
     models,
-    opts::{ExecCreateOpts, ExecResizeOpts, ExecStartOpts},
-    stream, Docker, Result,
+    opts::{detach_sequence, ExecCreateOpts, ExecResizeOpts, ExecStartOpts},
+    stream, trace, Docker, Result,
 };
 
 api_doc! { Exec
@@ -15,13 +15,15 @@ api_doc! { Exec
 pub struct Exec {
     docker: Docker,
     id: crate::Id,
+    detach_keys: Option<String>,
 }}
 
 impl Exec {
-    fn new(docker: Docker, id: impl Into<crate::Id>) -> Self {
+    fn new(docker: Docker, id: impl Into<crate::Id>, detach_keys: Option<String>) -> Self {
         Exec {
             docker,
             id: id.into(),
+            detach_keys,
         }
     }
 
@@ -29,16 +31,17 @@ impl Exec {
     ///
     /// It's in callers responsibility to ensure that exec instance with specified id actually
     /// exists. Use [Exec::create](Exec::create) to ensure that the exec instance is created
-    /// beforehand.
+    /// beforehand. The detach keys of the instance are unknown, so the returned stream of
+    /// [`Exec::start`](Exec::start) only ends on the default detach sequence `ctrl-p,ctrl-q`.
     pub fn get(docker: Docker, id: impl Into<crate::Id>) -> Exec {
-        Exec::new(docker, id)
+        Exec::new(docker, id, None)
     }
 
     api_doc! { Exec => Inspect
     |
     /// Inspect this Exec instance
     pub async fn inspect(&self) -> Result<models::ExecInspect200Response> {
-        Self::inspect_impl(&self.docker, self.id.as_ref()).await
+        trace::future("exec.inspect", Some(self.id.as_ref()), Self::inspect_impl(&self.docker, self.id.as_ref())).await
     }}
 
     async fn inspect_impl(docker: &Docker, id: &str) -> Result<models::ExecInspect200Response> {
@@ -56,6 +59,9 @@ impl Exec {
             id: String,
         }
 
+        docker
+            .require_opts_api_version(opts.required_api_version())
+            .await?;
         docker
             .post_json(
                 &format!("/containers/{}/exec", container_id),
@@ -75,28 +81,41 @@ impl Exec {
         opts: &ExecCreateOpts,
     ) -> Result<Exec>
     {
-        Self::create_impl(docker.clone(), container_id.as_ref(), opts)
+        trace::future("exec.create", Some(container_id.as_ref()), async {
+            Self::create_impl(docker.clone(), container_id.as_ref(), opts)
+                .await
+                .map(|id| Exec::new(docker, id, opts.detach_keys().map(String::from)))
+        })
         .await
-            .map(|id| Exec::new(docker, id))
     }}
 
     async fn start_impl(
         docker: Docker,
         id: &str,
         opts: &ExecStartOpts,
+        detach_keys: Option<&str>,
     ) -> Result<tty::Multiplexer> {
+        docker
+            .require_opts_api_version(opts.required_api_version())
+            .await?;
         let endpoint = format!("/exec/{}/start", id);
         let inspect_data = Self::inspect_impl(&docker, id).await?;
         let is_tty = inspect_data
             .process_config
             .and_then(|c| c.tty)
             .unwrap_or_default();
+        let detach_keys = if inspect_data.open_stdin.unwrap_or_default() {
+            Some(detach_sequence(detach_keys)?)
+        } else {
+            None
+        };
 
         stream::attach(
             docker,
             endpoint,
             Payload::Json(opts.serialize_vec()?.into()),
             is_tty,
+            detach_keys,
         )
         .await
     }
@@ -105,7 +124,16 @@ impl Exec {
     |
     /// Starts this exec instance returning a multiplexed tty stream.
     pub async fn start(&self, opts: &ExecStartOpts) -> Result<tty::Multiplexer> {
-        Self::start_impl(self.docker.clone(), self.id.as_ref(), opts).await
+        trace::future("exec.start", Some(self.id.as_ref()), async {
+            Self::start_impl(
+                self.docker.clone(),
+                self.id.as_ref(),
+                opts,
+                self.detach_keys.as_deref(),
+            )
+            .await
+        })
+        .await
     }}
 
     pub(crate) async fn create_and_start(
@@ -117,7 +145,7 @@ impl Exec {
         let container_id = container_id.as_ref();
         let id = Self::create_impl(docker.clone(), container_id, create_opts).await?;
 
-        Self::start_impl(docker, id.as_ref(), start_opts).await
+        Self::start_impl(docker, id.as_ref(), start_opts, create_opts.detach_keys()).await
     }
 
     api_doc! { Exec => Resize
@@ -125,14 +153,17 @@ impl Exec {
     /// Resize the TTY session used by an exec instance. This only works if the exec was created
     /// with `tty` enabled.
     pub async fn resize(&self, opts: &ExecResizeOpts) -> Result<()> {
-        let body: Body = opts.serialize()?.into();
-
-        self.docker
-            .post_json(
-                &format!("/exec/{}/resize", &self.id),
-                Payload::Json(body),
-                Headers::none(),
-            )
-            .await
+        trace::future("exec.resize", Some(self.id.as_ref()), async {
+            let body: Body = opts.serialize()?.into();
+
+            self.docker
+                .post_json(
+                    &format!("/exec/{}/resize", &self.id),
+                    Payload::Json(body),
+                    Headers::none(),
+                )
+                .await
+        })
+        .await
     }}
 }
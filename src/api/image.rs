@@ -18,7 +18,7 @@ use containers_api::{
     url::{construct_ep, encoded_pair, encoded_pairs},
 };
 
-use crate::Result;
+use crate::{trace, Result};
 
 impl_api_ty!(Image => name);
 
@@ -33,9 +33,12 @@ impl Image {
     ///
     /// Use [`delete`](Image::delete) to delete without options.
     pub async fn remove(&self, opts: &ImageRemoveOpts) -> Result<Vec<models::ImageDeleteResponseItem>> {
-        let ep =
-            containers_api::url::construct_ep(format!("/images/{}", self.name), opts.serialize());
-        self.docker.delete_json(ep.as_ref()).await
+        trace::future("image.remove", self.trace_id(), async {
+            let ep =
+                containers_api::url::construct_ep(format!("/images/{}", self.name), opts.serialize());
+            self.docker.delete_json(ep.as_ref()).await
+        })
+        .await
     }}
 
     api_doc! { Image => Delete
@@ -44,79 +47,113 @@ impl Image {
     ///
     /// Use [`remove`](Image::remove) to delete with options.
     pub async fn delete(&self) -> Result<Vec<models::ImageDeleteResponseItem>> {
-        self.docker
-            .delete_json(&format!("/images/{}", self.name))
-            .await
+        trace::future("image.delete", self.trace_id(), async {
+            self.docker
+                .delete_json(&format!("/images/{}", self.name))
+                .await
+        })
+        .await
     }}
 
     api_doc! { Image => History
     |
     /// Lists the history of the images set of changes.
     pub async fn history(&self) -> Result<models::ImageHistory200Response> {
-        self.docker
-            .get_json(&format!("/images/{}/history", self.name))
-            .await
+        trace::future("image.history", self.trace_id(), async {
+            self.docker
+                .get_json(&format!("/images/{}/history", self.name))
+                .await
+        })
+        .await
     }}
 
     api_doc! { Image => Get
     |
     /// Export this image to a tarball.
     pub fn export(&self) -> impl Stream<Item = Result<Vec<u8>>> + Unpin + '_ {
-        Box::pin(
+        Box::pin(trace::stream(
+            "image.export",
+            self.trace_id(),
             self.docker
                 .get_stream(format!("/images/{}/get", self.name))
                 .map_ok(|c| c.to_vec()),
-        )
+        ))
     }}
 
     api_doc! { Image => Tag
     |
     /// Adds a tag to an image.
     pub async fn tag(&self, opts: &TagOpts) -> Result<()> {
-        let ep = construct_ep(format!("/images/{}/tag", self.name), opts.serialize());
-        self.docker
-            .post_string(&ep, Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("image.tag", self.trace_id(), async {
+            let ep = construct_ep(format!("/images/{}/tag", self.name), opts.serialize());
+            self.docker
+                .post_string(&ep, Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Image => Push
     |
     /// Push an image to registry.
+    ///
+    /// If no credentials are provided in `opts` they are resolved by the registry host of the
+    /// image, see [`Docker::with_credentials`](crate::Docker::with_credentials).
     pub async fn push(&self, opts: &ImagePushOpts) -> Result<()> {
-        let ep = construct_ep(format!("/images/{}/push", self.name), opts.serialize());
+        trace::future("image.push", self.trace_id(), async {
+            let ep = construct_ep(format!("/images/{}/push", self.name), opts.serialize());
 
-        let headers = opts
-            .auth_header()
-            .map(|auth| Headers::single(AUTH_HEADER, auth))
-            .unwrap_or_else(Headers::default);
+            let auth = match opts.auth_header() {
+                Some(auth) => Some(auth),
+                None => self.docker.registry_auth(self.name.as_ref()).await,
+            };
+            let headers = auth
+                .map(|auth| Headers::single(AUTH_HEADER, auth))
+                .unwrap_or_else(Headers::default);
 
-        self.docker
-            .post_string(&ep, Payload::empty(), Some(headers))
-            .await
-            .map(|_| ())
+            self.docker
+                .post_string(&ep, Payload::empty(), Some(headers))
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Distribution => Inspect
     |
     /// Return image digest and platform information by contacting the registry.
     pub async fn distribution_inspect(&self) -> Result<models::DistributionInspect> {
-        self.docker
-            .post_json(
-                &format!("/distribution/{}/json", self.name),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
+        trace::future("image.distribution_inspect", self.trace_id(), async {
+            self.docker
+                .post_json(
+                    &format!("/distribution/{}/json", self.name),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+        })
+        .await
     }}
 }
 
 impl Images {
     impl_api_ep! {img: Image, resp
-        List -> "/images/json", models::ImageSummary
         Prune ->  "/images/prune", models::ImagePrune200Response
     }
 
+    api_doc! { Image => List
+    |
+    /// List available Images.
+    pub async fn list(&self, opts: &ImageListOpts) -> Result<Vec<models::ImageSummary>> {
+        trace::future("image.list", None, async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            let ep = construct_ep("/images/json", opts.serialize());
+            self.docker.get_json(&ep).await
+        })
+        .await
+    }}
+
     api_doc! { Image => Build
     |
     /// Builds a new image by reading a Dockerfile in a target directory. If speed is
@@ -130,9 +167,13 @@ impl Images {
         let mut bytes = vec![];
         let tar_result = tarball::dir(&mut bytes, &opts.path);
 
+        let required_version = opts.required_api_version();
         let docker = &self.docker;
-        Box::pin(
+        Box::pin(trace::stream(
+            "image.build",
+            None,
             async move {
+                docker.require_opts_api_version(required_version).await?;
                 tar_result?;
 
                 let value_stream =
@@ -141,7 +182,7 @@ impl Images {
                 Ok(value_stream)
             }
             .try_flatten_stream(),
-        )
+        ))
     }}
 
     api_doc! { Image => Build
@@ -158,9 +199,13 @@ impl Images {
 
         let tar_result = tarball::dir_par(&opts.path);
 
+        let required_version = opts.required_api_version();
         let docker = &self.docker;
-        Box::pin(
+        Box::pin(trace::stream(
+            "image.build",
+            None,
             async move {
+                docker.require_opts_api_version(required_version).await?;
                 let bytes = tar_result?;
 
                 let value_stream =
@@ -169,7 +214,7 @@ impl Images {
                 Ok(value_stream)
             }
             .try_flatten_stream(),
-        )
+        ))
     }}
 
     api_doc! { Image => Search
@@ -179,27 +224,47 @@ impl Images {
     where
         T: AsRef<str>,
     {
-        self.docker
-            .get_json(&construct_ep(
-                "/images/search",
-                Some(encoded_pair("term", term.as_ref())),
-            ))
-            .await
+        trace::future("image.search", None, async {
+            self.docker
+                .get_json(&construct_ep(
+                    "/images/search",
+                    Some(encoded_pair("term", term.as_ref())),
+                ))
+                .await
+        })
+        .await
     }}
 
     api_doc! { Image => Pull
     |
     /// Pull and create a new docker images from an existing image.
+    ///
+    /// If no credentials are provided in `opts` they are resolved by the registry host of the
+    /// image, see [`Docker::with_credentials`](crate::Docker::with_credentials).
     pub fn pull<'docker>(
         &'docker self,
         opts: &PullOpts,
     ) -> impl Stream<Item = Result<models::ImageBuildChunk>> + Unpin + 'docker {
-        let headers = opts.auth_header().map(|a| Headers::single(AUTH_HEADER, a));
+        let ep = construct_ep("/images/create", opts.serialize());
+        let auth = opts.auth_header();
+        let image = opts.image().map(str::to_owned);
 
-        Box::pin(self.docker.post_into_stream(
-            construct_ep("/images/create", opts.serialize()),
-            Payload::empty(),
-            headers,
+        Box::pin(trace::stream(
+            "image.pull",
+            opts.image(),
+            async move {
+                let auth = match (auth, image) {
+                    (Some(auth), _) => Some(auth),
+                    (None, Some(image)) => self.docker.registry_auth(&image).await,
+                    (None, None) => None,
+                };
+                Ok(self.docker.post_into_stream(
+                    ep,
+                    Payload::empty(),
+                    auth.map(|a| Headers::single(AUTH_HEADER, a)),
+                ))
+            }
+            .try_flatten_stream(),
         ))
     }}
 
@@ -211,12 +276,16 @@ impl Images {
         &'docker self,
         names: Vec<&str>,
     ) -> impl Stream<Item = Result<Vec<u8>>> + 'docker {
-        self.docker
-            .get_stream(format!(
-                "/images/get?{}",
-                encoded_pairs(names.iter().map(|n| ("names", *n)))
-            ))
-            .map_ok(|c| c.to_vec())
+        trace::stream(
+            "image.export_all",
+            None,
+            self.docker
+                .get_stream(format!(
+                    "/images/get?{}",
+                    encoded_pairs(names.iter().map(|n| ("names", *n)))
+                ))
+                .map_ok(|c| c.to_vec()),
+        )
     }}
 
     api_doc! { Image => Load
@@ -230,7 +299,9 @@ impl Images {
     where
         R: Read + Send + 'docker,
     {
-        Box::pin(
+        Box::pin(trace::stream(
+            "image.import",
+            None,
             async move {
                 let mut bytes = Vec::default();
 
@@ -244,7 +315,7 @@ impl Images {
                 Ok(value_stream)
             }
             .try_flatten_stream(),
-        )
+        ))
     }}
 
     api_doc! { Image => Push
@@ -262,12 +333,16 @@ impl Images {
         &self,
         opts: &ClearCacheOpts,
     ) -> Result<models::BuildPrune200Response> {
-        self.docker
-            .post_json(
-                construct_ep("/build/prune", opts.serialize()),
-                Payload::empty(),
-                Headers::none(),
-            )
-            .await
+        trace::future("image.clear_cache", None, async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            self.docker
+                .post_json(
+                    construct_ep("/build/prune", opts.serialize()),
+                    Payload::empty(),
+                    Headers::none(),
+                )
+                .await
+        })
+        .await
     }}
 }
@@ -7,7 +7,7 @@ use crate::{
         ContainerConnectionOpts, ContainerDisconnectionOpts, NetworkCreateOpts, NetworkListOpts,
         NetworkPruneOpts,
     },
-    Result,
+    trace, Result,
 };
 
 impl_api_ty!(Network => id);
@@ -22,28 +22,34 @@ impl Network {
     |
     /// Connect a container to a network.
     pub async fn connect(&self, opts: &ContainerConnectionOpts) -> Result<()> {
-        self.docker
-            .post_string(
-                &format!("/networks/{}/connect", self.id),
-                Payload::Json(opts.serialize_vec()?),
-                Headers::none(),
-            )
-            .await
-            .map(|_| ())
+        trace::future("network.connect", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &format!("/networks/{}/connect", self.id),
+                    Payload::Json(opts.serialize_vec()?),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Network => Disconnect
     |
     /// Disconnect a container from a network.
     pub async fn disconnect(&self, opts: &ContainerDisconnectionOpts) -> Result<()> {
-        self.docker
-            .post_string(
-                &format!("/networks/{}/disconnect", &self.id),
-                Payload::Json(opts.serialize_vec()?),
-                Headers::none(),
-            )
-            .await
-            .map(|_| ())
+        trace::future("network.disconnect", self.trace_id(), async {
+            self.docker
+                .post_string(
+                    &format!("/networks/{}/disconnect", &self.id),
+                    Payload::Json(opts.serialize_vec()?),
+                    Headers::none(),
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 }
 
@@ -57,16 +63,20 @@ impl Networks {
     |
     /// Create a new network.
     pub async fn create(&self, opts: &NetworkCreateOpts) -> Result<Network> {
-        // #TODO: handle missing id and return warnings (?)
-        self.docker
-            .post_json(
-                "/networks/create",
-                Payload::Json(opts.serialize_vec()?),
-                Headers::none(),
-            )
-            .await
-            .map(|resp: models::NetworkCreate201Response| {
-                Network::new(self.docker.clone(), resp.id.unwrap_or_default())
-            })
+        trace::future("network.create", None, async {
+            // #TODO: handle missing id and return warnings (?)
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            self.docker
+                .post_json(
+                    "/networks/create",
+                    Payload::Json(opts.serialize_vec()?),
+                    Headers::none(),
+                )
+                .await
+                .map(|resp: models::NetworkCreate201Response| {
+                    Network::new(self.docker.clone(), resp.id.unwrap_or_default())
+                })
+        })
+        .await
     }}
 }
@@ -7,7 +7,7 @@ use crate::{
     conn::{Headers, Payload},
     models,
     opts::{NodeListOpts, NodeUpdateOpts},
-    Result,
+    trace, Result,
 };
 use containers_api::url::encoded_pair;
 
@@ -25,18 +25,21 @@ impl Node {
     |
     /// Update a node.
     pub async fn update(&self, opts: &NodeUpdateOpts) -> Result<()> {
-        self.docker
-            .post(
-                &format!(
-                    "/nodes/{}/update?{}",
-                    self.name,
-                    encoded_pair("version", opts.version().to_string())
-                ),
-                Payload::Json(opts.serialize_vec()?),
-                Headers::none()
-            )
-            .await
-            .map(|_| ())
+        trace::future("node.update", self.trace_id(), async {
+            self.docker
+                .post(
+                    &format!(
+                        "/nodes/{}/update?{}",
+                        self.name,
+                        encoded_pair("version", opts.version().to_string())
+                    ),
+                    Payload::Json(opts.serialize_vec()?),
+                    Headers::none()
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 }
 
@@ -4,7 +4,7 @@ use crate::{
     conn::{Headers, Payload},
     models,
     opts::PluginListOpts,
-    Result,
+    trace, Result,
 };
 use containers_api::url::{construct_ep, encoded_pair};
 
@@ -22,35 +22,44 @@ impl Plugin {
     |
     /// Enable a plugin.
     pub async fn enable(&self, timeout: Option<u64>) -> Result<()> {
-        let query = timeout.map(|timeout| encoded_pair("timeout", timeout));
-        self.docker
-            .post(
-                &construct_ep(format!("/plugins/{}/enable", self.name), query),
-                Payload::empty(),
-                Headers::none()
-            )
-            .await
-            .map(|_| ())
+        trace::future("plugin.enable", self.trace_id(), async {
+            let query = timeout.map(|timeout| encoded_pair("timeout", timeout));
+            self.docker
+                .post(
+                    &construct_ep(format!("/plugins/{}/enable", self.name), query),
+                    Payload::empty(),
+                    Headers::none()
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Plugin => Disable
     |
     /// Disable a plugin.
     pub async fn disable(&self) -> Result<()> {
-        self.docker
-            .post(&format!("/plugins/{}/disable", self.name), Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("plugin.disable", self.trace_id(), async {
+            self.docker
+                .post(&format!("/plugins/{}/disable", self.name), Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Plugin => Push
     |
     /// Push a plugin to the registry.
     pub async fn push(&self) -> Result<()> {
-        self.docker
-            .post(&format!("/plugins/{}/push", self.name), Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("plugin.push", self.trace_id(), async {
+            self.docker
+                .post(&format!("/plugins/{}/push", self.name), Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Plugin => Create
@@ -61,14 +70,17 @@ impl Plugin {
     where
         P: AsRef<Path>,
     {
-        self.docker
-            .post(
-                &format!("/plugins/{}/create", self.name),
-                Payload::Text(path.as_ref().to_string_lossy().to_string()),
-                Headers::none()
-            )
-            .await
-            .map(|_| ())
+        trace::future("plugin.create", self.trace_id(), async {
+            self.docker
+                .post(
+                    &format!("/plugins/{}/create", self.name),
+                    Payload::Text(path.as_ref().to_string_lossy().to_string()),
+                    Headers::none()
+                )
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 }
 
@@ -4,7 +4,7 @@ use crate::{
     conn::{Headers, Payload},
     models,
     opts::{SecretCreateOpts, SecretListOpts},
-    Result,
+    trace, Result,
 };
 
 impl_api_ty!(Secret => name);
@@ -26,17 +26,20 @@ impl Secrets {
     |
     /// Create a new secret.
     pub async fn create(&self, opts: &SecretCreateOpts) -> Result<Secret> {
-        use serde::Deserialize;
-        #[derive(Deserialize)]
-        struct SecretCreateResponse {
-            #[serde(rename = "Id")]
-            pub id: String,
-        }
-        self.docker
-            .post_json("/secrets/create", Payload::Json(opts.serialize_vec()?), Headers::none())
-            .await
-            .map(|resp: SecretCreateResponse| {
-                Secret::new(self.docker.clone(), resp.id)
-            })
+        trace::future("secret.create", None, async {
+            use serde::Deserialize;
+            #[derive(Deserialize)]
+            struct SecretCreateResponse {
+                #[serde(rename = "Id")]
+                pub id: String,
+            }
+            self.docker
+                .post_json("/secrets/create", Payload::Json(opts.serialize_vec()?), Headers::none())
+                .await
+                .map(|resp: SecretCreateResponse| {
+                    Secret::new(self.docker.clone(), resp.id)
+                })
+        })
+        .await
     }}
 }
@@ -3,7 +3,7 @@ use crate::{
     conn::{Headers, Payload, AUTH_HEADER},
     models,
     opts::{ServiceListOpts, ServiceOpts},
-    Result,
+    trace, Result,
 };
 
 impl_api_ty!(Service => name);
@@ -13,16 +13,19 @@ impl Service {
     |
     /// Creates a new service from ServiceOpts.
     pub async fn create(&self, opts: &ServiceOpts) -> Result<models::ServiceCreate201Response> {
-        let headers = opts
-            .auth_header()
-            .map(|a| Headers::single(AUTH_HEADER, a));
-        self.docker
-            .post_json(
-                "/services/create",
-                Payload::Json(opts.serialize_vec()?),
-                headers,
-            )
-            .await
+        trace::future("service.create", None, async {
+            let headers = opts
+                .auth_header()
+                .map(|a| Headers::single(AUTH_HEADER, a));
+            self.docker
+                .post_json(
+                    "/services/create",
+                    Payload::Json(opts.serialize_vec()?),
+                    headers,
+                )
+                .await
+        })
+        .await
     }}
 
     impl_api_ep! { svc: Service, resp
@@ -30,6 +33,18 @@ impl Service {
         Delete -> &format!("/services/{}", svc.name), models::ServiceUpdateResponse
         Logs -> &format!("/services/{}/logs", svc.name), ()
     }
+
+    /// Whether the containers of the service have a TTY, in which case their output is not
+    /// multiplexed.
+    async fn is_tty(&self) -> Result<bool> {
+        let service = self.inspect().await?;
+        Ok(service
+            .spec
+            .and_then(|s| s.task_template)
+            .and_then(|t| t.container_spec)
+            .and_then(|c| c.tty)
+            .unwrap_or_default())
+    }
 }
 
 impl Services {
@@ -4,7 +4,7 @@ use crate::{
     conn::{Headers, Payload},
     models,
     opts::{SwarmInitOpts, SwarmJoinOpts},
-    Docker, Result,
+    trace, Docker, Result,
 };
 
 api_doc! { Swarm
@@ -19,6 +19,11 @@ impl Swarm {
         Self { docker }
     }
 
+    /// The swarm has no id to record in tracing spans.
+    fn trace_id(&self) -> Option<&str> {
+        None
+    }
+
     impl_api_ep! {_swarm: Swarm, resp
         Inspect -> "/swarm", models::Swarm
     }
@@ -27,56 +32,73 @@ impl Swarm {
     |
     /// Get the unlock key.
     pub async fn get_unlock_key(&self) -> Result<models::SwarmUnlockkey200Response> {
-        self.docker.get_json("/swarm/unlockkey").await
+        trace::future("swarm.get_unlock_key", None, self.docker.get_json("/swarm/unlockkey")).await
     }}
 
     api_doc! { Swarm => Unlock
     |
     /// Unlock a locked manager.
     pub async fn unlock_manager(&self, key: &models::SwarmUnlockBodyParam) -> Result<()> {
-        self.docker
-            .post("/swarm/unlock", Payload::Json(serde_json::to_string(key)?), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("swarm.unlock_manager", None, async {
+            self.docker
+                .post("/swarm/unlock", Payload::Json(serde_json::to_string(key)?), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Swarm => Init
     |
     /// Initialize a new swarm.
     pub async fn initialize(&self, opts: &SwarmInitOpts) -> Result<()> {
-        self.docker
-            .post("/swarm/init", Payload::Json(opts.serialize_vec()?), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("swarm.initialize", None, async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            self.docker
+                .post("/swarm/init", Payload::Json(opts.serialize_vec()?), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Swarm => Join
     |
     /// Join an existing swarm.
     pub async fn join(&self, opts: &SwarmJoinOpts) -> Result<()> {
-        self.docker
-            .post("/swarm/join", Payload::Json(opts.serialize_vec()?), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("swarm.join", None, async {
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            self.docker
+                .post("/swarm/join", Payload::Json(opts.serialize_vec()?), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Swarm => Leave
     |
     /// Leave the current swarm.
     pub async fn leave(&self) -> Result<()> {
-        self.docker
-            .post("/swarm/leave?force=false", Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("swarm.leave", None, async {
+            self.docker
+                .post("/swarm/leave?force=false", Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 
     api_doc! { Swarm => Leave
     |
     /// Leave the current swarm forcefully, even if this is the last manager or that it will break the cluster.
     pub async fn force_leave(&self) -> Result<()> {
-        self.docker
-            .post("/swarm/leave?force=true", Payload::empty(), Headers::none())
-            .await
-            .map(|_| ())
+        trace::future("swarm.force_leave", None, async {
+            self.docker
+                .post("/swarm/leave?force=true", Payload::empty(), Headers::none())
+                .await
+                .map(|_| ())
+        })
+        .await
     }}
 }
@@ -1,9 +1,12 @@
 use crate::{
     models,
-    opts::{EventsOpts, SystemDataUsageOpts},
-    Docker, Error, Result,
+    opts::{EventsOpts, RegistryAuth, SystemDataUsageOpts},
+    trace, Docker, Error, Result,
+};
+use containers_api::{
+    conn::{Headers, Payload},
+    url::construct_ep,
 };
-use containers_api::url::construct_ep;
 use futures_util::{Stream, TryStreamExt};
 
 use std::{convert::TryFrom, io};
@@ -13,23 +16,43 @@ impl Docker {
     |
     /// Returns the version of Docker that is running and various information about the system that Docker is running on.
     pub async fn version(&self) -> Result<models::SystemVersion> {
-        self.get_json("/version").await
+        trace::future("system.version", None, self.get_json("/version")).await
     }}
 
     api_doc! { System => Info
     |
     /// Returns system information about Docker instance that is running
     pub async fn info(&self) -> Result<models::SystemInfo> {
-        self.get_json("/info").await
+        trace::future("system.info", None, self.get_json("/info")).await
     }}
 
     api_doc! { System => Ping
     |
     /// This is a dummy endpoint you can use to test if the server is accessible
     pub async fn ping(&self) -> Result<models::PingInfo> {
-        self.get("/_ping")
+        trace::future("system.ping", None, async {
+            self.get("/_ping")
+                .await
+                .and_then(|resp| models::PingInfo::try_from(resp.headers()))
+        })
+        .await
+    }}
+
+    api_doc! { System => Auth
+    |
+    /// Validates credentials for a registry and, if available, gets an identity token for
+    /// accessing the registry without the password. The token can be used with
+    /// [`AuthResponse::registry_auth`](crate::models::AuthResponse::registry_auth).
+    pub async fn auth(&self, auth: &RegistryAuth) -> Result<models::AuthResponse> {
+        trace::future("system.auth", None, async {
+            self.post_json(
+                "/auth",
+                Payload::Json(serde_json::to_string(auth)?),
+                Headers::none(),
+            )
             .await
-            .and_then(|resp| models::PingInfo::try_from(resp.headers()))
+        })
+        .await
     }}
 
     api_doc! { System => Events
@@ -46,20 +69,26 @@ impl Docker {
         )
         .into_async_read();
 
-        Box::pin(
+        Box::pin(trace::stream(
+            "system.events",
+            None,
             asynchronous_codec::FramedRead::new(reader, asynchronous_codec::LinesCodec)
                 .map_err(Error::IO)
                 .and_then(|s: String| async move {
                     serde_json::from_str(&s).map_err(Error::SerdeJsonError)
                 }),
-        )
+        ))
     }}
 
     api_doc! { System => DataUsage
     |
     /// Returns data usage of this Docker instance
     pub async fn data_usage(&self, opts: &SystemDataUsageOpts) -> Result<models::SystemDataUsage200Response> {
-        let ep = construct_ep("/system/df", opts.serialize());
-        self.get_json(&ep).await
+        trace::future("system.data_usage", None, async {
+            self.require_opts_api_version(opts.required_api_version()).await?;
+            let ep = construct_ep("/system/df", opts.serialize());
+            self.get_json(&ep).await
+        })
+        .await
     }}
 }
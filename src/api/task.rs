@@ -10,6 +10,17 @@ impl Task {
         Inspect -> &format!("/tasks/{}", task.id), models::Task
         Logs -> &format!("/tasks/{}/logs", task.id), ()
     }
+
+    /// Whether the container of the task has a TTY, in which case its output is not
+    /// multiplexed.
+    async fn is_tty(&self) -> Result<bool> {
+        let task = self.inspect().await?;
+        Ok(task
+            .spec
+            .and_then(|s| s.container_spec)
+            .and_then(|c| c.tty)
+            .unwrap_or_default())
+    }
 }
 
 impl Tasks {
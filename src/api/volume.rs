@@ -4,7 +4,7 @@ use crate::{
     conn::{Headers, Payload},
     models,
     opts::{ClusterVolumeUpdateOpts, VolumeCreateOpts, VolumeListOpts, VolumePruneOpts},
-    Result,
+    trace, Result,
 };
 use containers_api::url;
 
@@ -20,9 +20,13 @@ impl Volume {
     |
     /// Update a volume. Valid only for Swarm cluster volumes
     pub async fn update(&self, opts: &ClusterVolumeUpdateOpts) -> Result<()> {
-        let mut ep = format!("/volumes/{}", self.name());
-        url::append_query(&mut ep, url::encoded_pair("version", opts.version()));
-        self.docker.put(&ep, Payload::Json(opts.serialize_vec()?)).await.map(|_| ())
+        trace::future("volume.update", self.trace_id(), async {
+            self.docker.require_api_version((1, 42)).await?;
+            let mut ep = format!("/volumes/{}", self.name());
+            url::append_query(&mut ep, url::encoded_pair("version", opts.version()));
+            self.docker.put(&ep, Payload::Json(opts.serialize_vec()?)).await.map(|_| ())
+        })
+        .await
     }}
 }
 
@@ -35,21 +39,28 @@ impl Volumes {
     |
     /// List available volumes
     pub async fn list(&self, opts: &VolumeListOpts) -> Result<models::VolumeListResponse> {
-        let ep = url::construct_ep("/volumes", opts.serialize());
-        self.docker.get_json(&ep).await
+        trace::future("volume.list", None, async {
+            let ep = url::construct_ep("/volumes", opts.serialize());
+            self.docker.get_json(&ep).await
+        })
+        .await
     }}
 
     api_doc! { Volume => Create
     |
     /// Create a new volume.
     pub async fn create(&self, opts: &VolumeCreateOpts) -> Result<models::Volume> {
-        // #TODO: handle missing id and return warnings (?)
-        self.docker
-            .post_json(
-                "/volumes/create",
-                Payload::Json(opts.serialize_vec()?),
-                Headers::none(),
-            )
-            .await
+        trace::future("volume.create", None, async {
+            // #TODO: handle missing id and return warnings (?)
+            self.docker.require_opts_api_version(opts.required_api_version()).await?;
+            self.docker
+                .post_json(
+                    "/volumes/create",
+                    Payload::Json(opts.serialize_vec()?),
+                    Headers::none(),
+                )
+                .await
+        })
+        .await
     }}
 }
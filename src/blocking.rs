@@ -0,0 +1,846 @@
+//! Blocking (synchronous) interface to the Docker API. Enabled with the `blocking` feature.
+//!
+//! The interfaces mirror their async counterparts, every method blocks the current thread until
+//! the request completes. Streaming endpoints like logs, pull, events or stats return an
+//! [`Iter`](Iter) that blocks until the next item of the stream arrives.
+//!
+//! Each [`Docker`](Docker) owns a current-thread tokio runtime that is shared by all of its
+//! clones and all interfaces created from it. Blocking methods panic when called from within
+//! an async runtime, use the async interface there instead.
+//!
+//! ```no_run
+//! use docker_api::{blocking::Docker, opts::ContainerListOpts};
+//!
+//! let docker = Docker::new("unix:///var/run/docker.sock").unwrap();
+//! for container in docker.containers().list(&ContainerListOpts::default()).unwrap() {
+//!     println!("{:?}", container.names);
+//! }
+//! ```
+
+use crate::{
+    conn::{tty, TtyChunk},
+    models,
+    opts::{
+        ClearCacheOpts, ClusterVolumeUpdateOpts, ContainerAttachOpts, ContainerCommitOpts,
+        ContainerConnectionOpts, ContainerCreateOpts, ContainerDisconnectionOpts,
+        ContainerListOpts, ContainerPruneOpts, ContainerRemoveOpts, ContainerResizeOpts,
+        ContainerRestartOpts, ContainerStatsOpts, ContainerStopOpts, ContainerUpdateOpts,
+        ContainerWaitOpts, EventsOpts, ExecCreateOpts, ExecOutputOpts, ExecResizeOpts,
+        ExecStartOpts, ImageBuildOpts, ImageListOpts, ImagePruneOpts, ImagePushOpts,
+        ImageRemoveOpts, LogsOpts, NetworkCreateOpts, NetworkListOpts, NetworkPruneOpts, PullOpts,
+        RegistryAuth, RunOpts, SystemDataUsageOpts, TagOpts, VolumeCreateOpts, VolumeListOpts,
+        VolumePruneOpts,
+    },
+    ApiVersion, Error, Id, Result,
+};
+
+#[cfg(feature = "swarm")]
+use crate::opts::{
+    ConfigCreateOpts, ConfigListOpts, NodeListOpts, NodeUpdateOpts, PluginListOpts,
+    SecretCreateOpts, SecretListOpts, ServiceListOpts, ServiceOpts, SwarmInitOpts, SwarmJoinOpts,
+    TaskListOpts,
+};
+
+use futures_util::{io::AsyncWriteExt, Stream, StreamExt};
+use tokio::runtime::Runtime;
+
+use std::{collections::HashMap, fmt, io, io::Read, path::Path, pin::Pin, sync::Arc};
+
+/// Generates blocking versions of async methods of the wrapped `inner` interface.
+macro_rules! blocking_fns {
+    (
+        $base:ident
+        $(
+            $(#[$attr:meta])*
+            fn $name:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> $ret:ty;
+        )*
+    ) => {
+        $(
+        calculated_doc! {
+        #[doc = concat!("Blocking version of [`", stringify!($base), "::", stringify!($name), "`](crate::", stringify!($base), "::", stringify!($name), ").")]
+        $(#[$attr])*
+        pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+            self.rt.block_on(self.inner.$name($($arg),*))
+        }
+        }
+        )*
+    };
+}
+
+/// Generates blocking versions of methods of the wrapped `inner` interface that return streams.
+macro_rules! blocking_streams {
+    (
+        $base:ident
+        $(
+            $(#[$attr:meta])*
+            fn $name:ident(&self $(, $arg:ident: $ty:ty)* $(,)?) -> $item:ty;
+        )*
+    ) => {
+        $(
+        calculated_doc! {
+        #[doc = concat!("Blocking version of [`", stringify!($base), "::", stringify!($name), "`](crate::", stringify!($base), "::", stringify!($name), ") returning an iterator over the items of the stream.")]
+        $(#[$attr])*
+        pub fn $name(&self $(, $arg: $ty)*) -> Iter<'_, $item> {
+            Iter::new(&self.rt, self.inner.$name($($arg),*))
+        }
+        }
+        )*
+    };
+}
+
+/// Generates blocking wrappers of an API type and its plural counterpart, like `Container` and
+/// `Containers`.
+macro_rules! blocking_api_ty {
+    ($name:ident => $name_field:ident) => {
+        paste::item! {
+        calculated_doc! {
+        #[doc = concat!("Blocking interface for a Docker ", stringify!($name), ", see [`", stringify!($name), "`](crate::", stringify!($name), ").")]
+        #[derive(Debug)]
+        pub struct $name {
+            inner: crate::$name,
+            rt: Arc<Runtime>,
+        }
+        }
+
+        impl $name {
+            calculated_doc! {
+            #[doc = concat!("A getter for ", stringify!($name), " ", stringify!($name_field))]
+            pub fn $name_field(&self) -> &Id {
+                self.inner.$name_field()
+            }
+            }
+
+            /// Returns the async interface.
+            pub fn as_async(&self) -> &crate::$name {
+                &self.inner
+            }
+        }
+
+        calculated_doc! {
+        #[doc = concat!("Blocking interface for Docker ", stringify!($name), "s, see [`", stringify!($name), "s`](crate::", stringify!($name), "s).")]
+        #[derive(Debug)]
+        pub struct [< $name s >] {
+            inner: crate::[< $name s >],
+            rt: Arc<Runtime>,
+        }
+        }
+
+        impl [< $name s >] {
+            calculated_doc! {
+            #[doc = concat!("Returns a reference to a set of operations available to a specific ", stringify!($name), ".")]
+            pub fn get(&self, $name_field: impl Into<Id>) -> $name {
+                $name {
+                    inner: self.inner.get($name_field),
+                    rt: self.rt.clone(),
+                }
+            }
+            }
+
+            /// Returns the async interface.
+            pub fn as_async(&self) -> &crate::[< $name s >] {
+                &self.inner
+            }
+        }
+        }
+    };
+}
+
+/// Iterator over the items of a stream returned by an API endpoint. Every call to `next` blocks
+/// until the next item arrives.
+pub struct Iter<'a, T> {
+    rt: &'a Runtime,
+    stream: Pin<Box<dyn Stream<Item = Result<T>> + 'a>>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn new(rt: &'a Runtime, stream: impl Stream<Item = Result<T>> + 'a) -> Self {
+        Self {
+            rt,
+            stream: Box::pin(stream),
+        }
+    }
+}
+
+impl<T> Iterator for Iter<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rt.block_on(self.stream.next())
+    }
+}
+
+impl<T> fmt::Debug for Iter<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iter").finish_non_exhaustive()
+    }
+}
+
+/// Blocking version of [`tty::Multiplexer`](crate::conn::tty::Multiplexer). Iterating yields the
+/// output of the attached process, writing sends data to its standard input.
+pub struct Multiplexer {
+    rt: Arc<Runtime>,
+    inner: Pin<Box<tty::Multiplexer>>,
+}
+
+impl Multiplexer {
+    fn new(rt: Arc<Runtime>, inner: tty::Multiplexer) -> Self {
+        Self {
+            rt,
+            inner: Box::pin(inner),
+        }
+    }
+}
+
+impl Iterator for Multiplexer {
+    type Item = Result<TtyChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rt
+            .block_on(self.inner.next())
+            .map(|chunk| chunk.map_err(Error::Error))
+    }
+}
+
+impl io::Write for Multiplexer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.rt.block_on(self.inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.rt.block_on(self.inner.flush())
+    }
+}
+
+impl fmt::Debug for Multiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Multiplexer").finish_non_exhaustive()
+    }
+}
+
+//####################################################################################################
+// Docker
+//####################################################################################################
+
+#[derive(Clone, Debug)]
+/// Blocking entrypoint for interacting with the Docker API, see [`Docker`](crate::Docker).
+pub struct Docker {
+    inner: crate::Docker,
+    rt: Arc<Runtime>,
+}
+
+impl Docker {
+    /// Wraps an async client. Returns an error if the runtime can't be created.
+    pub fn from_async(docker: crate::Docker) -> Result<Self> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            inner: docker,
+            rt: Arc::new(rt),
+        })
+    }
+
+    /// Returns the async client.
+    pub fn as_async(&self) -> &crate::Docker {
+        &self.inner
+    }
+
+    /// Blocking version of [`Docker::new`](crate::Docker::new).
+    pub fn new(uri: impl AsRef<str>) -> Result<Self> {
+        Self::from_async(crate::Docker::new(uri)?)
+    }
+
+    /// Blocking version of [`Docker::new_versioned`](crate::Docker::new_versioned).
+    pub fn new_versioned(uri: impl AsRef<str>, version: impl Into<ApiVersion>) -> Result<Self> {
+        Self::from_async(crate::Docker::new_versioned(uri, version)?)
+    }
+
+    /// Blocking version of [`Docker::from_env`](crate::Docker::from_env).
+    pub fn from_env() -> Result<Self> {
+        Self::from_async(crate::Docker::from_env()?)
+    }
+
+    #[cfg(unix)]
+    #[cfg_attr(docsrs, doc(cfg(unix)))]
+    /// Blocking version of [`Docker::unix`](crate::Docker::unix).
+    pub fn unix(socket_path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_async(crate::Docker::unix(socket_path))
+    }
+
+    /// Blocking version of [`Docker::tcp`](crate::Docker::tcp).
+    pub fn tcp(host: impl AsRef<str>) -> Result<Self> {
+        Self::from_async(crate::Docker::tcp(host)?)
+    }
+
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    /// Blocking version of [`Docker::tls`](crate::Docker::tls).
+    pub fn tls(host: impl AsRef<str>, cert_path: impl AsRef<Path>, verify: bool) -> Result<Self> {
+        Self::from_async(crate::Docker::tls(host, cert_path, verify)?)
+    }
+
+    /// Blocking version of [`Docker::ssh`](crate::Docker::ssh).
+    pub fn ssh(uri: impl AsRef<str>) -> Result<Self> {
+        Self::from_async(crate::Docker::ssh(uri)?)
+    }
+
+    /// Exports an interface for interacting with Docker images.
+    pub fn images(&self) -> Images {
+        Images {
+            inner: self.inner.images(),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with Docker containers.
+    pub fn containers(&self) -> Containers {
+        Containers {
+            inner: self.inner.containers(),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with Docker networks.
+    pub fn networks(&self) -> Networks {
+        Networks {
+            inner: self.inner.networks(),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Exports an interface for interacting with Docker volumes.
+    pub fn volumes(&self) -> Volumes {
+        Volumes {
+            inner: self.inner.volumes(),
+            rt: self.rt.clone(),
+        }
+    }
+
+    /// Returns the API version used by the client, see
+    /// [`Docker::api_version`](crate::Docker::api_version).
+    pub fn api_version(&self) -> Option<ApiVersion> {
+        self.inner.api_version()
+    }
+
+    blocking_fns! { Docker
+        fn adjust_api_version(&self) -> Result<()>;
+        fn version(&self) -> Result<models::SystemVersion>;
+        fn info(&self) -> Result<models::SystemInfo>;
+        fn ping(&self) -> Result<models::PingInfo>;
+        fn auth(&self, auth: &RegistryAuth) -> Result<models::AuthResponse>;
+        fn data_usage(&self, opts: &SystemDataUsageOpts) -> Result<models::SystemDataUsage200Response>;
+    }
+
+    blocking_streams! { Docker
+        fn events(&self, opts: &EventsOpts) -> models::EventMessage;
+    }
+}
+
+//####################################################################################################
+// Containers
+//####################################################################################################
+
+blocking_api_ty!(Container => id);
+
+impl Container {
+    blocking_fns! { Container
+        fn inspect(&self) -> Result<models::ContainerInspect200Response>;
+        fn top(&self, psargs: Option<&str>) -> Result<models::ContainerTop200Response>;
+        fn changes(&self) -> Result<Option<models::ContainerChanges200Response>>;
+        fn start(&self) -> Result<()>;
+        fn stop(&self, opts: &ContainerStopOpts) -> Result<()>;
+        fn restart(&self, opts: &ContainerRestartOpts) -> Result<()>;
+        fn kill(&self, signal: Option<&str>) -> Result<()>;
+        fn update(&self, opts: &ContainerUpdateOpts) -> Result<Vec<String>>;
+        fn resize(&self, opts: &ContainerResizeOpts) -> Result<()>;
+        fn rename(&self, name: &str) -> Result<()>;
+        fn pause(&self) -> Result<()>;
+        fn unpause(&self) -> Result<()>;
+        fn wait(&self) -> Result<models::ContainerWaitResponse>;
+        fn wait_with(&self, opts: &ContainerWaitOpts) -> Result<models::ContainerWaitResponse>;
+        fn exec_output(&self, opts: &ExecCreateOpts) -> Result<models::ExecOutput>;
+        fn exec_output_with(
+            &self,
+            create_opts: &ExecCreateOpts,
+            opts: &ExecOutputOpts,
+        ) -> Result<models::ExecOutput>;
+        fn copy_file_into(&self, path: impl AsRef<Path>, bytes: &[u8]) -> Result<()>;
+        fn copy_to(&self, path: &Path, body: hyper::Body) -> Result<()>;
+        fn stat_file(&self, path: impl AsRef<Path>) -> Result<String>;
+        fn commit(&self, opts: &ContainerCommitOpts, config: Option<&models::ContainerConfig>) -> Result<String>;
+        fn remove(&self, opts: &ContainerRemoveOpts) -> Result<String>;
+        fn delete(&self) -> Result<String>;
+    }
+
+    blocking_streams! { Container
+        fn logs(&self, opts: &LogsOpts) -> models::LogLine;
+        #[cfg(feature = "chrono")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+        fn follow_forever(&self, opts: &LogsOpts) -> models::LogLine;
+        fn export(&self) -> Vec<u8>;
+        fn stats(&self) -> models::ContainerStats;
+        fn stats_with(&self, opts: &ContainerStatsOpts) -> models::ContainerStats;
+        fn copy_from(&self, path: impl AsRef<Path>) -> Vec<u8>;
+    }
+
+    /// Blocking version of [`Container::attach`](crate::Container::attach).
+    pub fn attach(&self) -> Result<Multiplexer> {
+        let multiplexer = self.rt.block_on(self.inner.attach())?;
+        Ok(Multiplexer::new(self.rt.clone(), multiplexer))
+    }
+
+    /// Blocking version of [`Container::attach_with`](crate::Container::attach_with).
+    pub fn attach_with(&self, opts: &ContainerAttachOpts) -> Result<Multiplexer> {
+        let multiplexer = self.rt.block_on(self.inner.attach_with(opts))?;
+        Ok(Multiplexer::new(self.rt.clone(), multiplexer))
+    }
+
+    /// Blocking version of [`Container::exec`](crate::Container::exec).
+    pub fn exec(
+        &self,
+        create_opts: &ExecCreateOpts,
+        start_opts: &ExecStartOpts,
+    ) -> Result<Multiplexer> {
+        let multiplexer = self.rt.block_on(self.inner.exec(create_opts, start_opts))?;
+        Ok(Multiplexer::new(self.rt.clone(), multiplexer))
+    }
+}
+
+impl Containers {
+    blocking_fns! { Containers
+        fn list(&self, opts: &ContainerListOpts) -> Result<Vec<models::ContainerSummary>>;
+        fn prune(&self, opts: &ContainerPruneOpts) -> Result<models::ContainerPrune200Response>;
+        fn stats_snapshot(
+            &self,
+            opts: &ContainerListOpts,
+        ) -> Result<HashMap<String, models::ContainerStats>>;
+        fn run(
+            &self,
+            opts: &ContainerCreateOpts,
+            run_opts: &RunOpts,
+        ) -> Result<models::ContainerRunOutput>;
+    }
+
+    /// Blocking version of [`Containers::create`](crate::Containers::create).
+    pub fn create(&self, opts: &ContainerCreateOpts) -> Result<Container> {
+        let inner = self.rt.block_on(self.inner.create(opts))?;
+        Ok(Container {
+            inner,
+            rt: self.rt.clone(),
+        })
+    }
+}
+
+//####################################################################################################
+// Exec
+//####################################################################################################
+
+/// Blocking interface for a docker exec instance, see [`Exec`](crate::Exec).
+pub struct Exec {
+    inner: crate::Exec,
+    rt: Arc<Runtime>,
+}
+
+impl Exec {
+    /// Blocking version of [`Exec::create`](crate::Exec::create).
+    pub fn create(
+        docker: &Docker,
+        container_id: impl AsRef<str>,
+        opts: &ExecCreateOpts,
+    ) -> Result<Exec> {
+        let inner = docker.rt.block_on(crate::Exec::create(
+            docker.inner.clone(),
+            container_id,
+            opts,
+        ))?;
+        Ok(Exec {
+            inner,
+            rt: docker.rt.clone(),
+        })
+    }
+
+    /// Get a reference to a set of operations available to an already created exec instance,
+    /// see [`Exec::get`](crate::Exec::get).
+    pub fn get(docker: &Docker, id: impl Into<Id>) -> Exec {
+        Exec {
+            inner: crate::Exec::get(docker.inner.clone(), id),
+            rt: docker.rt.clone(),
+        }
+    }
+
+    /// Returns the async interface.
+    pub fn as_async(&self) -> &crate::Exec {
+        &self.inner
+    }
+
+    blocking_fns! { Exec
+        fn inspect(&self) -> Result<models::ExecInspect200Response>;
+        fn resize(&self, opts: &ExecResizeOpts) -> Result<()>;
+    }
+
+    /// Blocking version of [`Exec::start`](crate::Exec::start).
+    pub fn start(&self, opts: &ExecStartOpts) -> Result<Multiplexer> {
+        let multiplexer = self.rt.block_on(self.inner.start(opts))?;
+        Ok(Multiplexer::new(self.rt.clone(), multiplexer))
+    }
+}
+
+//####################################################################################################
+// Images
+//####################################################################################################
+
+blocking_api_ty!(Image => name);
+
+impl Image {
+    blocking_fns! { Image
+        fn inspect(&self) -> Result<models::ImageInspect>;
+        fn remove(&self, opts: &ImageRemoveOpts) -> Result<Vec<models::ImageDeleteResponseItem>>;
+        fn delete(&self) -> Result<Vec<models::ImageDeleteResponseItem>>;
+        fn history(&self) -> Result<models::ImageHistory200Response>;
+        fn tag(&self, opts: &TagOpts) -> Result<()>;
+        fn push(&self, opts: &ImagePushOpts) -> Result<()>;
+        fn distribution_inspect(&self) -> Result<models::DistributionInspect>;
+    }
+
+    blocking_streams! { Image
+        fn export(&self) -> Vec<u8>;
+    }
+}
+
+impl Images {
+    blocking_fns! { Images
+        fn list(&self, opts: &ImageListOpts) -> Result<Vec<models::ImageSummary>>;
+        fn prune(&self, opts: &ImagePruneOpts) -> Result<models::ImagePrune200Response>;
+        fn search(&self, term: impl AsRef<str>) -> Result<models::ImageSearch200Response>;
+        fn push(&self, name: impl Into<Id>, opts: &ImagePushOpts) -> Result<()>;
+        fn clear_cache(&self, opts: &ClearCacheOpts) -> Result<models::BuildPrune200Response>;
+    }
+
+    blocking_streams! { Images
+        fn build(&self, opts: &ImageBuildOpts) -> models::ImageBuildChunk;
+        #[cfg(feature = "par-compress")]
+        #[cfg_attr(docsrs, doc(cfg(feature = "par-compress")))]
+        fn build_par(&self, opts: &ImageBuildOpts) -> models::ImageBuildChunk;
+        fn pull(&self, opts: &PullOpts) -> models::ImageBuildChunk;
+        fn export(&self, names: Vec<&str>) -> Vec<u8>;
+    }
+
+    /// Blocking version of [`Images::import`](crate::Images::import) returning an iterator over
+    /// the items of the stream.
+    pub fn import<'a, R>(&'a self, tarball: R) -> Iter<'a, models::ImageBuildChunk>
+    where
+        R: Read + Send + 'a,
+    {
+        Iter::new(&self.rt, self.inner.import(tarball))
+    }
+}
+
+//####################################################################################################
+// Networks
+//####################################################################################################
+
+blocking_api_ty!(Network => id);
+
+impl Network {
+    blocking_fns! { Network
+        fn inspect(&self) -> Result<models::Network>;
+        fn delete(&self) -> Result<()>;
+        fn connect(&self, opts: &ContainerConnectionOpts) -> Result<()>;
+        fn disconnect(&self, opts: &ContainerDisconnectionOpts) -> Result<()>;
+    }
+}
+
+impl Networks {
+    blocking_fns! { Networks
+        fn list(&self, opts: &NetworkListOpts) -> Result<Vec<models::Network>>;
+        fn prune(&self, opts: &NetworkPruneOpts) -> Result<models::NetworkPrune200Response>;
+    }
+
+    /// Blocking version of [`Networks::create`](crate::Networks::create).
+    pub fn create(&self, opts: &NetworkCreateOpts) -> Result<Network> {
+        let inner = self.rt.block_on(self.inner.create(opts))?;
+        Ok(Network {
+            inner,
+            rt: self.rt.clone(),
+        })
+    }
+}
+
+//####################################################################################################
+// Volumes
+//####################################################################################################
+
+blocking_api_ty!(Volume => name);
+
+impl Volume {
+    blocking_fns! { Volume
+        fn inspect(&self) -> Result<models::Volume>;
+        fn delete(&self) -> Result<()>;
+        fn update(&self, opts: &ClusterVolumeUpdateOpts) -> Result<()>;
+    }
+}
+
+impl Volumes {
+    blocking_fns! { Volumes
+        fn list(&self, opts: &VolumeListOpts) -> Result<models::VolumeListResponse>;
+        fn create(&self, opts: &VolumeCreateOpts) -> Result<models::Volume>;
+        fn prune(&self, opts: &VolumePruneOpts) -> Result<models::VolumePrune200Response>;
+    }
+}
+
+//####################################################################################################
+// Swarm
+//####################################################################################################
+
+#[cfg(feature = "swarm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "swarm")))]
+pub use self::swarm::*;
+
+#[cfg(feature = "swarm")]
+mod swarm {
+    use super::*;
+
+    impl Docker {
+        /// Exports an interface for interacting with Docker services.
+        pub fn services(&self) -> Services {
+            Services {
+                inner: self.inner.services(),
+                rt: self.rt.clone(),
+            }
+        }
+
+        /// Exports an interface for interacting with Docker configs.
+        pub fn configs(&self) -> Configs {
+            Configs {
+                inner: self.inner.configs(),
+                rt: self.rt.clone(),
+            }
+        }
+
+        /// Exports an interface for interacting with Docker tasks.
+        pub fn tasks(&self) -> Tasks {
+            Tasks {
+                inner: self.inner.tasks(),
+                rt: self.rt.clone(),
+            }
+        }
+
+        /// Exports an interface for interacting with Docker secrets.
+        pub fn secrets(&self) -> Secrets {
+            Secrets {
+                inner: self.inner.secrets(),
+                rt: self.rt.clone(),
+            }
+        }
+
+        /// Exports an interface for interacting with Docker swarm.
+        pub fn swarm(&self) -> Swarm {
+            Swarm {
+                inner: self.inner.swarm(),
+                rt: self.rt.clone(),
+            }
+        }
+
+        /// Exports an interface for interacting with Docker nodes.
+        pub fn nodes(&self) -> Nodes {
+            Nodes {
+                inner: self.inner.nodes(),
+                rt: self.rt.clone(),
+            }
+        }
+
+        /// Exports an interface for interacting with Docker plugins.
+        pub fn plugins(&self) -> Plugins {
+            Plugins {
+                inner: self.inner.plugins(),
+                rt: self.rt.clone(),
+            }
+        }
+    }
+
+    blocking_api_ty!(Config => name);
+
+    impl Config {
+        blocking_fns! { Config
+            fn inspect(&self) -> Result<models::Config>;
+            fn delete(&self) -> Result<()>;
+        }
+    }
+
+    impl Configs {
+        blocking_fns! { Configs
+            fn list(&self, opts: &ConfigListOpts) -> Result<Vec<models::Config>>;
+        }
+
+        /// Blocking version of [`Configs::create`](crate::Configs::create).
+        pub fn create(&self, opts: &ConfigCreateOpts) -> Result<Config> {
+            let inner = self.rt.block_on(self.inner.create(opts))?;
+            Ok(Config {
+                inner,
+                rt: self.rt.clone(),
+            })
+        }
+    }
+
+    blocking_api_ty!(Secret => name);
+
+    impl Secret {
+        blocking_fns! { Secret
+            fn inspect(&self) -> Result<models::Secret>;
+            fn delete(&self) -> Result<()>;
+        }
+    }
+
+    impl Secrets {
+        blocking_fns! { Secrets
+            fn list(&self, opts: &SecretListOpts) -> Result<Vec<models::Secret>>;
+        }
+
+        /// Blocking version of [`Secrets::create`](crate::Secrets::create).
+        pub fn create(&self, opts: &SecretCreateOpts) -> Result<Secret> {
+            let inner = self.rt.block_on(self.inner.create(opts))?;
+            Ok(Secret {
+                inner,
+                rt: self.rt.clone(),
+            })
+        }
+    }
+
+    blocking_api_ty!(Node => name);
+
+    impl Node {
+        blocking_fns! { Node
+            fn inspect(&self) -> Result<models::Node>;
+            fn delete(&self) -> Result<()>;
+            fn force_delete(&self) -> Result<()>;
+            fn update(&self, opts: &NodeUpdateOpts) -> Result<()>;
+        }
+    }
+
+    impl Nodes {
+        blocking_fns! { Nodes
+            fn list(&self, opts: &NodeListOpts) -> Result<Vec<models::Node>>;
+        }
+    }
+
+    blocking_api_ty!(Plugin => name);
+
+    impl Plugin {
+        blocking_fns! { Plugin
+            fn inspect(&self) -> Result<models::Plugin>;
+            fn delete(&self) -> Result<models::Plugin>;
+            fn force_delete(&self) -> Result<models::Plugin>;
+            fn enable(&self, timeout: Option<u64>) -> Result<()>;
+            fn disable(&self) -> Result<()>;
+            fn push(&self) -> Result<()>;
+            fn create(&self, path: impl AsRef<Path>) -> Result<()>;
+        }
+    }
+
+    impl Plugins {
+        blocking_fns! { Plugins
+            fn list(&self, opts: &PluginListOpts) -> Result<Vec<models::Plugin>>;
+        }
+    }
+
+    blocking_api_ty!(Service => name);
+
+    impl Service {
+        blocking_fns! { Service
+            fn create(&self, opts: &ServiceOpts) -> Result<models::ServiceCreate201Response>;
+            fn inspect(&self) -> Result<models::Service>;
+            fn delete(&self) -> Result<()>;
+        }
+
+        blocking_streams! { Service
+            fn logs(&self, opts: &LogsOpts) -> models::LogLine;
+        }
+    }
+
+    impl Services {
+        blocking_fns! { Services
+            fn list(&self, opts: &ServiceListOpts) -> Result<Vec<models::Service>>;
+        }
+    }
+
+    blocking_api_ty!(Task => id);
+
+    impl Task {
+        blocking_fns! { Task
+            fn inspect(&self) -> Result<models::Task>;
+        }
+
+        blocking_streams! { Task
+            fn logs(&self, opts: &LogsOpts) -> models::LogLine;
+        }
+    }
+
+    impl Tasks {
+        blocking_fns! { Tasks
+            fn list(&self, opts: &TaskListOpts) -> Result<Vec<models::Task>>;
+        }
+    }
+
+    /// Blocking interface for Docker swarm, see [`Swarm`](crate::Swarm).
+    pub struct Swarm {
+        inner: crate::Swarm,
+        rt: Arc<Runtime>,
+    }
+
+    impl Swarm {
+        /// Returns the async interface.
+        pub fn as_async(&self) -> &crate::Swarm {
+            &self.inner
+        }
+
+        blocking_fns! { Swarm
+            fn inspect(&self) -> Result<models::Swarm>;
+            fn get_unlock_key(&self) -> Result<models::SwarmUnlockkey200Response>;
+            fn unlock_manager(&self, key: &models::SwarmUnlockBodyParam) -> Result<()>;
+            fn initialize(&self, opts: &SwarmInitOpts) -> Result<()>;
+            fn join(&self, opts: &SwarmJoinOpts) -> Result<()>;
+            fn leave(&self) -> Result<()>;
+            fn force_leave(&self) -> Result<()>;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockDocker;
+
+    #[test]
+    fn mirrors_async_api() {
+        let docker = Docker::from_async(MockDocker::new().docker()).unwrap();
+        docker.ping().unwrap();
+
+        let opts = PullOpts::builder().image("alpine").build();
+        let chunks = docker
+            .images()
+            .pull(&opts)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert!(!chunks.is_empty());
+        docker.images().get("alpine").inspect().unwrap();
+
+        let opts = ContainerCreateOpts::builder()
+            .image("alpine")
+            .name("web")
+            .build();
+        let container = docker.containers().create(&opts).unwrap();
+        container.start().unwrap();
+        let inspect = container.inspect().unwrap();
+        assert_eq!(inspect.state.and_then(|s| s.running), Some(true));
+        assert_eq!(
+            docker.containers().list(&Default::default()).unwrap().len(),
+            1
+        );
+        assert!(docker
+            .containers()
+            .get("nope")
+            .inspect()
+            .unwrap_err()
+            .is_not_found());
+    }
+}
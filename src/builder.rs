@@ -45,6 +45,11 @@ macro_rules! impl_api_ty {
                 }
                 }
 
+                /// Returns the identifier of this object recorded in tracing spans.
+                pub(crate) fn trace_id(&self) -> Option<&str> {
+                    Some(self.$name_field.as_ref())
+                }
+
 
             }
 
@@ -149,7 +154,12 @@ macro_rules! impl_api_ep {
         #[doc = concat!("Inspect this ", stringify!($base), ".")]
         pub async fn inspect(&self) -> Result<$ret> {
             let $it = self;
-            self.docker.get_json($ep).await
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".inspect"),
+                self.trace_id(),
+                self.docker.get_json($ep),
+            )
+            .await
         }}
         }
     };
@@ -178,7 +188,12 @@ macro_rules! impl_api_ep {
         |
         #[doc = concat!("Delete this ", stringify!($base), ".")]
         pub async fn force_delete(&self) -> Result<$ret> {
-            self._delete(true).await
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".force_delete"),
+                self.trace_id(),
+                self._delete(true),
+            )
+            .await
         }}
         }
         paste::item! {
@@ -186,7 +201,12 @@ macro_rules! impl_api_ep {
         |
         #[doc = concat!("Delete this ", stringify!($base), ".")]
         pub async fn delete(&self) -> Result<$ret> {
-            self._delete(false).await
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".delete"),
+                self.trace_id(),
+                self._delete(false),
+            )
+            .await
         }}
         }
     };
@@ -199,7 +219,13 @@ macro_rules! impl_api_ep {
         #[doc = concat!("Delete this ", stringify!($base), ".")]
         pub async fn delete(&self) -> Result<()> {
             let $it = self;
-            self.docker.delete($ep).await.map(|_| ())
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".delete"),
+                self.trace_id(),
+                self.docker.delete($ep),
+            )
+            .await
+            .map(|_| ())
         }}
         }
     };
@@ -219,7 +245,12 @@ macro_rules! impl_api_ep {
         pub async fn remove(&self, opts: &[< $base RemoveOpts >]) -> Result<$ret> {
             let $it = self;
             let ep = containers_api::url::construct_ep($ep, opts.serialize());
-            self.docker.$fn(ep.as_ref()).await
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".remove"),
+                self.trace_id(),
+                self.docker.$fn(ep.as_ref()),
+            )
+            .await
         }}
         }
         paste::item! {
@@ -229,7 +260,12 @@ macro_rules! impl_api_ep {
         #[doc = concat!("Use [`remove`](", stringify!($base), "::remove) to customize options.")]
         pub async fn delete(&self) -> Result<[< $ret >]> {
             let $it = self;
-            self.docker.$fn($ep).await
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".delete"),
+                self.trace_id(),
+                self.docker.$fn($ep),
+            )
+            .await
         }}
         }
     };
@@ -241,8 +277,12 @@ macro_rules! impl_api_ep {
         |
         #[doc = concat!("List available ", stringify!($base), "s.")]
         pub async fn list(&self, opts: &[< $base ListOpts >]) -> Result<Vec<$ret>> {
-            let ep = containers_api::url::construct_ep($ep, opts.serialize());
-            self.docker.get_json(&ep).await
+            crate::trace::future(concat!(stringify!([< $base:lower >]), ".list"), None, async {
+                self.docker.require_opts_api_version(opts.required_api_version()).await?;
+                let ep = containers_api::url::construct_ep($ep, opts.serialize());
+                self.docker.get_json(&ep).await
+            })
+            .await
         }}
         }
     };
@@ -254,7 +294,10 @@ macro_rules! impl_api_ep {
         |
         #[doc = concat!("Create a new ", stringify!($base), ".")]
         pub async fn create(&self, opts: &[< $base CreateOpts >]) -> Result<[< $base >]> {
-            self.docker.post_json(&$ep, Payload::Json(opts.serialize_vec()?), Headers::none()).await
+            crate::trace::future(concat!(stringify!([< $base:lower >]), ".create"), None, async {
+                self.docker.post_json(&$ep, Payload::Json(opts.serialize_vec()?), Headers::none()).await
+            })
+            .await
             .map(|$resp: [< $ret >]| [< $base >]::new(self.docker.clone(), $($extra)*))
         }}
         }
@@ -267,12 +310,16 @@ macro_rules! impl_api_ep {
         |
         #[doc = concat!("Delete stopped/unused ", stringify!($base), "s.")]
         pub async fn prune(&self, opts: &[< $base PruneOpts >]) -> Result<$ret> {
-            self.docker
-                .post_json(
-                    &containers_api::url::construct_ep($ep, opts.serialize()),
+            crate::trace::future(
+                concat!(stringify!([< $base:lower >]), ".prune"),
+                None,
+                self.docker.post_json(
+                    containers_api::url::construct_ep($ep, opts.serialize()),
                     crate::conn::Payload::empty(),
                     crate::conn::Headers::none(),
-                ).await
+                ),
+            )
+            .await
         }}
         }
     };
@@ -282,19 +329,30 @@ macro_rules! impl_api_ep {
         paste::item! {
         api_doc! { $base => Logs
         |
-        #[doc = concat!("Returns a stream of logs from a ", stringify!($base), ".")]
+        #[doc = concat!("Returns a stream of log lines from a ", stringify!($base), ".")]
+        #[doc = ""]
+        #[doc = "The output is split into stdout and stderr lines unless it has a TTY."]
         pub fn logs<'docker>(
             &'docker self,
             opts: &crate::opts::LogsOpts
-        ) -> impl futures_util::Stream<Item = crate::Result<containers_api::conn::TtyChunk>> + Unpin + 'docker {
-            use containers_api::conn::tty;
-            use futures_util::TryStreamExt;
+        ) -> impl futures_util::Stream<Item = crate::Result<crate::models::LogLine>> + Unpin + 'docker {
+            use futures_util::TryFutureExt;
             let $it = self;
             let ep = containers_api::url::construct_ep($ep, opts.serialize());
+            let required_version = opts.required_api_version();
+            let timestamps = opts.timestamps();
 
-            let stream = Box::pin(self.docker.get_stream(ep).map_err(|e| containers_api::conn::Error::Any(Box::new(e))));
-
-            Box::pin(tty::decode(stream).map_err(crate::Error::Error))
+            Box::pin(crate::trace::stream(
+                concat!(stringify!([< $base:lower >]), ".logs"),
+                self.trace_id(),
+                async move {
+                    self.docker.require_opts_api_version(required_version).await?;
+                    let is_tty = self.is_tty().await?;
+                    let stream = Box::pin(self.docker.get_stream(ep));
+                    Ok(crate::stream::decode_logs(stream, is_tty, timestamps))
+                }
+                .try_flatten_stream(),
+            ))
         }
         }}
     };
@@ -0,0 +1,95 @@
+//! The docker CLI configuration file, usually located at `~/.docker/config.json`.
+
+use crate::Result;
+
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+const DOCKER_CONFIG_ENV: &str = "DOCKER_CONFIG";
+const CONFIG_FILE_NAME: &str = "config.json";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// Settings stored by the docker CLI in its configuration file.
+pub struct ConfigFile {
+    /// Name of the context selected with `docker context use`.
+    pub current_context: Option<String>,
+    #[serde(rename = "HttpHeaders", default)]
+    /// Extra headers the docker CLI sends with every request to the daemon, see
+    /// [`HeaderInterceptor`](crate::conn::HeaderInterceptor).
+    pub http_headers: HashMap<String, String>,
+    #[serde(default)]
+    /// Registry credentials stored by `docker login`, keyed by the registry address.
+    pub auths: HashMap<String, AuthConfig>,
+    /// Credential helper used for all registries, `docker-credential-<creds_store>` is executed.
+    pub creds_store: Option<String>,
+    #[serde(default)]
+    /// Credential helpers used for specific registries, keyed by the registry host.
+    pub cred_helpers: HashMap<String, String>,
+}
+
+#[derive(Clone, Default, Deserialize)]
+/// Credentials of a single registry stored in the `auths` section of the configuration file.
+pub struct AuthConfig {
+    /// Base64 encoded `username:password`.
+    pub auth: Option<String>,
+    #[serde(rename = "identitytoken")]
+    /// Token used instead of a password to authenticate with the registry.
+    pub identity_token: Option<String>,
+    /// Email address of the user.
+    pub email: Option<String>,
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |secret: &Option<String>| secret.as_ref().map(|_| "<redacted>");
+        f.debug_struct("AuthConfig")
+            .field("auth", &redacted(&self.auth))
+            .field("identity_token", &redacted(&self.identity_token))
+            .field("email", &self.email)
+            .finish()
+    }
+}
+
+impl ConfigFile {
+    /// Loads the configuration file from the docker configuration directory
+    /// (see [`config_dir`](config_dir)). If the directory can't be determined or the file doesn't
+    /// exist an empty configuration is returned.
+    pub fn load() -> Result<Self> {
+        match config_dir() {
+            Some(dir) => Self::load_from(dir.join(CONFIG_FILE_NAME)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Loads the configuration file located at `path`. If the file doesn't exist an empty
+    /// configuration is returned.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self> {
+        match std::fs::read(path.as_ref()) {
+            Ok(data) => serde_json::from_slice(&data).map_err(Into::into),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(crate) fn load_from_dir(dir: &Path) -> Result<Self> {
+        Self::load_from(dir.join(CONFIG_FILE_NAME))
+    }
+}
+
+/// Returns the docker CLI configuration directory, either `$DOCKER_CONFIG` or `~/.docker`.
+pub fn config_dir() -> Option<PathBuf> {
+    config_dir_impl(crate::docker::env_var)
+}
+
+pub(crate) fn config_dir_impl(var: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    var(DOCKER_CONFIG_ENV).map(PathBuf::from).or_else(|| {
+        var("HOME")
+            .or_else(|| var("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".docker"))
+    })
+}
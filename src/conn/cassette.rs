@@ -0,0 +1,602 @@
+//! Recording of exchanges with the docker daemon to cassette files and their replay.
+//!
+//! A cassette is a JSON file with a list of interactions. Every interaction contains the method
+//! and endpoint of a request and the status, headers and body chunks of its response. Streamed
+//! bodies, like pull progress or logs, are stored chunk by chunk so the replayed stream is the
+//! same as the recorded one. Chunks that aren't valid UTF-8 are stored base64 encoded.
+//!
+//! Connections upgraded by `attach` and `exec` are stored as the chunks read from and written
+//! to them in the order they were exchanged. On replay the recorded output is returned once the
+//! input written before it was written again, the content of the input isn't compared.
+//! Websocket connections are recorded without their messages and can't be replayed.
+//!
+//! Request headers and bodies aren't recorded, so registry credentials never end up in a
+//! cassette.
+
+use crate::conn::{Error, Result, Transport, Upgraded};
+
+use futures_util::{stream, Future, Stream};
+use hyper::{body::Bytes, header, Body, Request, Response, StatusCode, Uri};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use std::{
+    collections::VecDeque,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    endpoint: String,
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    #[serde(default)]
+    body: Vec<Chunk>,
+    /// Data exchanged over the upgraded connection of the response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    stream: Vec<Exchange>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Exchange {
+    /// Output of the daemon read by the client.
+    Read(Chunk),
+    /// Input written by the client.
+    Write(Chunk),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Chunk {
+    Text(String),
+    Binary { base64: String },
+}
+
+impl Chunk {
+    fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Binary {
+                base64: base64::encode(bytes),
+            },
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Bytes> {
+        match self {
+            Self::Text(text) => Ok(Bytes::copy_from_slice(text.as_bytes())),
+            Self::Binary { base64 } => base64::decode(base64)
+                .map(Bytes::from)
+                .map_err(|e| Error::IO(io::Error::new(io::ErrorKind::InvalidData, e))),
+        }
+    }
+}
+
+type UpgradeFuture = Pin<Box<dyn Future<Output = Result<Upgraded>> + Send>>;
+
+/// Connection of a recorded or replayed upgrade stored in the extensions of the response, as
+/// hyper can only upgrade the connections it made itself.
+pub(crate) struct Upgrade(Mutex<Option<UpgradeFuture>>);
+
+impl Upgrade {
+    fn new(upgrade: impl Future<Output = Result<Upgraded>> + Send + 'static) -> Self {
+        Self(Mutex::new(Some(Box::pin(upgrade))))
+    }
+
+    /// Takes the connection out of the extensions of `response` if it was recorded or replayed.
+    pub(crate) fn take(response: &mut Response<Body>) -> Option<UpgradeFuture> {
+        response
+            .extensions_mut()
+            .remove::<Self>()
+            .and_then(|upgrade| {
+                upgrade
+                    .0
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+            })
+    }
+}
+
+fn endpoint(uri: &Uri) -> String {
+    uri.path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug)]
+/// Records every exchange of a wrapped transport to a cassette file, see
+/// [`Transport::record`](Transport::record).
+pub struct CassetteRecorder {
+    path: Arc<PathBuf>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl CassetteRecorder {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path: Arc::new(path),
+            cassette: Arc::default(),
+        }
+    }
+
+    /// Sends the request with the `inner` transport and records the response once its body was
+    /// fully read or dropped. Upgraded connections are recorded until they are closed.
+    pub(crate) async fn record(
+        &self,
+        inner: &Transport,
+        req: Request<Body>,
+    ) -> Result<Response<Body>> {
+        let method = req.method().to_string();
+        let endpoint = endpoint(req.uri());
+        let tcp_upgrade = req
+            .headers()
+            .get(header::UPGRADE)
+            .is_some_and(|upgrade| upgrade == "tcp");
+        let mut response = inner.request(req).await?;
+
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+            .collect();
+        // reserve the slot now so concurrent requests are stored in the order they were answered
+        let idx = {
+            let mut cassette = self.lock();
+            cassette.interactions.push(Interaction {
+                method,
+                endpoint,
+                status: response.status().as_u16(),
+                headers,
+                body: vec![],
+                stream: vec![],
+            });
+            cassette.interactions.len() - 1
+        };
+
+        if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+            self.save();
+            // websockets are upgraded from the original response
+            if tcp_upgrade {
+                let on_upgrade = hyper::upgrade::on(&mut response);
+                let recorder = self.clone();
+                let upgrade = async move {
+                    let upgraded = on_upgrade.await?;
+                    Ok(Box::new(RecordingConnection {
+                        inner: upgraded,
+                        exchanges: vec![],
+                        idx,
+                        recorder: Some(recorder),
+                    }) as Upgraded)
+                };
+                response.extensions_mut().insert(Upgrade::new(upgrade));
+            }
+            return Ok(response);
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = RecordingBody {
+            body,
+            chunks: vec![],
+            idx,
+            recorder: Some(self.clone()),
+        };
+        Ok(Response::from_parts(parts, Body::wrap_stream(body)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cassette> {
+        self.cassette.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn finish(&self, idx: usize, chunks: Vec<Chunk>) {
+        self.lock().interactions[idx].body = chunks;
+        self.save();
+    }
+
+    fn finish_stream(&self, idx: usize, exchanges: Vec<Exchange>) {
+        self.lock().interactions[idx].stream = exchanges;
+        self.save();
+    }
+
+    fn save(&self) {
+        let data = match serde_json::to_vec_pretty(&*self.lock()) {
+            Ok(data) => data,
+            Err(e) => return log::warn!("failed to serialize cassette: {e}"),
+        };
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(&*self.path, data) {
+            log::warn!("failed to save cassette `{}`: {e}", self.path.display());
+        }
+    }
+}
+
+/// Response body that stores all read chunks in the cassette once it ends or is dropped.
+struct RecordingBody {
+    body: Body,
+    chunks: Vec<Chunk>,
+    idx: usize,
+    recorder: Option<CassetteRecorder>,
+}
+
+impl RecordingBody {
+    fn finish(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish(self.idx, std::mem::take(&mut self.chunks));
+        }
+    }
+}
+
+impl Stream for RecordingBody {
+    type Item = std::result::Result<Bytes, hyper::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.body).poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(chunk))) => this.chunks.push(Chunk::new(chunk)),
+            Poll::Ready(None) => this.finish(),
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl Drop for RecordingBody {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Upgraded connection that stores all read and written chunks in the cassette once it ends or
+/// is dropped.
+struct RecordingConnection {
+    inner: hyper::upgrade::Upgraded,
+    exchanges: Vec<Exchange>,
+    idx: usize,
+    recorder: Option<CassetteRecorder>,
+}
+
+impl RecordingConnection {
+    fn finish(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish_stream(self.idx, std::mem::take(&mut self.exchanges));
+        }
+    }
+}
+
+impl AsyncRead for RecordingConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = &poll {
+            match &buf.filled()[filled..] {
+                [] => this.finish(),
+                read => this.exchanges.push(Exchange::Read(Chunk::new(read))),
+            }
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for RecordingConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = &poll {
+            this.exchanges.push(Exchange::Write(Chunk::new(&buf[..*n])));
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl Drop for RecordingConnection {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Data of a replayed upgraded connection.
+enum Replayed {
+    Read(Bytes),
+    /// Number of bytes written by the client before the next output is returned.
+    Write(usize),
+}
+
+/// Upgraded connection replaying the recorded output. Reads wait until the input recorded
+/// before the output was written, the connection ends after the last recorded output.
+struct ReplayedConnection {
+    exchanges: VecDeque<Replayed>,
+    reader: Option<Waker>,
+}
+
+impl AsyncRead for ReplayedConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.exchanges.front_mut() {
+            Some(Replayed::Read(data)) => {
+                let read = data.split_to(data.len().min(buf.remaining()));
+                buf.put_slice(&read);
+                if data.is_empty() {
+                    this.exchanges.pop_front();
+                }
+                Poll::Ready(Ok(()))
+            }
+            Some(Replayed::Write(_)) => {
+                this.reader = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl AsyncWrite for ReplayedConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let mut written = buf.len();
+        while let Some(Replayed::Write(expected)) = this.exchanges.front_mut() {
+            let n = written.min(*expected);
+            *expected -= n;
+            written -= n;
+            if *expected == 0 {
+                this.exchanges.pop_front();
+            }
+            if written == 0 {
+                break;
+            }
+        }
+        if let Some(reader) = this.reader.take() {
+            reader.wake();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Answers requests with interactions recorded in a cassette file, see
+/// [`Transport::replay`](Transport::replay).
+pub struct CassettePlayer {
+    interactions: Arc<Mutex<Vec<Option<Interaction>>>>,
+}
+
+impl CassettePlayer {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let cassette: Cassette = serde_json::from_slice(&data)?;
+        Ok(Self {
+            interactions: Arc::new(Mutex::new(
+                cassette.interactions.into_iter().map(Some).collect(),
+            )),
+        })
+    }
+
+    /// Answers the request with the first interaction with the same method and endpoint that
+    /// wasn't replayed yet.
+    pub(crate) fn replay(&self, req: &Request<Body>) -> Result<Response<Body>> {
+        let method = req.method().as_str();
+        let endpoint = endpoint(req.uri());
+        let interaction = self
+            .interactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter_mut()
+            .find(|i| matches!(i, Some(i) if i.method == method && i.endpoint == endpoint))
+            .and_then(Option::take)
+            .ok_or_else(|| {
+                Error::IO(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no recorded interaction for {method} {endpoint}"),
+                ))
+            })?;
+
+        let mut response = Response::builder().status(interaction.status);
+        for (name, value) in &interaction.headers {
+            response = response.header(name, value);
+        }
+        let chunks = interaction
+            .body
+            .iter()
+            .map(Chunk::to_bytes)
+            .collect::<Result<Vec<_>>>()?;
+        let body = Body::wrap_stream(stream::iter(
+            chunks.into_iter().map(Ok::<_, std::convert::Infallible>),
+        ));
+
+        if interaction.status == StatusCode::SWITCHING_PROTOCOLS.as_u16() {
+            let exchanges = interaction
+                .stream
+                .iter()
+                .map(|exchange| match exchange {
+                    Exchange::Read(chunk) => chunk.to_bytes().map(Replayed::Read),
+                    Exchange::Write(chunk) => chunk.to_bytes().map(|c| Replayed::Write(c.len())),
+                })
+                .collect::<Result<_>>()?;
+            let connection = ReplayedConnection {
+                exchanges,
+                reader: None,
+            };
+            response =
+                response.extension(Upgrade::new(
+                    async move { Ok(Box::new(connection) as Upgraded) },
+                ));
+        }
+        Ok(response.body(body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn records_and_replays_interactions() {
+        use crate::{mock::MockDocker, opts::PullOpts, Docker};
+        use futures_util::TryStreamExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassettes").join("pull.json");
+
+        let daemon = MockDocker::new();
+        let inner = daemon.docker().transport().clone();
+        let docker = Docker::from_transport(Transport::record(inner, &path));
+        let opts = PullOpts::builder().image("alpine").build();
+        let recorded: Vec<_> = docker.images().pull(&opts).try_collect().await.unwrap();
+        let version = docker.version().await.unwrap();
+        assert!(docker.images().get("nope").inspect().await.is_err());
+
+        let docker = Docker::from_transport(Transport::replay(&path).unwrap());
+        let replayed: Vec<_> = docker.images().pull(&opts).try_collect().await.unwrap();
+        assert_eq!(replayed.len(), recorded.len());
+        assert_eq!(
+            docker.version().await.unwrap().api_version,
+            version.api_version
+        );
+        assert!(docker
+            .images()
+            .get("nope")
+            .inspect()
+            .await
+            .unwrap_err()
+            .is_not_found());
+        // every interaction is only replayed once
+        assert!(docker.version().await.is_err());
+    }
+
+    /// Serves the inspect request of an attach and the attach itself, which writes a line to
+    /// stdout, waits for `ls` on stdin and answers it on stderr.
+    fn serve_attach() -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            let read_until = |stream: &mut std::net::TcpStream, end: &[u8]| {
+                let mut data = vec![];
+                let mut buf = [0; 1024];
+                while !data.windows(end.len()).any(|w| w == end) {
+                    let n = stream.read(&mut buf).unwrap();
+                    assert_ne!(n, 0, "connection closed early");
+                    data.extend_from_slice(&buf[..n]);
+                }
+            };
+
+            let (mut stream, _) = listener.accept().unwrap();
+            read_until(&mut stream, b"\r\n\r\n");
+            let inspect = r#"{"Id":"web","Config":{"Tty":false}}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{inspect}",
+                inspect.len()
+            )
+            .unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().unwrap();
+            read_until(&mut stream, b"\r\n\r\n");
+            stream
+                .write_all(b"HTTP/1.1 101 UPGRADED\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n")
+                .unwrap();
+            stream.write_all(b"\x01\0\0\0\0\0\0\x06hello\n").unwrap();
+            read_until(&mut stream, b"ls\n");
+            stream.write_all(b"\x02\0\0\0\0\0\0\x07got ls\n").unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn records_and_replays_upgraded_connections() {
+        use crate::{conn::TtyChunk, Docker};
+        use futures_util::{AsyncWriteExt, StreamExt};
+
+        async fn attach(docker: Docker) {
+            let container = docker.containers().get("web");
+            let (mut reader, mut writer) = container.attach().await.unwrap().split();
+            assert!(matches!(
+                reader.next().await,
+                Some(Ok(TtyChunk::StdOut(out))) if out == b"hello\n"
+            ));
+            writer.write_all(b"ls\n").await.unwrap();
+            assert!(matches!(
+                reader.next().await,
+                Some(Ok(TtyChunk::StdErr(err))) if err == b"got ls\n"
+            ));
+            assert!(reader.next().await.is_none());
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("attach.json");
+
+        let daemon = Docker::new(format!("tcp://{}", serve_attach())).unwrap();
+        let inner = daemon.transport().clone();
+        attach(Docker::from_transport(Transport::record(inner, &path))).await;
+
+        let cassette: Cassette = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let upgrade = &cassette.interactions[1];
+        assert_eq!(upgrade.status, 101);
+        assert!(matches!(upgrade.stream.first(), Some(Exchange::Read(_))));
+        assert!(upgrade
+            .stream
+            .iter()
+            .any(|e| matches!(e, Exchange::Write(Chunk::Text(input)) if input == "ls\n")));
+
+        attach(Docker::from_transport(Transport::replay(&path).unwrap())).await;
+    }
+
+    #[test]
+    fn encodes_binary_chunks() {
+        let chunk = Chunk::new(&[1, 0, 0, 0, 0, 0, 0, 2, b'h', b'i', 0xff]);
+        assert!(matches!(chunk, Chunk::Binary { .. }));
+        assert_eq!(
+            chunk.to_bytes().unwrap().as_ref(),
+            [1, 0, 0, 0, 0, 0, 0, 2, b'h', b'i', 0xff]
+        );
+        assert!(matches!(Chunk::new(b"text"), Chunk::Text(_)));
+    }
+}
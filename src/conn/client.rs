@@ -0,0 +1,537 @@
+use crate::{
+    conn::{
+        self,
+        compat::Compat,
+        transport::{build_request, get_response_string, stream_json_response, stream_response},
+        Headers, InterceptedRequest, InterceptedResponse, Interceptor, Interceptors, Payload,
+        Transport,
+    },
+    docker::RetryPolicy,
+    trace, Error, Result,
+};
+use futures_util::{
+    io::{AsyncRead, AsyncWrite},
+    stream::Stream,
+    TryFutureExt, TryStreamExt,
+};
+use hyper::{body::Bytes, header, Body, Method, Request, Response, StatusCode};
+use log::trace;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "websocket")]
+use crate::conn::WebSocket;
+
+#[derive(Debug, Clone)]
+pub(crate) struct RequestClient {
+    transport: Transport,
+    validate_fn: Box<ValidateResponseFn>,
+    timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    interceptors: Interceptors,
+}
+
+/// Connection upgraded to a raw stream, used to attach to containers and exec instances.
+pub(crate) type Upgraded = Box<dyn UpgradedIo>;
+
+pub(crate) trait UpgradedIo:
+    tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin
+{
+}
+
+impl<T> UpgradedIo for T where T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin {}
+
+pub(crate) type ValidateResponseFn =
+    fn(Response<Body>) -> Pin<Box<dyn Future<Output = Result<Response<Body>>> + Send + Sync>>;
+
+impl RequestClient {
+    /// Creates a new RequestClient with a specified transport and a function to validate
+    /// each response.
+    pub fn new(transport: Transport, validate_fn: Box<ValidateResponseFn>) -> Self {
+        Self {
+            transport,
+            validate_fn,
+            timeout: None,
+            retry_policy: None,
+            interceptors: Interceptors::default(),
+        }
+    }
+
+    /// Transport used to send requests.
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+
+    /// Sets the maximum time to wait for the response headers.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the policy used to retry failed GET and HEAD requests.
+    pub fn set_retry_policy(&mut self, retry_policy: Option<RetryPolicy>) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Replaces the interceptors called around every request sent by this client.
+    pub fn set_interceptors(&mut self, interceptors: Interceptors) {
+        self.interceptors = interceptors;
+    }
+
+    /// Registers an interceptor called around every request sent by this client.
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    async fn with_timeout<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| Error::Timeout(timeout))?,
+            None => future.await,
+        }
+    }
+
+    fn make_request<B>(
+        &self,
+        method: http::Method,
+        endpoint: &str,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> conn::Result<Request<Body>>
+    where
+        B: Into<Body>,
+    {
+        let uri = self.transport.make_uri(endpoint)?;
+        build_request(method, uri, body, headers)
+    }
+
+    async fn send_request(&self, mut request: Request<Body>) -> Result<Response<Body>> {
+        if self.interceptors.is_empty() {
+            return self.send_request_impl(request).await;
+        }
+
+        let method = request.method().clone();
+        let endpoint = request
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str().to_string())
+            .unwrap_or_default();
+        self.interceptors.before_request(&mut InterceptedRequest {
+            method: &method,
+            endpoint: &endpoint,
+            headers: request.headers_mut(),
+        });
+
+        let start = Instant::now();
+        let result = self.send_request_impl(request).await;
+        self.interceptors.after_response(&InterceptedResponse {
+            method: &method,
+            endpoint: &endpoint,
+            status: match &result {
+                Ok(response) => Some(response.status()),
+                Err(e) => e.status_code(),
+            },
+            error: result.as_ref().err(),
+            latency: start.elapsed(),
+        });
+        result
+    }
+
+    async fn send_request_impl(&self, request: Request<Body>) -> Result<Response<Body>> {
+        self.with_timeout(async {
+            let response = self.transport.request(request).await?;
+            trace::record_status(response.status());
+            (self.validate_fn)(response).await
+        })
+        .await
+    }
+
+    /// Sends a request without a body retrying it according to the retry policy. Only use with
+    /// idempotent methods.
+    async fn send_idempotent_request(
+        &self,
+        method: Method,
+        endpoint: &str,
+    ) -> Result<Response<Body>> {
+        let mut attempt = 0;
+        loop {
+            let req =
+                self.make_request(method.clone(), endpoint, Payload::empty(), Headers::none());
+            let err = match self.send_request(req?).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            match &self.retry_policy {
+                Some(policy) if attempt < policy.max_retries() && err.is_retryable() => {
+                    let backoff = policy.backoff(attempt);
+                    log::debug!("{method} {endpoint} failed: {err}, retrying in {backoff:?}");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                _ => return Err(err),
+            }
+        }
+    }
+
+    async fn read_response_string(&self, response: Response<Body>) -> Result<String> {
+        // the body isn't bounded by the timeout as long-polling endpoints like `/wait` only
+        // finish it once the awaited event happened
+        let body = get_response_string(response).await?;
+        trace::record_bytes(body.len());
+        Ok(body)
+    }
+
+    //####################################################################################################
+    // GET
+    //####################################################################################################
+
+    /// Make a GET request to the `endpoint` and return the response.
+    pub async fn get(&self, endpoint: impl AsRef<str>) -> Result<Response<Body>> {
+        self.send_idempotent_request(Method::GET, endpoint.as_ref())
+            .await
+    }
+
+    /// Make a GET request to the `endpoint` and return the response as a string.
+    pub async fn get_string(&self, endpoint: impl AsRef<str>) -> Result<String> {
+        let response = self.get(endpoint).await?;
+        self.read_response_string(response).await
+    }
+
+    /// Make a GET request to the `endpoint` and return the response as a JSON deserialized object.
+    pub async fn get_json<T: DeserializeOwned>(&self, endpoint: impl AsRef<str>) -> Result<T> {
+        let raw_string = self.get_string(endpoint).await?;
+        trace!("{raw_string}");
+        serde_json::from_str::<T>(&raw_string).map_err(Error::from)
+    }
+
+    async fn get_stream_impl(
+        &self,
+        endpoint: impl AsRef<str>,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + '_> {
+        let response = self.get(endpoint).await?;
+        Ok(trace::count_bytes(
+            stream_response(response).map_err(Error::from),
+        ))
+    }
+
+    /// Make a GET request to the `endpoint` and return a stream of byte chunks.
+    pub fn get_stream<'client>(
+        &'client self,
+        endpoint: impl AsRef<str> + 'client,
+    ) -> impl Stream<Item = Result<Bytes>> + 'client {
+        self.get_stream_impl(endpoint).try_flatten_stream()
+    }
+
+    //####################################################################################################
+    // POST
+    //####################################################################################################
+
+    /// Make a POST request to the `endpoint` and return the response.
+    pub async fn post<B>(
+        &self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> Result<Response<Body>>
+    where
+        B: Into<Body>,
+    {
+        let req = self.make_request(Method::POST, endpoint.as_ref(), body, headers);
+        self.send_request(req?).await
+    }
+
+    /// Make a POST request to the `endpoint` and return the response as a string.
+    pub async fn post_string<B>(
+        &self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> Result<String>
+    where
+        B: Into<Body>,
+    {
+        let response = self.post(endpoint, body, headers).await?;
+        self.read_response_string(response).await
+    }
+
+    /// Make a POST request to the `endpoint` and return the response as a JSON
+    /// deserialized value.
+    pub async fn post_json<B, T>(
+        &self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+        B: Into<Body>,
+    {
+        let raw_string = self.post_string(endpoint, body, headers).await?;
+        trace!("{raw_string}");
+        serde_json::from_str::<T>(&raw_string).map_err(Error::from)
+    }
+
+    async fn post_stream_impl<B>(
+        &self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + '_>
+    where
+        B: Into<Body>,
+    {
+        let response = self.post(endpoint, body, headers).await?;
+        Ok(trace::count_bytes(
+            stream_response(response).map_err(Error::from),
+        ))
+    }
+
+    /// Make a straeming POST request to the `endpoint` and return a
+    /// stream of byte chunks.
+    ///
+    /// Use [`post_into_stream`](RequestClient::post_into_stream) if the endpoint
+    /// returns JSON values.
+    pub fn post_stream<'client, B>(
+        &'client self,
+        endpoint: impl AsRef<str> + 'client,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> impl Stream<Item = Result<Bytes>> + 'client
+    where
+        B: Into<Body> + 'client,
+    {
+        self.post_stream_impl(endpoint, body, headers)
+            .try_flatten_stream()
+    }
+
+    async fn post_json_stream_impl<B>(
+        &self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + '_>
+    where
+        B: Into<Body>,
+    {
+        let response = self.post(endpoint, body, headers).await?;
+        Ok(trace::count_bytes(
+            stream_json_response(response).map_err(Error::from),
+        ))
+    }
+
+    /// Send a streaming post request.
+    fn post_json_stream<'client, B>(
+        &'client self,
+        endpoint: impl AsRef<str> + 'client,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> impl Stream<Item = Result<Bytes>> + 'client
+    where
+        B: Into<Body> + 'client,
+    {
+        self.post_json_stream_impl(endpoint, body, headers)
+            .try_flatten_stream()
+    }
+
+    /// Make a streaming POST request to the `endpoint` and return a stream of
+    /// JSON deserialized chunks.
+    pub fn post_into_stream<'client, B, T>(
+        &'client self,
+        endpoint: impl AsRef<str> + 'client,
+        body: Payload<B>,
+        headers: Option<Headers>,
+    ) -> impl Stream<Item = Result<T>> + 'client
+    where
+        B: Into<Body> + 'client,
+        T: DeserializeOwned,
+    {
+        self.post_json_stream(endpoint, body, headers)
+            .and_then(|chunk| async move {
+                trace!("got chunk {:?}", chunk);
+                let stream = futures_util::stream::iter(
+                    serde_json::Deserializer::from_slice(&chunk)
+                        .into_iter()
+                        .collect::<Vec<_>>(),
+                )
+                .map_err(Error::from);
+
+                Ok(stream)
+            })
+            .try_flatten()
+    }
+
+    pub async fn post_upgrade_stream<B>(
+        self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+    ) -> Result<impl AsyncRead + AsyncWrite>
+    where
+        B: Into<Body>,
+    {
+        self.stream_upgrade(Method::POST, endpoint, body).await
+    }
+
+    //####################################################################################################
+    // PUT
+    //####################################################################################################
+
+    /// Make a PUT request to the `endpoint` and return the response.
+    pub async fn put<B>(
+        &self,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+    ) -> Result<Response<Body>>
+    where
+        B: Into<Body>,
+    {
+        let req = self.make_request(Method::PUT, endpoint.as_ref(), body, Headers::none());
+        self.send_request(req?).await
+    }
+
+    /// Make a PUT request to the `endpoint` and return the response as a string.
+    pub async fn put_string<B>(&self, endpoint: impl AsRef<str>, body: Payload<B>) -> Result<String>
+    where
+        B: Into<Body>,
+    {
+        let response = self.put(endpoint, body).await?;
+        self.read_response_string(response).await
+    }
+
+    //####################################################################################################
+    // DELETE
+    //####################################################################################################
+
+    /// Make a DELETE request to the `endpoint` and return the response.
+    pub async fn delete(&self, endpoint: impl AsRef<str>) -> Result<Response<Body>> {
+        let req = self.make_request(
+            Method::DELETE,
+            endpoint.as_ref(),
+            Payload::empty(),
+            Headers::none(),
+        );
+        self.send_request(req?).await
+    }
+
+    /// Make a DELETE request to the `endpoint` and return the response as a string.
+    pub async fn delete_string(&self, endpoint: impl AsRef<str>) -> Result<String> {
+        let response = self.delete(endpoint).await?;
+        self.read_response_string(response).await
+    }
+
+    /// Make a DELETE request to the `endpoint` and return the response as a JSON
+    /// deserialized object.
+    pub async fn delete_json<T: DeserializeOwned>(&self, endpoint: impl AsRef<str>) -> Result<T> {
+        let raw_string = self.delete_string(endpoint).await?;
+        trace!("{raw_string}");
+        serde_json::from_str::<T>(&raw_string).map_err(Error::from)
+    }
+
+    //####################################################################################################
+    // HEAD
+    //####################################################################################################
+
+    /// Make a HEAD request to the `endpoint` and return the response.
+    pub async fn head(&self, endpoint: impl AsRef<str>) -> Result<Response<Body>> {
+        self.send_idempotent_request(Method::HEAD, endpoint.as_ref())
+            .await
+    }
+
+    //####################################################################################################
+    // STREAM
+    //####################################################################################################
+
+    async fn stream_upgrade<B>(
+        &self,
+        method: Method,
+        endpoint: impl AsRef<str>,
+        body: Payload<B>,
+    ) -> Result<impl AsyncRead + AsyncWrite>
+    where
+        B: Into<Body>,
+    {
+        self.stream_upgrade_tokio(method, endpoint.as_ref(), body)
+            .await
+            .map(Compat::new)
+    }
+
+    /// Makes an HTTP request, upgrading the connection to a TCP
+    /// stream on success.
+    async fn stream_upgrade_tokio<B>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Payload<B>,
+    ) -> Result<Upgraded>
+    where
+        B: Into<Body>,
+    {
+        let mut headers = Headers::default();
+        headers.add(header::CONNECTION.as_str(), "Upgrade");
+        headers.add(header::UPGRADE.as_str(), "tcp");
+
+        #[allow(unused_mut)]
+        let mut response = self.upgrade(method, endpoint, body, headers).await?;
+        #[cfg(feature = "cassette")]
+        if let Some(upgrade) = conn::cassette::Upgrade::take(&mut response) {
+            return Ok(upgrade.await?);
+        }
+        Ok(Box::new(
+            hyper::upgrade::on(response)
+                .await
+                .map_err(conn::Error::from)?,
+        ))
+    }
+
+    /// Makes a GET request, upgrading the connection to the websocket protocol on success.
+    #[cfg(feature = "websocket")]
+    pub async fn get_upgrade_websocket(&self, endpoint: impl AsRef<str>) -> Result<WebSocket> {
+        use tokio_tungstenite::tungstenite::{
+            handshake::{client::generate_key, derive_accept_key},
+            protocol::Role,
+        };
+
+        let key = generate_key();
+        let mut headers = Headers::default();
+        headers.add(header::CONNECTION.as_str(), "Upgrade");
+        headers.add(header::UPGRADE.as_str(), "websocket");
+        headers.add(header::SEC_WEBSOCKET_VERSION.as_str(), "13");
+        headers.add(header::SEC_WEBSOCKET_KEY.as_str(), key.clone());
+
+        let response = self
+            .upgrade(Method::GET, endpoint.as_ref(), Payload::empty(), headers)
+            .await?;
+        let accept = response.headers().get(header::SEC_WEBSOCKET_ACCEPT);
+        if accept.map(|a| a.as_bytes()) != Some(derive_accept_key(key.as_bytes()).as_bytes()) {
+            return Err(Error::from(conn::Error::ConnectionNotUpgraded));
+        }
+
+        let upgraded = hyper::upgrade::on(response)
+            .await
+            .map_err(conn::Error::from)?;
+        Ok(WebSocket::from_raw_socket(upgraded, Role::Client, None).await)
+    }
+
+    /// Sends a request asking to upgrade the connection, returns the response if the server
+    /// switched protocols.
+    async fn upgrade<B>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: Payload<B>,
+        headers: Headers,
+    ) -> Result<Response<Body>>
+    where
+        B: Into<Body>,
+    {
+        let req = self.make_request(method, endpoint, body, Some(headers));
+
+        let response = self.send_request(req?).await?;
+        match response.status() {
+            StatusCode::SWITCHING_PROTOCOLS => Ok(response),
+            _ => Err(Error::from(conn::Error::ConnectionNotUpgraded)),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Hooks invoked around every request sent to the docker daemon.
+
+use crate::{config_file::ConfigFile, Error};
+
+use hyper::{
+    header::{HeaderName, HeaderValue, USER_AGENT},
+    HeaderMap, Method, StatusCode,
+};
+
+use std::{fmt, sync::Arc, time::Duration};
+
+/// A request about to be sent to the docker daemon.
+pub struct InterceptedRequest<'a> {
+    /// HTTP method of the request.
+    pub method: &'a Method,
+    /// Endpoint of the request including the query, for example `/containers/json?all=true`.
+    pub endpoint: &'a str,
+    /// Headers that will be sent with the request.
+    pub headers: &'a mut HeaderMap,
+}
+
+/// Outcome of a request sent to the docker daemon.
+pub struct InterceptedResponse<'a> {
+    /// HTTP method of the request.
+    pub method: &'a Method,
+    /// Endpoint of the request including the query.
+    pub endpoint: &'a str,
+    /// Status code returned by the daemon, `None` if no response was received.
+    pub status: Option<StatusCode>,
+    /// Error the request failed with, if any.
+    pub error: Option<&'a Error>,
+    /// Time elapsed between sending the request and receiving the response headers. For
+    /// streaming and upgraded requests this doesn't include consuming the body or stream.
+    pub latency: Duration,
+}
+
+/// Middleware that sees every request sent by a [`Docker`](crate::Docker) client, including
+/// streaming and upgraded ones.
+///
+/// Interceptors are called in the order they were registered. A request that is retried
+/// according to the [`RetryPolicy`](crate::RetryPolicy) is intercepted on every attempt.
+pub trait Interceptor: Send + Sync {
+    /// Called before the request is sent. Headers can be added or modified here.
+    fn before_request(&self, _request: &mut InterceptedRequest<'_>) {}
+
+    /// Called once the response headers were received or the request failed.
+    fn after_response(&self, _response: &InterceptedResponse<'_>) {}
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors(Vec<Arc<dyn Interceptor>>);
+
+impl Interceptors {
+    pub fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.0.push(interceptor);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn before_request(&self, request: &mut InterceptedRequest<'_>) {
+        self.0.iter().for_each(|i| i.before_request(request));
+    }
+
+    pub fn after_response(&self, response: &InterceptedResponse<'_>) {
+        self.0.iter().for_each(|i| i.after_response(response));
+    }
+}
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interceptors({})", self.0.len())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Interceptor adding a fixed set of headers to every request. Headers already set on a request
+/// are left untouched, just like the docker CLI does with the `HttpHeaders` of its configuration
+/// file.
+pub struct HeaderInterceptor {
+    headers: HeaderMap,
+}
+
+impl HeaderInterceptor {
+    /// Creates an interceptor that doesn't add any headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an interceptor adding the `HttpHeaders` of a docker CLI configuration file.
+    /// Headers with an invalid name or value are skipped.
+    pub fn from_config_file(config: &ConfigFile) -> Self {
+        config
+            .http_headers
+            .iter()
+            .fold(Self::new(), |interceptor, (name, value)| {
+                interceptor.header(name, value)
+            })
+    }
+
+    /// Adds a header sent with every request. An invalid name or value is skipped.
+    pub fn header(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        match (
+            HeaderName::from_bytes(name.as_ref().as_bytes()),
+            HeaderValue::from_str(value.as_ref()),
+        ) {
+            (Ok(name), Ok(value)) => {
+                self.headers.insert(name, value);
+            }
+            _ => log::warn!("skipping invalid header `{}`", name.as_ref()),
+        }
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    pub fn user_agent(self, user_agent: impl AsRef<str>) -> Self {
+        self.header(USER_AGENT.as_str(), user_agent)
+    }
+}
+
+impl Interceptor for HeaderInterceptor {
+    fn before_request(&self, request: &mut InterceptedRequest<'_>) {
+        for (name, value) in &self.headers {
+            if !request.headers.contains_key(name) {
+                request.headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_missing_headers() {
+        let config: ConfigFile = serde_json::from_str(
+            r#"{"HttpHeaders": {"X-Meta": "platform", "User-Agent": "config", "Bad Name": "x"}}"#,
+        )
+        .unwrap();
+        let interceptor = HeaderInterceptor::from_config_file(&config);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("client"));
+        interceptor.before_request(&mut InterceptedRequest {
+            method: &Method::GET,
+            endpoint: "/_ping",
+            headers: &mut headers,
+        });
+
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers["x-meta"], "platform");
+        assert_eq!(headers[USER_AGENT], "client");
+    }
+}
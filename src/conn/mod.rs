@@ -0,0 +1,29 @@
+//! Connection related items
+#[cfg(feature = "cassette")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cassette")))]
+pub mod cassette;
+mod client;
+mod compat;
+mod interceptor;
+mod ssh;
+mod timeout;
+mod transport;
+
+pub(crate) use client::{RequestClient, Upgraded};
+pub(crate) use containers_api::conn::*;
+pub use containers_api::conn::{Error, TtyChunk};
+pub(crate) use interceptor::Interceptors;
+pub use interceptor::{HeaderInterceptor, InterceptedRequest, InterceptedResponse, Interceptor};
+pub use ssh::{SshCommand, SshConnector};
+pub use timeout::TimeoutConnector;
+pub use transport::Transport;
+
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+pub use tokio_tungstenite::tungstenite::{Error as WebSocketError, Message};
+
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+/// Connection upgraded to the websocket protocol. It implements `Stream` for receiving and
+/// `Sink` for sending [`Message`](Message)s.
+pub type WebSocket = tokio_tungstenite::WebSocketStream<hyper::upgrade::Upgraded>;
@@ -0,0 +1,246 @@
+//! Connections to docker hosts reachable over SSH.
+//!
+//! Just like the docker CLI, every connection spawns `ssh` that runs `docker system dial-stdio`
+//! on the remote host. The command proxies its standard input and output to the docker daemon,
+//! so HTTP is spoken directly over the standard streams of the spawned process.
+
+use crate::{Error, Result};
+
+use hyper::{
+    client::connect::{Connected, Connection},
+    service::Service,
+    Uri,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    process::{Child, ChildStdin, ChildStdout},
+};
+
+use std::{
+    ffi::OsString,
+    future::Future,
+    io,
+    pin::Pin,
+    process::Stdio,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// Command spawned for every connection to a docker host reachable over SSH.
+///
+/// The command has to proxy its standard input and output to the docker daemon, like
+/// `docker system dial-stdio` does.
+pub struct SshCommand {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+impl SshCommand {
+    /// Creates a new command that runs `program` without any arguments.
+    pub fn new(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+        }
+    }
+
+    /// Creates the command used by the docker CLI for an `ssh://[user@]host[:port][/socket]` URI,
+    /// that is `ssh [-l user] [-p port] -- host docker [--host unix:///socket] system dial-stdio`.
+    pub fn from_uri(uri: impl AsRef<str>) -> Result<Self> {
+        let url = url::Url::parse(uri.as_ref()).map_err(Error::InvalidUrl)?;
+        if url.scheme() != "ssh" {
+            return Err(Error::UnsupportedScheme(url.scheme().to_string()));
+        }
+        let host = match url.host_str() {
+            Some(host) if !host.is_empty() => host.to_string(),
+            _ => return Err(Error::MissingAuthority),
+        };
+
+        let mut command = Self::new("ssh");
+        if !url.username().is_empty() {
+            command = command.arg("-l").arg(url.username());
+        }
+        if let Some(port) = url.port() {
+            command = command.arg("-p").arg(port.to_string());
+        }
+        command = command.arg("--").arg(host).arg("docker");
+        if !matches!(url.path(), "" | "/") {
+            command = command.arg("--host").arg(format!("unix://{}", url.path()));
+        }
+
+        Ok(command.args(["system", "dial-stdio"]))
+    }
+
+    /// Adds an argument passed to the program.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds multiple arguments passed to the program.
+    pub fn args<A>(mut self, args: impl IntoIterator<Item = A>) -> Self
+    where
+        A: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Program that is spawned.
+    pub fn program(&self) -> &OsString {
+        &self.program
+    }
+
+    /// Arguments passed to the program.
+    pub fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    fn spawn(&self) -> io::Result<SshStream> {
+        log::trace!("spawning {:?} {:?}", self.program, self.args);
+        let mut child = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::other("failed to open stdin of ssh command"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("failed to open stdout of ssh command"))?;
+
+        Ok(SshStream {
+            _child: child,
+            stdin,
+            stdout,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Connector spawning a [`SshCommand`](SshCommand) for every new connection.
+pub struct SshConnector {
+    command: Arc<SshCommand>,
+}
+
+impl SshConnector {
+    pub fn new(command: SshCommand) -> Self {
+        Self {
+            command: Arc::new(command),
+        }
+    }
+}
+
+impl Service<Uri> for SshConnector {
+    type Response = SshStream;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<SshStream>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: Uri) -> Self::Future {
+        let command = self.command.clone();
+        Box::pin(async move { command.spawn() })
+    }
+}
+
+/// Standard streams of a spawned [`SshCommand`](SshCommand). The process is killed once the
+/// stream is dropped.
+pub struct SshStream {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl Connection for SshStream {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl AsyncRead for SshStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SshStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().stdin).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdin).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &SshCommand) -> Vec<&str> {
+        command
+            .get_args()
+            .iter()
+            .map(|arg| arg.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn creates_ssh_command_from_uri() {
+        let command = SshCommand::from_uri("ssh://host").unwrap();
+        assert_eq!(command.program(), "ssh");
+        assert_eq!(
+            args(&command),
+            ["--", "host", "docker", "system", "dial-stdio"]
+        );
+
+        let command = SshCommand::from_uri("ssh://me@10.0.0.1:2222/run/docker.sock").unwrap();
+        assert_eq!(
+            args(&command),
+            [
+                "-l",
+                "me",
+                "-p",
+                "2222",
+                "--",
+                "10.0.0.1",
+                "docker",
+                "--host",
+                "unix:///run/docker.sock",
+                "system",
+                "dial-stdio"
+            ]
+        );
+
+        assert!(matches!(
+            SshCommand::from_uri("ssh://").unwrap_err(),
+            Error::MissingAuthority
+        ));
+        assert!(matches!(
+            SshCommand::from_uri("tcp://host").unwrap_err(),
+            Error::UnsupportedScheme(_)
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+//! Transports for communicating with the docker daemon
+
+use crate::conn::{Error, Headers, Payload, Result, SshConnector, TimeoutConnector};
+
+use futures_util::{
+    stream::{self, Stream},
+    StreamExt,
+};
+use hyper::{
+    body::Bytes,
+    client::{Client, HttpConnector},
+    header, Body, Method, Request, Response,
+};
+#[cfg(feature = "tls")]
+use hyper_openssl::HttpsConnector;
+#[cfg(unix)]
+use hyperlocal::UnixConnector;
+#[cfg(unix)]
+use hyperlocal::Uri as DomainUri;
+use url::Url;
+
+use std::{iter::IntoIterator, path::PathBuf};
+
+#[cfg(feature = "cassette")]
+use crate::conn::cassette::{CassettePlayer, CassetteRecorder};
+#[cfg(feature = "cassette")]
+use std::path::Path;
+
+/// Transports are types which define supported means of communication.
+#[derive(Clone, Debug)]
+pub enum Transport {
+    /// A network tcp interface
+    Tcp {
+        client: Client<TimeoutConnector<HttpConnector>>,
+        host: Url,
+    },
+    /// TCP/TLS
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    EncryptedTcp {
+        client: Client<TimeoutConnector<HttpsConnector<HttpConnector>>>,
+        host: Url,
+    },
+    /// A Unix domain socket
+    #[cfg(unix)]
+    Unix {
+        client: Client<TimeoutConnector<UnixConnector>>,
+        path: PathBuf,
+    },
+    /// Standard streams of a command, usually `ssh ... docker system dial-stdio`, spawned for
+    /// every connection
+    Ssh {
+        client: Client<TimeoutConnector<SshConnector>>,
+        host: String,
+    },
+    /// In-memory daemon used for testing
+    #[cfg(feature = "mock")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+    Mock { daemon: crate::mock::MockDocker },
+    /// Another transport whose exchanges are recorded to a cassette file
+    #[cfg(feature = "cassette")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cassette")))]
+    Record {
+        inner: Box<Transport>,
+        recorder: CassetteRecorder,
+    },
+    /// Exchanges replayed from a cassette file
+    #[cfg(feature = "cassette")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cassette")))]
+    Replay { player: CassettePlayer },
+}
+
+impl Transport {
+    pub fn remote_addr(&self) -> &str {
+        match &self {
+            Self::Tcp { ref host, .. } => host.as_ref(),
+            #[cfg(feature = "tls")]
+            Self::EncryptedTcp { ref host, .. } => host.as_ref(),
+            #[cfg(unix)]
+            Self::Unix { ref path, .. } => path.to_str().unwrap_or_default(),
+            Self::Ssh { ref host, .. } => host,
+            #[cfg(feature = "mock")]
+            Self::Mock { .. } => "mock",
+            #[cfg(feature = "cassette")]
+            Self::Record { inner, .. } => inner.remote_addr(),
+            #[cfg(feature = "cassette")]
+            Self::Replay { .. } => "cassette",
+        }
+    }
+
+    pub fn make_uri(&self, ep: &str) -> Result<hyper::Uri> {
+        match self {
+            Transport::Tcp { host, .. } => format!("{host}{ep}").parse().map_err(Error::InvalidUri),
+            #[cfg(feature = "tls")]
+            Transport::EncryptedTcp { host, .. } => {
+                format!("{host}{ep}").parse().map_err(Error::InvalidUri)
+            }
+            #[cfg(unix)]
+            Transport::Unix { path, .. } => Ok(DomainUri::new(path, ep).into()),
+            // the connector ignores the address, it only has to be a valid http URI
+            Transport::Ssh { .. } => format!("http://docker{ep}")
+                .parse()
+                .map_err(Error::InvalidUri),
+            #[cfg(feature = "mock")]
+            Transport::Mock { .. } => format!("http://mock{ep}")
+                .parse()
+                .map_err(Error::InvalidUri),
+            #[cfg(feature = "cassette")]
+            Transport::Record { inner, .. } => inner.make_uri(ep),
+            #[cfg(feature = "cassette")]
+            Transport::Replay { .. } => format!("http://cassette{ep}")
+                .parse()
+                .map_err(Error::InvalidUri),
+        }
+    }
+
+    #[cfg(feature = "cassette")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cassette")))]
+    /// Wraps the `inner` transport recording all of its exchanges to a cassette file at `path`.
+    /// The file is rewritten after every response, streamed responses are stored once their
+    /// body is fully read or dropped. See the [`cassette`](crate::conn::cassette) module.
+    pub fn record(inner: Transport, path: impl Into<PathBuf>) -> Self {
+        Transport::Record {
+            inner: Box::new(inner),
+            recorder: CassetteRecorder::new(path.into()),
+        }
+    }
+
+    #[cfg(feature = "cassette")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cassette")))]
+    /// Creates a transport answering requests with the interactions recorded in the cassette
+    /// file at `path` without connecting to a daemon. Each recorded interaction is replayed once
+    /// for a request with the same method and endpoint.
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        CassettePlayer::load(path.as_ref()).map(|player| Transport::Replay { player })
+    }
+
+    /// Send the given request and return a Future of the response.
+    pub async fn request(&self, req: Request<Body>) -> Result<Response<Body>> {
+        log::trace!("sending request {} {}", req.method(), req.uri());
+        match self {
+            Transport::Tcp { ref client, .. } => client.request(req),
+            #[cfg(feature = "tls")]
+            Transport::EncryptedTcp { ref client, .. } => client.request(req),
+            #[cfg(unix)]
+            Transport::Unix { ref client, .. } => client.request(req),
+            Transport::Ssh { ref client, .. } => client.request(req),
+            #[cfg(feature = "mock")]
+            Transport::Mock { ref daemon } => return Ok(daemon.handle(req).await),
+            #[cfg(feature = "cassette")]
+            Transport::Record {
+                ref inner,
+                ref recorder,
+            } => return Box::pin(recorder.record(inner, req)).await,
+            #[cfg(feature = "cassette")]
+            Transport::Replay { ref player } => return player.replay(&req),
+        }
+        .await
+        .map_err(Error::from)
+    }
+}
+
+async fn body_to_string(body: Body) -> Result<String> {
+    let bytes = hyper::body::to_bytes(body).await?;
+    String::from_utf8(bytes.to_vec()).map_err(Error::from)
+}
+
+/// Builds an HTTP request.
+pub(crate) fn build_request<B>(
+    method: Method,
+    uri: hyper::Uri,
+    body: Payload<B>,
+    headers: Option<Headers>,
+) -> Result<Request<Body>>
+where
+    B: Into<Body>,
+{
+    let builder = hyper::http::request::Builder::new();
+    let req = builder.method(method).uri(&uri);
+    let mut req = req.header(header::HOST, "");
+
+    if let Some(h) = headers {
+        for (k, v) in h.into_iter() {
+            req = req.header(k, v);
+        }
+    }
+
+    // early return
+    if body.is_none() {
+        return Ok(req.body(Body::empty())?);
+    }
+
+    let mime = body.mime_type();
+    if let Some(c) = mime {
+        req = req.header(header::CONTENT_TYPE, &c.to_string());
+    }
+
+    // it's ok to unwrap, we check that the body is not none
+    req.body(body.into_inner().unwrap().into())
+        .map_err(Error::from)
+}
+
+pub(crate) async fn get_response_string(response: Response<Body>) -> Result<String> {
+    body_to_string(response.into_body()).await
+}
+
+pub(crate) fn stream_response(response: Response<Body>) -> impl Stream<Item = Result<Bytes>> {
+    stream_body(response.into_body())
+}
+
+pub(crate) fn stream_json_response(response: Response<Body>) -> impl Stream<Item = Result<Bytes>> {
+    stream_json_body(response.into_body())
+}
+
+fn stream_body(body: Body) -> impl Stream<Item = Result<Bytes>> {
+    async fn unfold(mut body: Body) -> Option<(Result<Bytes>, Body)> {
+        body.next()
+            .await
+            .map(|chunk| (chunk.map_err(Error::from), body))
+    }
+
+    stream::unfold(body, unfold)
+}
+
+static JSON_WHITESPACE: &[u8] = b"\r\n";
+
+fn stream_json_body(body: Body) -> impl Stream<Item = Result<Bytes>> {
+    async fn unfold(mut body: Body) -> Option<(Result<Bytes>, Body)> {
+        let mut chunk = Vec::new();
+        while let Some(chnk) = body.next().await {
+            match chnk {
+                Ok(chnk) => {
+                    chunk.extend(chnk.to_vec());
+                    if chnk.ends_with(JSON_WHITESPACE) {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    return Some((Err(Error::from(e)), body));
+                }
+            }
+        }
+
+        if chunk.is_empty() {
+            return None;
+        }
+
+        Some((Ok(Bytes::from(chunk)), body))
+    }
+
+    stream::unfold(body, unfold)
+}
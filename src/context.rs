@@ -0,0 +1,358 @@
+//! Docker CLI contexts.
+//!
+//! Contexts are created with `docker context create` and stored by the docker CLI in the
+//! configuration directory (`$DOCKER_CONFIG` or `~/.docker`). Each context describes how to
+//! connect to a docker host and optionally holds TLS material used for the connection.
+
+use crate::{
+    config_file::{self, ConfigFile},
+    ApiVersion, Docker, Error, Result,
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the context that is used when no other context is selected. It connects to the host
+/// specified by the `DOCKER_HOST` environment variable or the default socket.
+pub const DEFAULT_CONTEXT: &str = "default";
+
+const DOCKER_CONTEXT_ENV: &str = "DOCKER_CONTEXT";
+const DOCKER_ENDPOINT: &str = "docker";
+const META_FILE_NAME: &str = "meta.json";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContextMeta {
+    name: String,
+    #[serde(default)]
+    metadata: Option<ContextMetadata>,
+    #[serde(default)]
+    endpoints: HashMap<String, EndpointMeta>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContextMetadata {
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EndpointMeta {
+    host: Option<String>,
+    #[serde(rename = "SkipTLSVerify")]
+    #[serde(default)]
+    skip_tls_verify: bool,
+}
+
+#[derive(Clone, Debug)]
+/// A docker CLI context describing how to connect to a docker host.
+pub struct Context {
+    name: String,
+    description: Option<String>,
+    host: Option<String>,
+    skip_tls_verify: bool,
+    tls_path: Option<PathBuf>,
+}
+
+impl Context {
+    fn default_context() -> Self {
+        Self {
+            name: DEFAULT_CONTEXT.into(),
+            description: None,
+            host: None,
+            skip_tls_verify: false,
+            tls_path: None,
+        }
+    }
+
+    /// Name of this context.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Description of this context.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// URI of the docker host this context points to. `None` for the default context.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Whether the certificate of the docker host should not be verified.
+    pub fn skip_tls_verify(&self) -> bool {
+        self.skip_tls_verify
+    }
+
+    /// Directory containing the `ca.pem`, `cert.pem` and `key.pem` files of this context if it
+    /// has any TLS material.
+    pub fn tls_path(&self) -> Option<&Path> {
+        self.tls_path.as_deref()
+    }
+
+    /// Creates a new Docker instance connected to the host of this context.
+    ///
+    /// Connecting to a context with TLS material requires the `tls` feature to be enabled.
+    pub fn docker(&self) -> Result<Docker> {
+        self.docker_impl(None)
+    }
+
+    /// Same as [`Context::docker`](Context::docker) but the API version can be explicitly
+    /// specified.
+    pub fn docker_versioned(&self, version: impl Into<ApiVersion>) -> Result<Docker> {
+        self.docker_impl(Some(version.into()))
+    }
+
+    fn docker_impl(&self, version: Option<ApiVersion>) -> Result<Docker> {
+        match (&self.host, &self.tls_path) {
+            (None, _) => Docker::from_host_env(crate::docker::env_var, version.into()),
+            // like the docker CLI, use TLS without verification when skipping verification of a
+            // context without TLS material
+            (Some(host), tls_path) if tls_path.is_some() || self.skip_tls_verify => {
+                Docker::new_tls_uri(
+                    host,
+                    version.into(),
+                    tls_path.as_deref(),
+                    !self.skip_tls_verify,
+                )
+            }
+            (Some(host), _) => match version {
+                Some(version) => Docker::new_versioned(host, version),
+                None => Docker::new(host),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Interface for reading contexts stored by the docker CLI.
+pub struct ContextStore {
+    config_dir: PathBuf,
+}
+
+impl ContextStore {
+    /// Creates a new store reading contexts from the docker configuration directory
+    /// `config_dir`.
+    pub fn new(config_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            config_dir: config_dir.into(),
+        }
+    }
+
+    /// Creates a new store reading contexts from the default docker configuration directory
+    /// (`$DOCKER_CONFIG` or `~/.docker`).
+    pub fn from_env() -> Result<Self> {
+        config_file::config_dir()
+            .map(Self::new)
+            .ok_or_else(|| Error::StringError("failed to find docker config directory".into()))
+    }
+
+    /// Returns the name of the currently selected context. The `DOCKER_CONTEXT` environment
+    /// variable takes precedence over `currentContext` from the configuration file.
+    pub fn current(&self) -> Result<String> {
+        self.current_impl(crate::docker::env_var)
+    }
+
+    pub(crate) fn current_impl(&self, var: impl Fn(&str) -> Option<String>) -> Result<String> {
+        if let Some(name) = var(DOCKER_CONTEXT_ENV) {
+            return Ok(name);
+        }
+        ConfigFile::load_from_dir(&self.config_dir).map(|config| {
+            config
+                .current_context
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| DEFAULT_CONTEXT.into())
+        })
+    }
+
+    /// Returns the currently selected context.
+    pub fn current_context(&self) -> Result<Context> {
+        self.current().and_then(|name| self.get(&name))
+    }
+
+    /// Lists all available contexts including the default one. Like `docker context ls`,
+    /// contexts that can't be read are skipped with a warning.
+    pub fn list(&self) -> Result<Vec<Context>> {
+        let mut contexts = vec![Context::default_context()];
+
+        let entries = match std::fs::read_dir(self.meta_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(contexts),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if !entry.path().join(META_FILE_NAME).exists() {
+                continue;
+            }
+            match self.read_context(&entry.file_name().to_string_lossy()) {
+                Ok(context) => contexts.push(context),
+                Err(e) => log::warn!("skipping context {}: {e}", entry.path().display()),
+            }
+        }
+        contexts[1..].sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(contexts)
+    }
+
+    /// Returns the context with the given `name`.
+    pub fn get(&self, name: &str) -> Result<Context> {
+        if name == DEFAULT_CONTEXT {
+            return Ok(Context::default_context());
+        }
+        match self.read_context(&context_id(name)) {
+            Err(Error::IO(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(Error::ContextNotFound(name.into()))
+            }
+            context => context,
+        }
+    }
+
+    fn meta_dir(&self) -> PathBuf {
+        self.config_dir.join("contexts").join("meta")
+    }
+
+    fn read_context(&self, id: &str) -> Result<Context> {
+        let data = std::fs::read(self.meta_dir().join(id).join(META_FILE_NAME))?;
+        let mut meta: ContextMeta = serde_json::from_slice(&data)?;
+        let endpoint = meta.endpoints.remove(DOCKER_ENDPOINT);
+
+        let tls_path = self
+            .config_dir
+            .join("contexts")
+            .join("tls")
+            .join(id)
+            .join(DOCKER_ENDPOINT);
+
+        Ok(Context {
+            name: meta.name,
+            description: meta.metadata.and_then(|m| m.description),
+            host: endpoint
+                .as_ref()
+                .and_then(|e| e.host.clone())
+                .filter(|host| !host.is_empty()),
+            skip_tls_verify: endpoint.map(|e| e.skip_tls_verify).unwrap_or_default(),
+            tls_path: tls_path.exists().then_some(tls_path),
+        })
+    }
+}
+
+/// Returns the id of the context `name`, the docker CLI stores every context in a directory
+/// named after the SHA-256 digest of its name.
+fn context_id(name: &str) -> String {
+    format!("{:x}", Sha256::digest(name.as_bytes()))
+}
+
+impl Docker {
+    /// Creates a new Docker instance connected to the host of the docker CLI context `name`.
+    pub fn from_context(name: impl AsRef<str>) -> Result<Self> {
+        ContextStore::from_env()?.get(name.as_ref())?.docker()
+    }
+
+    /// Creates a new Docker instance connected to the host of the currently selected docker CLI
+    /// context. See [`ContextStore::current`](ContextStore::current).
+    pub fn from_current_context() -> Result<Self> {
+        ContextStore::from_env()?.current_context()?.docker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_context(dir: &Path, id: &str, meta: &str) {
+        let meta_dir = dir.join("contexts").join("meta").join(id);
+        std::fs::create_dir_all(&meta_dir).unwrap();
+        std::fs::write(meta_dir.join(META_FILE_NAME), meta).unwrap();
+    }
+
+    #[test]
+    fn reads_contexts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContextStore::new(dir.path());
+        let no_env = |_: &str| None;
+
+        assert_eq!(store.current_impl(no_env).unwrap(), DEFAULT_CONTEXT);
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        std::fs::write(
+            dir.path().join("config.json"),
+            r#"{"auths":{},"currentContext":"remote"}"#,
+        )
+        .unwrap();
+        write_context(
+            dir.path(),
+            &context_id("remote"),
+            r#"{"Name":"remote","Metadata":{"Description":"build host"},"Endpoints":{"docker":{"Host":"tcp://10.0.0.1:2376","SkipTLSVerify":true}}}"#,
+        );
+        write_context(
+            dir.path(),
+            &context_id("local"),
+            r#"{"Name":"local","Metadata":{},"Endpoints":{"docker":{"Host":"unix:///var/run/docker.sock","SkipTLSVerify":false}}}"#,
+        );
+        std::fs::create_dir_all(
+            dir.path()
+                .join("contexts")
+                .join("tls")
+                .join(context_id("remote"))
+                .join("docker"),
+        )
+        .unwrap();
+
+        assert_eq!(store.current_impl(no_env).unwrap(), "remote");
+        assert_eq!(
+            store
+                .current_impl(|key| (key == DOCKER_CONTEXT_ENV).then(|| "local".to_string()))
+                .unwrap(),
+            "local"
+        );
+
+        let names = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, [DEFAULT_CONTEXT, "local", "remote"]);
+
+        let remote = store.get("remote").unwrap();
+        assert_eq!(remote.description(), Some("build host"));
+        assert_eq!(remote.host(), Some("tcp://10.0.0.1:2376"));
+        assert!(remote.skip_tls_verify());
+        assert!(remote.tls_path().is_some());
+
+        let local = store.get("local").unwrap();
+        assert_eq!(local.host(), Some("unix:///var/run/docker.sock"));
+        assert!(local.tls_path().is_none());
+        #[cfg(unix)]
+        local.docker().unwrap();
+
+        match store.get("missing").unwrap_err() {
+            Error::ContextNotFound(name) if name == "missing" => {}
+            e => panic!("Expected Error::ContextNotFound, got {e}"),
+        }
+
+        // skipping verification without TLS material still connects with TLS
+        write_context(
+            dir.path(),
+            &context_id("insecure"),
+            r#"{"Name":"insecure","Endpoints":{"docker":{"Host":"tcp://10.0.0.2:2376","SkipTLSVerify":true}}}"#,
+        );
+        let insecure = store.get("insecure").unwrap();
+        assert!(insecure.tls_path().is_none());
+        #[cfg(feature = "tls")]
+        insecure.docker().unwrap();
+        #[cfg(not(feature = "tls"))]
+        assert!(matches!(insecure.docker(), Err(Error::TlsNotEnabled)));
+
+        // an unreadable context doesn't break looking up or listing other contexts
+        write_context(dir.path(), &context_id("broken"), "{");
+        assert_eq!(store.list().unwrap().len(), 4);
+        assert!(store.get("broken").is_err());
+        assert_eq!(store.get("remote").unwrap().name(), "remote");
+    }
+}
@@ -0,0 +1,351 @@
+//! Resolution of registry credentials stored by the docker CLI.
+//!
+//! Credentials are looked up the same way the docker CLI does it. A credential helper configured
+//! for the registry in `credHelpers` takes precedence over the `credsStore` helper. The
+//! credentials stored in `auths` are only used when no helper applies, a helper without
+//! credentials for the registry returns none. Helpers are executed as
+//! `docker-credential-<helper> get` with the registry address written to their standard input.
+
+use crate::{
+    config_file::{AuthConfig, ConfigFile},
+    opts::RegistryAuth,
+    Error, Result,
+};
+
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+/// Host of the default registry used for references without a registry host.
+pub const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Address under which the docker CLI stores the credentials of the default registry.
+const DEFAULT_REGISTRY_ADDRESS: &str = "https://index.docker.io/v1/";
+
+const HELPER_PREFIX: &str = "docker-credential-";
+
+/// Message returned by credential helpers when no credentials are stored for a registry.
+const CREDENTIALS_NOT_FOUND: &str = "credentials not found";
+
+/// Returns the registry host of an image reference, for example `ghcr.io` for
+/// `ghcr.io/owner/app:1.0` or [`DEFAULT_REGISTRY`](DEFAULT_REGISTRY) for `ubuntu:22.04`.
+pub fn registry_host(reference: &str) -> &str {
+    match reference.split_once('/') {
+        Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => normalize_host(host),
+        _ => DEFAULT_REGISTRY,
+    }
+}
+
+/// Strips the scheme and path of a registry address and maps the aliases of the default
+/// registry to [`DEFAULT_REGISTRY`](DEFAULT_REGISTRY).
+fn normalize_host(address: &str) -> &str {
+    let host = address
+        .strip_prefix("https://")
+        .or_else(|| address.strip_prefix("http://"))
+        .unwrap_or(address);
+    let host = host.split('/').next().unwrap_or_default();
+    match host {
+        "index.docker.io" | "registry-1.docker.io" => DEFAULT_REGISTRY,
+        host => host,
+    }
+}
+
+/// Returns the address passed to credential helpers for a registry host.
+fn server_address(host: &str) -> &str {
+    if host == DEFAULT_REGISTRY {
+        DEFAULT_REGISTRY_ADDRESS
+    } else {
+        host
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredentials {
+    username: String,
+    secret: String,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Registry credentials of a docker CLI configuration file.
+pub struct Credentials {
+    config: ConfigFile,
+    helper_dir: Option<PathBuf>,
+}
+
+impl Credentials {
+    /// Creates a store resolving credentials with the given configuration. A default
+    /// configuration never returns any credentials.
+    pub fn new(config: ConfigFile) -> Self {
+        Self {
+            config,
+            helper_dir: None,
+        }
+    }
+
+    /// Creates a store using the configuration file of the docker CLI, see
+    /// [`ConfigFile::load`](ConfigFile::load).
+    pub fn load() -> Result<Self> {
+        ConfigFile::load().map(Self::new)
+    }
+
+    /// Looks up credential helpers in `dir` instead of the directories in `PATH`.
+    pub fn helper_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.helper_dir = Some(dir.into());
+        self
+    }
+
+    /// Returns the credentials for the registry of an image reference, see
+    /// [`registry_host`](registry_host).
+    pub async fn for_image(&self, reference: &str) -> Result<Option<RegistryAuth>> {
+        self.get(registry_host(reference)).await
+    }
+
+    /// Returns the credentials for a registry, `registry` may be a host like `ghcr.io` or an
+    /// address like `https://index.docker.io/v1/`. `None` is returned if no credentials are
+    /// stored for it, with a credential helper configured only the helper is asked.
+    pub async fn get(&self, registry: &str) -> Result<Option<RegistryAuth>> {
+        let host = normalize_host(registry);
+        let helper = self
+            .config
+            .cred_helpers
+            .iter()
+            .find(|(registry, _)| normalize_host(registry) == host)
+            .map(|(_, helper)| helper)
+            .or(self.config.creds_store.as_ref());
+
+        if let Some(helper) = helper {
+            return self.run_helper(helper, server_address(host)).await;
+        }
+
+        self.config
+            .auths
+            .iter()
+            .find(|(registry, _)| normalize_host(registry) == host)
+            .map(|(_, config)| auth_from_config(config, server_address(host)))
+            .transpose()
+            .map(Option::flatten)
+    }
+
+    async fn run_helper(&self, helper: &str, address: &str) -> Result<Option<RegistryAuth>> {
+        let program = format!("{HELPER_PREFIX}{helper}");
+        let program = match &self.helper_dir {
+            Some(dir) => dir.join(program),
+            None => Path::new(&program).to_path_buf(),
+        };
+        let err = |message: String| Error::CredentialHelper {
+            helper: program.display().to_string(),
+            message,
+        };
+
+        log::trace!("running {} get for {address}", program.display());
+        let mut child = tokio::process::Command::new(&program)
+            .arg("get")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| err(e.to_string()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // a helper exiting without reading its input closes the pipe early, its output and
+            // exit status are still reported below
+            match stdin.write_all(address.as_bytes()).await {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+                    return Err(err(e.to_string()))
+                }
+                _ => {}
+            }
+        }
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| err(e.to_string()))?;
+
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stdout.contains(CREDENTIALS_NOT_FOUND) || stderr.contains(CREDENTIALS_NOT_FOUND) {
+                return Ok(None);
+            }
+            let message = if stderr.trim().is_empty() {
+                stdout
+            } else {
+                stderr
+            };
+            return Err(err(message.trim().to_string()));
+        }
+
+        let credentials: HelperCredentials =
+            serde_json::from_slice(&output.stdout).map_err(|e| err(e.to_string()))?;
+        if credentials.username == "<token>" {
+            Ok(Some(RegistryAuth::token(credentials.secret)))
+        } else {
+            Ok(Some(
+                RegistryAuth::builder()
+                    .username(credentials.username)
+                    .password(credentials.secret)
+                    .server_address(address)
+                    .build(),
+            ))
+        }
+    }
+}
+
+fn auth_from_config(config: &AuthConfig, address: &str) -> Result<Option<RegistryAuth>> {
+    if let Some(token) = config.identity_token.as_ref().filter(|t| !t.is_empty()) {
+        return Ok(Some(RegistryAuth::token(token)));
+    }
+    let auth = match config.auth.as_deref().filter(|a| !a.is_empty()) {
+        Some(auth) => auth,
+        None => return Ok(None),
+    };
+
+    let invalid = || Error::StringError(format!("invalid auth stored for registry `{address}`"));
+    let decoded = base64::decode(auth.trim())
+        .ok()
+        .and_then(|auth| String::from_utf8(auth).ok())
+        .ok_or_else(invalid)?;
+    let (username, password) = decoded.split_once(':').ok_or_else(invalid)?;
+
+    let mut builder = RegistryAuth::builder()
+        .username(username)
+        .password(password)
+        .server_address(address);
+    if let Some(email) = &config.email {
+        builder = builder.email(email);
+    }
+    Ok(Some(builder.build()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials(config: &str) -> Credentials {
+        Credentials::new(serde_json::from_str(config).unwrap())
+    }
+
+    fn password(auth: Option<RegistryAuth>) -> (String, String, Option<String>) {
+        match auth {
+            Some(RegistryAuth::Password {
+                username,
+                password,
+                server_address,
+                ..
+            }) => (username, password, server_address),
+            other => panic!("expected password auth, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_registry_hosts() {
+        assert_eq!(registry_host("ubuntu"), "docker.io");
+        assert_eq!(registry_host("library/ubuntu:22.04"), "docker.io");
+        assert_eq!(registry_host("docker.io/library/ubuntu"), "docker.io");
+        assert_eq!(registry_host("index.docker.io/library/ubuntu"), "docker.io");
+        assert_eq!(registry_host("ghcr.io/owner/app:1.0"), "ghcr.io");
+        assert_eq!(registry_host("localhost:5000/app"), "localhost:5000");
+        assert_eq!(registry_host("localhost/app"), "localhost");
+    }
+
+    #[tokio::test]
+    async fn resolves_stored_auths() {
+        let credentials = credentials(
+            r#"{"auths": {
+                "https://index.docker.io/v1/": {"auth": "dXNlcjpwYXNz"},
+                "ghcr.io": {"identitytoken": "token"},
+                "quay.io": {"auth": ""}
+            }}"#,
+        );
+
+        assert_eq!(
+            password(credentials.for_image("ubuntu").await.unwrap()),
+            (
+                "user".into(),
+                "pass".into(),
+                Some(DEFAULT_REGISTRY_ADDRESS.into())
+            )
+        );
+        assert!(matches!(
+            credentials.for_image("ghcr.io/owner/app").await.unwrap(),
+            Some(RegistryAuth::Token { identity_token }) if identity_token == "token"
+        ));
+        assert!(credentials
+            .for_image("quay.io/owner/app")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(credentials.get("gcr.io").await.unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn runs_credential_helpers() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let helper = |name: &str, script: &str| {
+            let path = dir.path().join(format!("{HELPER_PREFIX}{name}"));
+            std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        helper(
+            "store",
+            r#"read server
+case "$server" in
+  https://index.docker.io/v1/) echo '{"ServerURL":"'$server'","Username":"hub","Secret":"hubpass"}' ;;
+  *) echo "credentials not found in native keychain"; exit 1 ;;
+esac"#,
+        );
+        helper(
+            "ecr",
+            r#"read server
+echo '{"ServerURL":"'$server'","Username":"<token>","Secret":"ecrtoken"}'"#,
+        );
+        helper("broken", "echo 'helper exploded' >&2; exit 1");
+
+        let credentials = credentials(
+            r#"{
+                "auths": {"quay.io": {"auth": "cXVheTpwYXNz"}},
+                "credsStore": "store",
+                "credHelpers": {
+                    "123.dkr.ecr.us-east-1.amazonaws.com": "ecr",
+                    "broken.io": "broken"
+                }
+            }"#,
+        )
+        .helper_dir(dir.path());
+
+        assert_eq!(
+            password(credentials.for_image("ubuntu").await.unwrap()),
+            (
+                "hub".into(),
+                "hubpass".into(),
+                Some(DEFAULT_REGISTRY_ADDRESS.into())
+            )
+        );
+        assert!(matches!(
+            credentials
+                .for_image("123.dkr.ecr.us-east-1.amazonaws.com/app")
+                .await
+                .unwrap(),
+            Some(RegistryAuth::Token { identity_token }) if identity_token == "ecrtoken"
+        ));
+        // like the docker CLI, the auths aren't used once the store is configured
+        assert!(credentials
+            .for_image("quay.io/app")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(credentials.get("ghcr.io").await.unwrap().is_none());
+        assert!(matches!(
+            credentials.get("broken.io").await,
+            Err(Error::CredentialHelper { message, .. }) if message == "helper exploded"
+        ));
+    }
+}
@@ -2,11 +2,16 @@
 //!
 //! API Reference: <https://docs.docker.com/engine/api/v1.42/>
 use crate::{
-    conn::{get_http_connector, Headers, Payload, Transport},
+    config_file,
+    conn::{
+        get_http_connector, Headers, Interceptor, Interceptors, Payload, RequestClient, SshCommand,
+        SshConnector, TimeoutConnector, Transport,
+    },
+    context::{ContextStore, DEFAULT_CONTEXT},
+    credentials::Credentials,
     errors::{Error, Result},
-    ApiVersion, Containers, Images, Networks, Volumes,
+    trace, ApiVersion, Containers, Images, Networks, Volumes,
 };
-use containers_api::conn::RequestClient;
 
 #[cfg(feature = "swarm")]
 use crate::{Configs, Nodes, Plugins, Secrets, Services, Swarm, Tasks};
@@ -19,21 +24,147 @@ use crate::conn::get_unix_connector;
 use futures_util::{
     io::{AsyncRead, AsyncWrite},
     stream::Stream,
+    TryFutureExt,
 };
-use hyper::{body::Bytes, Body, Client, Response};
+use hyper::{body::Bytes, Body, Client, Method, Response};
 use serde::de::DeserializeOwned;
 use std::future::Future;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, PoisonError, RwLock,
+};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+const DOCKER_HOST_ENV: &str = "DOCKER_HOST";
+const DOCKER_TLS_VERIFY_ENV: &str = "DOCKER_TLS_VERIFY";
+const DOCKER_CERT_PATH_ENV: &str = "DOCKER_CERT_PATH";
+const DOCKER_API_VERSION_ENV: &str = "DOCKER_API_VERSION";
+
+/// API version reported by daemons that don't send the `Api-Version` header on `/_ping`.
+const FALLBACK_API_VERSION: ApiVersion = ApiVersion::new(1, Some(24), None);
+
+/// Minimum API versions of endpoints added after v1.24, the oldest version supported by the
+/// daemon. `*` matches a single path segment and `**` any number of segments.
+const ENDPOINT_API_VERSIONS: &[(&str, (usize, usize))] = &[
+    ("/containers/prune", (1, 25)),
+    ("/images/prune", (1, 25)),
+    ("/networks/prune", (1, 25)),
+    ("/volumes/prune", (1, 25)),
+    ("/system/df", (1, 25)),
+    ("/swarm/unlock", (1, 25)),
+    ("/swarm/unlockkey", (1, 25)),
+    ("/secrets/**", (1, 25)),
+    ("/plugins/**", (1, 25)),
+    ("/plugins/**/upgrade", (1, 26)),
+    ("/services/*/logs", (1, 29)),
+    ("/tasks/*/logs", (1, 29)),
+    ("/configs/**", (1, 30)),
+    ("/distribution/**", (1, 30)),
+    ("/build/prune", (1, 31)),
+];
+
+/// Returns the minimum API version of an endpoint, `None` if it's available in every version.
+fn endpoint_api_version(endpoint: &str) -> Option<ApiVersion> {
+    fn matches(pattern: &[&str], path: &[&str]) -> bool {
+        match (pattern.split_first(), path.split_first()) {
+            (Some((&"**", rest)), _) => {
+                matches(rest, path) || (!path.is_empty() && matches(pattern, &path[1..]))
+            }
+            (Some((p, rest)), Some((segment, path))) if *p == "*" || p == segment => {
+                matches(rest, path)
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+    let segments = |path: &'static str| path.split('/').filter(|s| !s.is_empty());
+
+    let path = endpoint.split('?').next().unwrap_or_default();
+    let path: Vec<_> = path.split('/').filter(|s| !s.is_empty()).collect();
+    ENDPOINT_API_VERSIONS
+        .iter()
+        .filter(|(pattern, _)| matches(&segments(pattern).collect::<Vec<_>>(), &path))
+        .map(|(_, version)| ApiVersion::from(*version))
+        .max()
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// Options used when creating the transport of a [`Docker`](Docker) instance.
+pub(crate) struct ClientOpts {
+    version: Option<ApiVersion>,
+    connect_timeout: Option<Duration>,
+}
+
+impl ClientOpts {
+    fn versioned(version: impl Into<ApiVersion>) -> Self {
+        Self {
+            version: Some(version.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Option<ApiVersion>> for ClientOpts {
+    fn from(version: Option<ApiVersion>) -> Self {
+        Self {
+            version,
+            ..Default::default()
+        }
+    }
+}
 
 /// Entrypoint interface for communicating with docker daemon
 #[derive(Debug, Clone)]
 pub struct Docker {
-    version: Option<ApiVersion>,
-    client: RequestClient<Error>,
+    version: Arc<VersionState>,
+    client: RequestClient,
+    credentials: Option<Arc<Credentials>>,
+    /// Credentials of the docker CLI configuration, loaded once on first use and shared by all
+    /// clones. `None` if the configuration failed to load.
+    cli_credentials: Arc<OnceCell<Option<Credentials>>>,
+}
+
+#[derive(Debug, Default)]
+/// API version shared by a [`Docker`](Docker) instance with all of its clones.
+struct VersionState {
+    version: RwLock<Option<ApiVersion>>,
+    negotiate: AtomicBool,
+    negotiated: OnceCell<()>,
 }
 
 impl Docker {
+    pub(crate) fn new_with_transport(transport: Transport, opts: ClientOpts) -> Self {
+        Self {
+            version: Arc::new(VersionState {
+                version: RwLock::new(opts.version),
+                ..Default::default()
+            }),
+            client: RequestClient::new(transport, Box::new(validate_response)),
+            credentials: None,
+            cli_credentials: Default::default(),
+        }
+    }
+
+    /// Creates a new unversioned Docker instance that sends requests with the given `transport`,
+    /// for example one recording to or replaying from a cassette file.
+    pub fn from_transport(transport: Transport) -> Self {
+        Self::new_with_transport(transport, ClientOpts::default())
+    }
+
+    /// Returns the transport used to communicate with the docker daemon.
+    pub fn transport(&self) -> &Transport {
+        self.client.transport()
+    }
+
+    /// Creates a new builder for a Docker instance with custom connection settings like timeouts
+    /// and retries. See [`DockerBuilder`](DockerBuilder).
+    pub fn builder(uri: impl Into<String>) -> DockerBuilder {
+        DockerBuilder::new(uri)
+    }
+
     /// Creates a new Docker instance by automatically choosing appropriate connection type based
     /// on provided `uri`.
     ///
@@ -41,6 +172,7 @@ impl Docker {
     ///  - `unix://` only works when build target is `unix`, otherwise returns an Error
     ///  - `tcp://`
     ///  - `http://`
+    ///  - `ssh://`
     ///
     ///  To create a Docker instance utilizing TLS use explicit [Docker::tls](Docker::tls)
     ///  constructor (this requires `tls` feature enabled).
@@ -48,22 +180,124 @@ impl Docker {
     ///  This creates an unversioned connector that'll use the latest server version, to use a specific version see
     ///  [`Docker::unix_versioned`](Docker::unix_versioned).
     pub fn new(uri: impl AsRef<str>) -> Result<Self> {
-        Self::new_impl(uri.as_ref(), None)
+        Self::new_impl(uri.as_ref(), ClientOpts::default())
     }
 
     /// Same as [`Docker::new`](Docker::new) but the API version can be explicitly specified.
     pub fn new_versioned(uri: impl AsRef<str>, version: impl Into<ApiVersion>) -> Result<Self> {
-        Self::new_impl(uri.as_ref(), Some(version.into()))
+        Self::new_impl(uri.as_ref(), ClientOpts::versioned(version))
+    }
+
+    /// Creates a new Docker instance configured from the environment the same way the docker CLI
+    /// does it.
+    ///
+    /// The following environment variables are respected:
+    ///  - `DOCKER_HOST` - URI of the docker host. Same schemes as in [`Docker::new`](Docker::new)
+    ///    are supported with the addition of `https://`.
+    ///  - `DOCKER_TLS_VERIFY` - when set to a non-empty value a TLS connection is used and the
+    ///    certificate of the host is verified with `ca.pem`.
+    ///  - `DOCKER_CERT_PATH` - directory containing `ca.pem`, `cert.pem` and `key.pem`. When set a
+    ///    TLS connection is used. Defaults to `~/.docker`.
+    ///  - `DOCKER_API_VERSION` - the API version to use, by default the connector is unversioned.
+    ///
+    /// TLS connections require the `tls` feature to be enabled.
+    ///
+    /// If `DOCKER_HOST` is not set the rootless socket `/run/user/<uid>/docker.sock` is tried
+    /// first and `/var/run/docker.sock` after it. When no docker host can be found a
+    /// [`Error::DockerHostNotFound`](Error::DockerHostNotFound) listing every location that was
+    /// tried is returned.
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_impl(env_var)
     }
 
-    fn new_impl(uri: &str, version: Option<ApiVersion>) -> Result<Self> {
+    fn from_env_impl(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let version = var(DOCKER_API_VERSION_ENV)
+            .map(|version| version.parse::<ApiVersion>())
+            .transpose()?;
+
+        if var(DOCKER_HOST_ENV).is_none() {
+            if let Some(config_dir) = config_file::config_dir_impl(&var) {
+                let store = ContextStore::new(config_dir);
+                let name = store.current_impl(&var)?;
+                if name != DEFAULT_CONTEXT {
+                    let context = store.get(&name)?;
+                    return match version {
+                        Some(version) => context.docker_versioned(version),
+                        None => context.docker(),
+                    };
+                }
+            }
+        }
+
+        Self::from_host_env(var, version.into())
+    }
+
+    /// Creates a new Docker instance from `DOCKER_HOST` and TLS related environment variables
+    /// falling back to the default sockets, ignoring any selected context.
+    pub(crate) fn from_host_env(
+        var: impl Fn(&str) -> Option<String>,
+        opts: ClientOpts,
+    ) -> Result<Self> {
+        let host = match var(DOCKER_HOST_ENV) {
+            Some(host) => host,
+            None => {
+                #[allow(unused_mut)]
+                let mut tried = vec![format!("{DOCKER_HOST_ENV} environment variable")];
+                #[cfg(unix)]
+                for socket in default_unix_sockets() {
+                    if socket.exists() {
+                        return Ok(Self::new_unix_impl(socket, opts));
+                    }
+                    tried.push(socket.display().to_string());
+                }
+                return Err(Error::DockerHostNotFound(tried));
+            }
+        };
+
+        let verify = var(DOCKER_TLS_VERIFY_ENV).is_some();
+        let cert_path = var(DOCKER_CERT_PATH_ENV).map(PathBuf::from);
+
+        if verify || cert_path.is_some() || host.starts_with("https://") {
+            let cert_path = cert_path
+                .or_else(|| config_file::config_dir_impl(&var))
+                .unwrap_or_default();
+            Self::new_tls_uri(&host, opts, Some(&cert_path), verify)
+        } else {
+            Self::new_impl(&host, opts)
+        }
+    }
+
+    /// Creates a TLS connector if the `uri` points to a TCP host, otherwise the TLS material is
+    /// ignored and the connector is created with [`Docker::new`](Docker::new). Without a
+    /// `cert_path` no client certificate is sent.
+    pub(crate) fn new_tls_uri(
+        uri: &str,
+        opts: ClientOpts,
+        cert_path: Option<&Path>,
+        verify: bool,
+    ) -> Result<Self> {
+        match uri.split_once("://") {
+            #[cfg(feature = "tls")]
+            Some(("tcp" | "http" | "https", host)) => {
+                Self::new_tls_impl(host, opts, cert_path, verify)
+            }
+            #[cfg(not(feature = "tls"))]
+            Some(("tcp" | "http" | "https", _)) => {
+                let _ = (cert_path, verify);
+                Err(Error::TlsNotEnabled)
+            }
+            _ => Self::new_impl(uri, opts),
+        }
+    }
+
+    fn new_impl(uri: &str, opts: ClientOpts) -> Result<Self> {
         let mut it = uri.split("://");
 
         match it.next() {
             #[cfg(unix)]
             Some("unix") => {
                 if let Some(path) = it.next() {
-                    Ok(Self::new_unix_impl(path, version))
+                    Ok(Self::new_unix_impl(path, opts))
                 } else {
                     Err(Error::MissingAuthority)
                 }
@@ -72,11 +306,12 @@ impl Docker {
             Some("unix") => Err(Error::UnsupportedScheme("unix".to_string())),
             Some("tcp") | Some("http") => {
                 if let Some(host) = it.next() {
-                    Self::new_tcp_impl(host, version)
+                    Self::new_tcp_impl(host, opts)
                 } else {
                     Err(Error::MissingAuthority)
                 }
             }
+            Some("ssh") => Self::new_ssh_impl(uri, opts),
             Some(scheme) => Err(Error::UnsupportedScheme(scheme.to_string())),
             None => unreachable!(), // This is never possible because calling split on an empty string
                                     // always returns at least one element
@@ -93,30 +328,88 @@ impl Docker {
     ///  This creates an unversioned connector that'll use the latest server version, to use a specific version see
     ///  [`Docker::unix_versioned`](Docker::unix_versioned).
     pub fn unix(socket_path: impl AsRef<Path>) -> Self {
-        Self::new_unix_impl(socket_path.as_ref(), None)
+        Self::new_unix_impl(socket_path.as_ref(), ClientOpts::default())
     }
 
     #[cfg(unix)]
     #[cfg_attr(docsrs, doc(cfg(unix)))]
     /// Same as [`Docker::unix`](Docker::unix) but the API version can be explicitly specified.
     pub fn unix_versioned(socket_path: impl AsRef<Path>, version: impl Into<ApiVersion>) -> Self {
-        Self::new_unix_impl(socket_path.as_ref(), Some(version.into()))
+        Self::new_unix_impl(socket_path.as_ref(), ClientOpts::versioned(version))
     }
 
     #[cfg(unix)]
-    fn new_unix_impl(socket_path: impl Into<PathBuf>, version: Option<ApiVersion>) -> Self {
-        Docker {
-            version,
-            client: RequestClient::new(
-                Transport::Unix {
-                    client: Client::builder()
-                        .pool_max_idle_per_host(0)
-                        .build(get_unix_connector()),
-                    path: socket_path.into(),
-                },
-                Box::new(validate_response),
-            ),
-        }
+    fn new_unix_impl(socket_path: impl Into<PathBuf>, opts: ClientOpts) -> Self {
+        Self::new_with_transport(
+            Transport::Unix {
+                client: Client::builder()
+                    .pool_max_idle_per_host(0)
+                    .build(TimeoutConnector::new(
+                        get_unix_connector(),
+                        opts.connect_timeout,
+                    )),
+                path: socket_path.into(),
+            },
+            opts,
+        )
+    }
+
+    /// Creates a new docker instance for a docker host reachable over SSH. `uri` has the form of
+    /// `ssh://[user@]host[:port][/path/to/docker.sock]`.
+    ///
+    /// Every connection spawns `ssh` that runs `docker system dial-stdio` on the remote host, so
+    /// the `ssh` binary has to be available locally and the docker CLI on the remote host. To
+    /// customize the spawned command use [`Docker::ssh_command`](Docker::ssh_command).
+    ///
+    /// Returns an error if the provided `uri` will fail to parse as URL.
+    ///
+    ///  This creates an unversioned connector that'll use the latest server version, to use a specific version see
+    ///  [`Docker::ssh_versioned`](Docker::ssh_versioned).
+    pub fn ssh(uri: impl AsRef<str>) -> Result<Self> {
+        Self::new_ssh_impl(uri.as_ref(), ClientOpts::default())
+    }
+
+    /// Same as [`Docker::ssh`](Docker::ssh) but the API version can be explicitly specified.
+    pub fn ssh_versioned(uri: impl AsRef<str>, version: impl Into<ApiVersion>) -> Result<Self> {
+        Self::new_ssh_impl(uri.as_ref(), ClientOpts::versioned(version))
+    }
+
+    /// Creates a new docker instance that spawns `command` for every connection. The command has
+    /// to proxy its standard input and output to the docker daemon, like `docker system
+    /// dial-stdio` does.
+    ///
+    ///  This creates an unversioned connector that'll use the latest server version, to use a specific version see
+    ///  [`Docker::ssh_command_versioned`](Docker::ssh_command_versioned).
+    pub fn ssh_command(command: SshCommand) -> Self {
+        Self::new_ssh_command_impl(command, ClientOpts::default())
+    }
+
+    /// Same as [`Docker::ssh_command`](Docker::ssh_command) but the API version can be explicitly
+    /// specified.
+    pub fn ssh_command_versioned(command: SshCommand, version: impl Into<ApiVersion>) -> Self {
+        Self::new_ssh_command_impl(command, ClientOpts::versioned(version))
+    }
+
+    fn new_ssh_impl(uri: &str, opts: ClientOpts) -> Result<Self> {
+        SshCommand::from_uri(uri).map(|command| Self::new_ssh_command_impl(command, opts))
+    }
+
+    fn new_ssh_command_impl(command: SshCommand, opts: ClientOpts) -> Self {
+        let host = std::iter::once(command.program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self::new_with_transport(
+            Transport::Ssh {
+                client: Client::builder().build(TimeoutConnector::new(
+                    SshConnector::new(command),
+                    opts.connect_timeout,
+                )),
+                host,
+            },
+            opts,
+        )
     }
 
     #[cfg(feature = "tls")]
@@ -135,7 +428,12 @@ impl Docker {
     ///  This creates an unversioned connector that'll use the latest server version, to use a specific version see
     ///  [`Docker::unix_versioned`](Docker::unix_versioned).
     pub fn tls(host: impl AsRef<str>, cert_path: impl AsRef<Path>, verify: bool) -> Result<Self> {
-        Self::new_tls_impl(host.as_ref(), None, cert_path.as_ref(), verify)
+        Self::new_tls_impl(
+            host.as_ref(),
+            ClientOpts::default(),
+            Some(cert_path.as_ref()),
+            verify,
+        )
     }
 
     #[cfg(feature = "tls")]
@@ -149,8 +447,8 @@ impl Docker {
     ) -> Result<Self> {
         Self::new_tls_impl(
             host.as_ref(),
-            Some(version.into()),
-            cert_path.as_ref(),
+            ClientOpts::versioned(version),
+            Some(cert_path.as_ref()),
             verify,
         )
     }
@@ -158,20 +456,22 @@ impl Docker {
     #[cfg(feature = "tls")]
     fn new_tls_impl(
         host: &str,
-        version: Option<ApiVersion>,
-        cert_path: &Path,
+        opts: ClientOpts,
+        cert_path: Option<&Path>,
         verify: bool,
     ) -> Result<Self> {
-        Ok(Self {
-            version,
-            client: RequestClient::new(
-                Transport::EncryptedTcp {
-                    client: Client::builder().build(get_https_connector(cert_path, verify)?),
-                    host: url::Url::parse(&format!("https://{host}")).map_err(Error::InvalidUrl)?,
-                },
-                Box::new(validate_response),
-            ),
-        })
+        let connector = match cert_path {
+            Some(cert_path) => get_https_connector(cert_path, verify)?,
+            None => get_https_connector_without_cert(verify)?,
+        };
+        Ok(Self::new_with_transport(
+            Transport::EncryptedTcp {
+                client: Client::builder()
+                    .build(TimeoutConnector::new(connector, opts.connect_timeout)),
+                host: url::Url::parse(&format!("https://{host}")).map_err(Error::InvalidUrl)?,
+            },
+            opts,
+        ))
     }
 
     /// Creates a new docker instance for a docker host listening on a given TCP socket `host`.
@@ -185,25 +485,25 @@ impl Docker {
     ///  This creates an unversioned connector that'll use the latest server version, to use a specific version see
     ///  [`Docker::unix_versioned`](Docker::unix_versioned).
     pub fn tcp(host: impl AsRef<str>) -> Result<Self> {
-        Self::new_tcp_impl(host.as_ref(), None)
+        Self::new_tcp_impl(host.as_ref(), ClientOpts::default())
     }
 
     /// Same as [`Docker::tcp`](Docker::tcp) but the API version can be explicitly specified.
     pub fn tcp_versioned(host: impl AsRef<str>, version: impl Into<ApiVersion>) -> Result<Self> {
-        Self::new_tcp_impl(host.as_ref(), Some(version.into()))
+        Self::new_tcp_impl(host.as_ref(), ClientOpts::versioned(version))
     }
 
-    fn new_tcp_impl(host: &str, version: Option<ApiVersion>) -> Result<Self> {
-        Ok(Self {
-            version,
-            client: RequestClient::new(
-                Transport::Tcp {
-                    client: Client::builder().build(get_http_connector()),
-                    host: url::Url::parse(&format!("tcp://{host}")).map_err(Error::InvalidUrl)?,
-                },
-                Box::new(validate_response),
-            ),
-        })
+    fn new_tcp_impl(host: &str, opts: ClientOpts) -> Result<Self> {
+        Ok(Self::new_with_transport(
+            Transport::Tcp {
+                client: Client::builder().build(TimeoutConnector::new(
+                    get_http_connector(),
+                    opts.connect_timeout,
+                )),
+                host: url::Url::parse(&format!("tcp://{host}")).map_err(Error::InvalidUrl)?,
+            },
+            opts,
+        ))
     }
 
     /// Exports an interface for interacting with Docker images
@@ -226,9 +526,84 @@ impl Docker {
         Volumes::new(self.clone())
     }
 
+    /// Registers an interceptor called around every request sent by the returned instance and
+    /// its clones, see [`Interceptor`](crate::conn::Interceptor).
+    pub fn with_interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.client.add_interceptor(Arc::new(interceptor));
+        self
+    }
+
+    /// Sets the registry credentials used by [`Images::pull`](crate::Images::pull) and
+    /// [`Image::push`](crate::Image::push) when no credentials are provided in the options.
+    /// By default the credentials of the docker CLI configuration file are used, see
+    /// [`Credentials::load`](crate::credentials::Credentials::load). The configuration file is
+    /// only read once, on the first pull or push.
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(Arc::new(credentials));
+        self
+    }
+
+    /// Returns the serialized credentials for the registry of the image `reference`. Failures
+    /// are only logged so that pulling public images still works with a broken configuration.
+    pub(crate) async fn registry_auth(&self, reference: &str) -> Option<String> {
+        let credentials = match &self.credentials {
+            Some(credentials) => credentials,
+            None => {
+                let credentials = self
+                    .cli_credentials
+                    .get_or_init(|| async {
+                        Credentials::load()
+                            .map_err(|e| log::warn!("failed to load registry credentials: {e}"))
+                            .ok()
+                    })
+                    .await;
+                credentials.as_ref()?
+            }
+        };
+        match credentials.for_image(reference).await {
+            Ok(auth) => auth.map(|auth| auth.serialize()),
+            Err(e) => {
+                log::warn!("failed to resolve registry credentials for `{reference}`: {e}");
+                None
+            }
+        }
+    }
+
+    /// Enables API version negotiation. Before the first request the daemon is pinged once and
+    /// the API version it supports, capped at [`LATEST_API_VERSION`](crate::LATEST_API_VERSION),
+    /// is used for all subsequent requests.
+    ///
+    /// The negotiated version is shared by all clones of this instance and all interfaces like
+    /// [`Containers`](crate::Containers) created from it. Negotiation is skipped if an API
+    /// version was explicitly specified when creating this instance.
+    pub fn with_api_version_negotiation(self) -> Self {
+        if self.api_version().is_none() {
+            self.version.negotiate.store(true, Ordering::Release);
+        }
+        self
+    }
+
+    /// Returns the API version used by this client. `None` means that requests are sent to
+    /// unversioned endpoints and the daemon uses its latest version.
+    pub fn api_version(&self) -> Option<ApiVersion> {
+        *self
+            .version
+            .version
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_api_version(&self, version: ApiVersion) {
+        *self
+            .version
+            .version
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(version);
+    }
+
     /// Verifies the API version returned by the server and adjusts the version used by this client
-    /// in future requests.
-    pub async fn adjust_api_version(&mut self) -> Result<()> {
+    /// and all of its clones in future requests.
+    pub async fn adjust_api_version(&self) -> Result<()> {
         let server_version: ApiVersion = self.version().await.and_then(|v| {
             v.api_version
                 .unwrap_or_default()
@@ -236,34 +611,94 @@ impl Docker {
                 .map_err(Error::MalformedVersion)
         })?;
 
-        self.version = Some(server_version);
+        self.set_api_version(server_version);
+        // an explicitly adjusted version must not be overwritten by a later negotiation
+        let _ = self.version.negotiated.set(());
 
         Ok(())
     }
 
+    async fn negotiate_api_version(&self) -> Result<()> {
+        if !self.version.negotiate.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        self.version
+            .negotiated
+            .get_or_try_init(|| async {
+                let response = self.client.get("/_ping").await?;
+                let server_version = match response.headers().get("api-version") {
+                    Some(version) => version
+                        .to_str()
+                        .map_err(|e| Error::InvalidResponse(e.to_string()))?
+                        .parse::<ApiVersion>()
+                        .map_err(Error::MalformedVersion)?,
+                    None => FALLBACK_API_VERSION,
+                };
+                let version = server_version.min(crate::LATEST_API_VERSION);
+                log::debug!("negotiated API version {version}");
+                self.set_api_version(version);
+                Ok::<_, Error>(())
+            })
+            .await
+            .map(|_| ())
+    }
+
     //####################################################################################################
     //
     // Utility functions to make requests
     //
     //####################################################################################################
 
-    fn make_endpoint(&self, endpoint: impl AsRef<str>) -> String {
-        if let Some(version) = self.version {
-            version.make_endpoint(endpoint)
+    /// Returns an error if the API version used by this client is older than `required`.
+    /// Unversioned clients use the latest version of the daemon so they always pass.
+    pub(crate) async fn require_api_version(&self, required: impl Into<ApiVersion>) -> Result<()> {
+        let required = required.into();
+        self.negotiate_api_version().await?;
+        match self.api_version() {
+            Some(negotiated) if negotiated < required => Err(Error::UnsupportedApiVersion {
+                required,
+                negotiated,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Same as [`Docker::require_api_version`](Docker::require_api_version) but for the version
+    /// required by the parameters of some opts.
+    pub(crate) async fn require_opts_api_version(
+        &self,
+        required: Option<ApiVersion>,
+    ) -> Result<()> {
+        match required {
+            Some(required) => self.require_api_version(required).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn make_endpoint(&self, endpoint: impl AsRef<str>) -> Result<String> {
+        self.negotiate_api_version().await?;
+        if let Some(required) = endpoint_api_version(endpoint.as_ref()) {
+            self.require_api_version(required).await?;
+        }
+        if let Some(version) = self.api_version() {
+            Ok(version.make_endpoint(endpoint))
         } else {
-            endpoint.as_ref().to_owned()
+            Ok(endpoint.as_ref().to_owned())
         }
     }
 
     pub(crate) async fn get(&self, endpoint: &str) -> Result<Response<Body>> {
-        self.client.get(self.make_endpoint(endpoint)).await
+        trace::record_request(&Method::GET, endpoint);
+        self.client.get(self.make_endpoint(endpoint).await?).await
     }
 
     pub(crate) async fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
-        self.client.get_json(self.make_endpoint(endpoint)).await
+        trace::record_request(&Method::GET, endpoint);
+        self.client
+            .get_json(self.make_endpoint(endpoint).await?)
+            .await
     }
 
-    #[allow(dead_code)]
     pub(crate) async fn post<B>(
         &self,
         endpoint: &str,
@@ -273,8 +708,9 @@ impl Docker {
     where
         B: Into<Body>,
     {
+        trace::record_request(&Method::POST, endpoint);
         self.client
-            .post(self.make_endpoint(endpoint), body, headers)
+            .post(self.make_endpoint(endpoint).await?, body, headers)
             .await
     }
 
@@ -287,8 +723,9 @@ impl Docker {
     where
         B: Into<Body>,
     {
+        trace::record_request(&Method::POST, endpoint);
         self.client
-            .post_string(self.make_endpoint(endpoint), body, headers)
+            .post_string(self.make_endpoint(endpoint).await?, body, headers)
             .await
     }
 
@@ -302,8 +739,10 @@ impl Docker {
         T: DeserializeOwned,
         B: Into<Body>,
     {
+        let endpoint = endpoint.as_ref();
+        trace::record_request(&Method::POST, endpoint);
         self.client
-            .post_json(self.make_endpoint(endpoint), body, headers)
+            .post_json(self.make_endpoint(endpoint).await?, body, headers)
             .await
     }
 
@@ -311,23 +750,29 @@ impl Docker {
     where
         B: Into<Body>,
     {
+        trace::record_request(&Method::PUT, endpoint);
         self.client
-            .put_string(self.make_endpoint(endpoint), body)
+            .put_string(self.make_endpoint(endpoint).await?, body)
             .await
     }
 
     pub(crate) async fn delete(&self, endpoint: &str) -> Result<String> {
+        trace::record_request(&Method::DELETE, endpoint);
         self.client
-            .delete_string(self.make_endpoint(endpoint))
+            .delete_string(self.make_endpoint(endpoint).await?)
             .await
     }
 
     pub(crate) async fn delete_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T> {
-        self.client.delete_json(self.make_endpoint(endpoint)).await
+        trace::record_request(&Method::DELETE, endpoint);
+        self.client
+            .delete_json(self.make_endpoint(endpoint).await?)
+            .await
     }
 
     pub(crate) async fn head(&self, endpoint: &str) -> Result<Response<Body>> {
-        self.client.head(self.make_endpoint(endpoint)).await
+        trace::record_request(&Method::HEAD, endpoint);
+        self.client.head(self.make_endpoint(endpoint).await?).await
     }
 
     #[allow(dead_code)]
@@ -343,8 +788,12 @@ impl Docker {
     where
         B: Into<Body> + 'a,
     {
-        self.client
-            .post_stream(self.make_endpoint(endpoint), body, headers)
+        async move {
+            trace::record_request(&Method::POST, endpoint.as_ref());
+            self.make_endpoint(endpoint).await
+        }
+        .map_ok(move |ep| self.client.post_stream(ep, body, headers))
+        .try_flatten_stream()
     }
 
     /// Send a streaming post request that returns a stream of JSON values
@@ -360,15 +809,24 @@ impl Docker {
         B: Into<Body> + 'a,
         T: DeserializeOwned + 'a,
     {
-        self.client
-            .post_into_stream(self.make_endpoint(endpoint), body, headers)
+        async move {
+            trace::record_request(&Method::POST, endpoint.as_ref());
+            self.make_endpoint(endpoint).await
+        }
+        .map_ok(move |ep| self.client.post_into_stream(ep, body, headers))
+        .try_flatten_stream()
     }
 
     pub(crate) fn get_stream<'a>(
         &'a self,
         endpoint: impl AsRef<str> + Unpin + 'a,
     ) -> impl Stream<Item = Result<Bytes>> + 'a {
-        self.client.get_stream(self.make_endpoint(endpoint))
+        async move {
+            trace::record_request(&Method::GET, endpoint.as_ref());
+            self.make_endpoint(endpoint).await
+        }
+        .map_ok(move |ep| self.client.get_stream(ep))
+        .try_flatten_stream()
     }
 
     pub(crate) async fn post_upgrade_stream<B>(
@@ -379,9 +837,207 @@ impl Docker {
     where
         B: Into<Body>,
     {
-        let ep = self.make_endpoint(endpoint);
+        let endpoint = endpoint.as_ref();
+        trace::record_request(&Method::POST, endpoint);
+        let ep = self.make_endpoint(endpoint).await?;
         self.client.post_upgrade_stream(ep, body).await
     }
+
+    #[cfg(feature = "websocket")]
+    pub(crate) async fn get_upgrade_websocket(
+        &self,
+        endpoint: &str,
+    ) -> Result<crate::conn::WebSocket> {
+        trace::record_request(&Method::GET, endpoint);
+        self.client
+            .get_upgrade_websocket(self.make_endpoint(endpoint).await?)
+            .await
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Builder for [`Docker`](Docker) instances with custom connection settings.
+///
+/// Timeouts and retries rely on the tokio timer so the runtime has to have time enabled.
+pub struct DockerBuilder {
+    uri: String,
+    opts: ClientOpts,
+    #[cfg(feature = "tls")]
+    tls: Option<(PathBuf, bool)>,
+    timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    interceptors: Interceptors,
+    credentials: Option<Credentials>,
+}
+
+impl DockerBuilder {
+    /// Creates a new builder for a docker host at `uri`. Supported schemes are the same as in
+    /// [`Docker::new`](Docker::new).
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            opts: ClientOpts::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+            timeout: None,
+            retry_policy: None,
+            interceptors: Interceptors::default(),
+            credentials: None,
+        }
+    }
+
+    /// API version used by the client. By default the client is unversioned and uses the latest
+    /// version of the server.
+    pub fn version(mut self, version: impl Into<ApiVersion>) -> Self {
+        self.opts.version = Some(version.into());
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    /// Connect to `tcp://`, `http://` and `https://` hosts using TLS. See
+    /// [`Docker::tls`](Docker::tls) for the meaning of `cert_path` and `verify`.
+    pub fn tls(mut self, cert_path: impl Into<PathBuf>, verify: bool) -> Self {
+        self.tls = Some((cert_path.into(), verify));
+        self
+    }
+
+    /// Maximum time to wait for the response headers of a request, requests exceeding it fail
+    /// with [`Error::Timeout`](Error::Timeout). Reading the body isn't bounded, so long-polling
+    /// endpoints like [`Container::wait`](crate::Container::wait) can take longer.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum time to wait for a connection to the docker host to be established.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.opts.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry GET and HEAD requests that fail with a connection error, a timeout or a server
+    /// error according to `policy`.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Registers an interceptor called around every request, see
+    /// [`Interceptor`](crate::conn::Interceptor). Interceptors are called in the order they
+    /// were registered.
+    pub fn interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Registry credentials used when pulling and pushing images, see
+    /// [`Docker::with_credentials`](Docker::with_credentials).
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Creates the [`Docker`](Docker) instance. Returns an error if the URI is invalid or
+    /// reading the TLS certificates fails.
+    pub fn build(self) -> Result<Docker> {
+        #[cfg(feature = "tls")]
+        let mut docker = match &self.tls {
+            Some((cert_path, verify)) => {
+                Docker::new_tls_uri(&self.uri, self.opts, Some(cert_path), *verify)?
+            }
+            None => Docker::new_impl(&self.uri, self.opts)?,
+        };
+        #[cfg(not(feature = "tls"))]
+        let mut docker = Docker::new_impl(&self.uri, self.opts)?;
+
+        docker.client.set_timeout(self.timeout);
+        docker.client.set_retry_policy(self.retry_policy);
+        docker.client.set_interceptors(self.interceptors);
+        docker.credentials = self.credentials.map(Arc::new);
+
+        Ok(docker)
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Policy for retrying idempotent requests. The delay between attempts grows exponentially and is
+/// randomized so that many clients don't retry at the same time.
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a new policy that retries a request at most `max_retries` times.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the first retry that is doubled on every following retry. (Default: 100ms)
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Upper bound of the delay between retries. (Default: 10s)
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Maximum number of retries of a single request.
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Returns the delay before retry number `attempt` counting from 0. Half of the delay is
+    /// random.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        backoff / 2 + (backoff / 2).mul_f64(random_fraction())
+    }
+}
+
+/// Returns a random number in the range `[0, 1)`.
+fn random_fraction() -> f64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Reads an environment variable treating empty values as unset.
+pub(crate) fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|val| !val.is_empty())
+}
+
+#[cfg(unix)]
+/// Default locations of the docker socket in the order they are tried, rootless first.
+fn default_unix_sockets() -> [PathBuf; 2] {
+    let uid = nix::unistd::Uid::effective();
+    [
+        PathBuf::from(format!("/run/user/{uid}/docker.sock")),
+        PathBuf::from("/var/run/docker.sock"),
+    ]
 }
 
 fn validate_response(
@@ -424,10 +1080,7 @@ fn validate_response(
                             .unwrap_or("unknown error code")
                             .to_owned()
                     });
-                Err(Error::Fault {
-                    code: status,
-                    message,
-                })
+                Err(Error::from_status(status, message))
             }
         }
     })
@@ -471,9 +1124,77 @@ impl Docker {
     }
 }
 
+#[cfg(feature = "tls")]
+/// Creates a TLS connector that doesn't send a client certificate. Unless `verify` is set the
+/// certificate of the host isn't verified either.
+fn get_https_connector_without_cert(
+    verify: bool,
+) -> Result<hyper_openssl::HttpsConnector<hyper::client::HttpConnector>> {
+    use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+
+    let tls_error = |e| Error::Error(containers_api::conn::Error::ErrorStack(e));
+    let mut ssl = SslConnector::builder(SslMethod::tls()).map_err(tls_error)?;
+    ssl.set_cipher_list("DEFAULT").map_err(tls_error)?;
+    if !verify {
+        ssl.set_verify(SslVerifyMode::NONE);
+    }
+    hyper_openssl::HttpsConnector::with_connector(get_http_connector(), ssl).map_err(tls_error)
+}
+
 #[cfg(test)]
-mod tests {
-    use super::{Docker, Error};
+pub(crate) mod tests {
+    use super::{
+        ApiVersion, Body, Docker, Duration, Error, Headers, Payload, Response, RetryPolicy,
+        SshCommand,
+    };
+
+    fn env(vars: &'static [(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn creates_docker_from_env() {
+        let d = Docker::from_env_impl(env(&[("DOCKER_HOST", "tcp://127.0.0.1:2375")])).unwrap();
+        assert_eq!(d.api_version(), None);
+
+        let d = Docker::from_env_impl(env(&[
+            ("DOCKER_HOST", "unix:///var/run/docker.sock"),
+            ("DOCKER_API_VERSION", "1.41"),
+        ]))
+        .unwrap();
+        assert_eq!(d.api_version(), Some(ApiVersion::from((1, 41))));
+
+        match Docker::from_env_impl(env(&[
+            ("DOCKER_HOST", "tcp://127.0.0.1:2375"),
+            ("DOCKER_API_VERSION", "latest"),
+        ]))
+        .unwrap_err()
+        {
+            Error::MalformedVersion(_) => {}
+            e => panic!("Expected Error::MalformedVersion, got {e}"),
+        }
+
+        match Docker::from_env_impl(env(&[("DOCKER_HOST", "rand://127.0.0.1:80")])).unwrap_err() {
+            Error::UnsupportedScheme(scheme) if &scheme == "rand" => {}
+            e => panic!(r#"Expected Error::UnsupportedScheme("rand"), got {e}"#),
+        }
+
+        #[cfg(not(feature = "tls"))]
+        match Docker::from_env_impl(env(&[
+            ("DOCKER_HOST", "tcp://127.0.0.1:2376"),
+            ("DOCKER_TLS_VERIFY", "1"),
+        ]))
+        .unwrap_err()
+        {
+            Error::TlsNotEnabled => {}
+            e => panic!("Expected Error::TlsNotEnabled, got {e}"),
+        }
+    }
+
     #[test]
     fn creates_correct_docker() {
         let d = Docker::new("tcp://127.0.0.1:80");
@@ -496,6 +1217,14 @@ mod tests {
             }
         }
 
+        let d = Docker::new("ssh://user@127.0.0.1:22");
+        d.unwrap();
+        let d = Docker::new("ssh://");
+        match d.unwrap_err() {
+            Error::MissingAuthority => {}
+            e => panic!("Expected Error::MissingAuthority, got {e}"),
+        }
+
         let d = Docker::new("rand://127.0.0.1:80");
         match d.unwrap_err() {
             Error::UnsupportedScheme(scheme) if &scheme == "rand" => {}
@@ -513,4 +1242,565 @@ mod tests {
             e => panic!(r#"Expected Error::UnsupportedScheme(""), got {e}"#),
         }
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn talks_to_dial_stdio_command() {
+        let body = r#"{"ApiVersion":"1.42","Version":"23.0.1"}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let docker = Docker::ssh_command(SshCommand::new("sh").arg("-c").arg(format!(
+            "read -r line; printf '{response}'; cat > /dev/null"
+        )));
+
+        let version = docker.version().await.unwrap();
+        assert_eq!(version.api_version.as_deref(), Some("1.42"));
+        assert_eq!(version.version.as_deref(), Some("23.0.1"));
+    }
+
+    /// A fake `docker system dial-stdio` answering the inspect of a container without a TTY and
+    /// upgrading its attach request. The attached container writes `hello` to stdout and
+    /// answers `ls` on stdin with `got ls` on stderr.
+    #[cfg(unix)]
+    const FAKE_DIAL_STDIO_ATTACH: &str = r#"read -r method path rest
+while read -r header && [ "$header" != "$(printf '\r')" ]; do :; done
+case "$path" in
+  */json)
+    body='{"Id":"web","Config":{"Tty":false}}'
+    printf 'HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: %s\r\n\r\n%s' "${#body}" "$body"
+    ;;
+  */attach*)
+    printf 'HTTP/1.1 101 UPGRADED\r\nConnection: Upgrade\r\nUpgrade: tcp\r\n\r\n'
+    printf '\001\000\000\000\000\000\000\006hello\n'
+    read -r input
+    [ "$input" = ls ] && printf '\002\000\000\000\000\000\000\007got ls\n'
+    ;;
+esac"#;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn attaches_over_dial_stdio_command() {
+        use crate::conn::TtyChunk;
+        use futures_util::{AsyncWriteExt, StreamExt};
+
+        let docker =
+            Docker::ssh_command(SshCommand::new("sh").args(["-c", FAKE_DIAL_STDIO_ATTACH]));
+        let container = docker.containers().get("web");
+        let (reader, mut writer) = container.attach().await.unwrap().split();
+        futures_util::pin_mut!(reader);
+
+        match reader.next().await {
+            Some(Ok(TtyChunk::StdOut(bytes))) => assert_eq!(bytes, b"hello\n"),
+            chunk => panic!("Expected stdout chunk, got {chunk:?}"),
+        }
+        writer.write_all(b"ls\n").await.unwrap();
+        writer.flush().await.unwrap();
+        match reader.next().await {
+            Some(Ok(TtyChunk::StdErr(bytes))) => assert_eq!(bytes, b"got ls\n"),
+            chunk => panic!("Expected stderr chunk, got {chunk:?}"),
+        }
+        assert!(reader.next().await.is_none());
+    }
+
+    /// A fake `docker system dial-stdio` answering `/_ping` with `Api-Version: 1.99` and every
+    /// other request with `{"ApiVersion":"<requested path>"}`.
+    #[cfg(unix)]
+    const FAKE_DIAL_STDIO: &str = r#"read -r method path rest
+if [ "$path" = /_ping ]; then
+  printf 'HTTP/1.1 200 OK\r\nApi-Version: 1.99\r\nConnection: close\r\nContent-Length: 2\r\n\r\nOK'
+else
+  body="{\"ApiVersion\":\"$path\"}"
+  printf 'HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: %s\r\n\r\n%s' "${#body}" "$body"
+fi
+cat > /dev/null"#;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn negotiates_api_version() {
+        let docker = Docker::ssh_command(SshCommand::new("sh").args(["-c", FAKE_DIAL_STDIO]));
+        let version = docker.version().await.unwrap();
+        assert_eq!(version.api_version.as_deref(), Some("/version"));
+        assert_eq!(docker.api_version(), None);
+
+        let docker = Docker::ssh_command(SshCommand::new("sh").args(["-c", FAKE_DIAL_STDIO]))
+            .with_api_version_negotiation();
+        let clone = docker.clone();
+        let version = clone.version().await.unwrap();
+        assert_eq!(version.api_version.as_deref(), Some("/v1.42/version"));
+        assert_eq!(docker.api_version(), Some(crate::LATEST_API_VERSION));
+
+        let docker = Docker::ssh_command_versioned(
+            SshCommand::new("sh").args(["-c", FAKE_DIAL_STDIO]),
+            (1, 40),
+        )
+        .with_api_version_negotiation();
+        let version = docker.version().await.unwrap();
+        assert_eq!(version.api_version.as_deref(), Some("/v1.40/version"));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_api_version() {
+        use crate::opts::{
+            ClearCacheOpts, ContainerCreateOpts, ContainerStopOpts, NetworkCreateOpts,
+        };
+
+        // port 9 (discard) is never contacted as all requests fail the version check
+        let docker = Docker::new_versioned("tcp://127.0.0.1:9", (1, 39)).unwrap();
+
+        let opts = ContainerStopOpts::builder().signal("SIGINT").build();
+        match docker.containers().get("test").stop(&opts).await {
+            Err(Error::UnsupportedApiVersion {
+                required,
+                negotiated,
+            }) => {
+                assert_eq!(required, ApiVersion::from((1, 42)));
+                assert_eq!(negotiated, ApiVersion::from((1, 39)));
+            }
+            res => panic!("Expected Error::UnsupportedApiVersion, got {res:?}"),
+        }
+
+        let opts = ContainerCreateOpts::builder()
+            .image("test")
+            .device_requests(vec![])
+            .build();
+        match docker.containers().create(&opts).await {
+            Err(Error::UnsupportedApiVersion { required, .. }) => {
+                assert_eq!(required, ApiVersion::from((1, 40)))
+            }
+            res => panic!("Expected Error::UnsupportedApiVersion, got {res:?}"),
+        }
+
+        let docker = Docker::new_versioned("tcp://127.0.0.1:9", (1, 30)).unwrap();
+        match docker
+            .images()
+            .clear_cache(&ClearCacheOpts::default())
+            .await
+        {
+            Err(Error::UnsupportedApiVersion { required, .. }) => {
+                assert_eq!(required, ApiVersion::from((1, 31)))
+            }
+            res => panic!("Expected Error::UnsupportedApiVersion, got {res:?}"),
+        }
+
+        let docker = Docker::new_versioned("tcp://127.0.0.1:9", (1, 24)).unwrap();
+        match docker.containers().prune(&Default::default()).await {
+            Err(Error::UnsupportedApiVersion { required, .. }) => {
+                assert_eq!(required, ApiVersion::from((1, 25)))
+            }
+            res => panic!("Expected Error::UnsupportedApiVersion, got {res:?}"),
+        }
+        let opts = NetworkCreateOpts::builder("ingress").ingress(true).build();
+        match docker.networks().create(&opts).await {
+            Err(Error::UnsupportedApiVersion { required, .. }) => {
+                assert_eq!(required, ApiVersion::from((1, 29)))
+            }
+            res => panic!("Expected Error::UnsupportedApiVersion, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn finds_endpoint_api_versions() {
+        use super::endpoint_api_version;
+
+        let version = |endpoint| endpoint_api_version(endpoint).map(|v| v.to_string());
+        assert_eq!(version("/containers/json?all=true"), None);
+        assert_eq!(version("/containers/prune/json"), None);
+        assert_eq!(
+            version("/containers/prune?filters=%7B%7D").as_deref(),
+            Some("1.25")
+        );
+        assert_eq!(version("/configs").as_deref(), Some("1.30"));
+        assert_eq!(
+            version("/configs/abc/update?version=1").as_deref(),
+            Some("1.30")
+        );
+        assert_eq!(
+            version("/services/web/logs?stdout=true").as_deref(),
+            Some("1.29")
+        );
+        assert_eq!(version("/services/web").as_deref(), None);
+        assert_eq!(
+            version("/plugins/vieux/sshfs:latest/json").as_deref(),
+            Some("1.25")
+        );
+        assert_eq!(
+            version("/plugins/vieux/sshfs:latest/upgrade").as_deref(),
+            Some("1.26")
+        );
+        assert_eq!(
+            version("/distribution/library/alpine/json").as_deref(),
+            Some("1.30")
+        );
+    }
+
+    #[tokio::test]
+    async fn maps_error_responses() {
+        use hyper::StatusCode;
+
+        async fn validate(status: u16, body: &'static str) -> Error {
+            let response = Response::builder()
+                .status(status)
+                .body(Body::from(body))
+                .unwrap();
+            super::validate_response(response).await.unwrap_err()
+        }
+
+        let err = validate(404, r#"{"message":"No such container: test"}"#).await;
+        assert!(err.is_not_found());
+        assert!(!err.is_retryable());
+        assert_eq!(err.status_code(), Some(StatusCode::NOT_FOUND));
+        match err {
+            Error::NotFound { message } => assert_eq!(message, "No such container: test"),
+            e => panic!("Expected Error::NotFound, got {e}"),
+        }
+
+        assert!(validate(409, r#"{"message":"name in use"}"#)
+            .await
+            .is_conflict());
+        assert!(validate(304, "").await.is_not_modified());
+        assert!(matches!(
+            validate(400, "{}").await,
+            Error::BadParameter { message } if message == "Bad Request"
+        ));
+        assert!(matches!(
+            validate(401, "").await,
+            Error::Unauthorized { .. }
+        ));
+
+        let err = validate(503, r#"{"message":"unavailable"}"#).await;
+        assert!(err.is_retryable());
+        assert_eq!(err.status_code(), Some(StatusCode::SERVICE_UNAVAILABLE));
+
+        assert!(matches!(
+            validate(403, "").await,
+            Error::Fault { code, .. } if code == StatusCode::FORBIDDEN
+        ));
+    }
+
+    /// Serves every connection with the next response from `responses` and returns the address
+    /// of the server along with the received request heads.
+    pub(crate) fn serve(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, std::sync::mpsc::Receiver<String>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let _ = tx.send(String::from_utf8_lossy(&request).into_owned());
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} -\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (addr, rx)
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests() {
+        let unavailable = (503, r#"{"message":"unavailable"}"#);
+        let version = (200, r#"{"ApiVersion":"1.42"}"#);
+
+        let (addr, _) = serve(vec![unavailable, unavailable, version]);
+        let docker = Docker::builder(format!("tcp://{addr}"))
+            .retry_policy(RetryPolicy::new(2).initial_backoff(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+        let version = docker.version().await.unwrap();
+        assert_eq!(version.api_version.as_deref(), Some("1.42"));
+
+        let (addr, _) = serve(vec![unavailable, unavailable]);
+        let docker = Docker::builder(format!("tcp://{addr}"))
+            .retry_policy(RetryPolicy::new(1).initial_backoff(Duration::from_millis(1)))
+            .build()
+            .unwrap();
+        match docker.version().await.unwrap_err() {
+            Error::ServerError { message, .. } => assert_eq!(message, "unavailable"),
+            e => panic!("Expected Error::ServerError, got {e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn intercepts_requests() {
+        use crate::conn::{HeaderInterceptor, InterceptedResponse, Interceptor};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(String, String, Option<u16>)>>);
+
+        impl Interceptor for Arc<Recorder> {
+            fn after_response(&self, response: &InterceptedResponse<'_>) {
+                self.0.lock().unwrap().push((
+                    response.method.to_string(),
+                    response.endpoint.to_string(),
+                    response.status.map(|s| s.as_u16()),
+                ));
+            }
+        }
+
+        let (addr, requests) = serve(vec![
+            (200, r#"{"ApiVersion":"1.42"}"#),
+            (404, r#"{"message":"no such container"}"#),
+        ]);
+        let recorder = Arc::new(Recorder::default());
+        let docker = Docker::builder(format!("tcp://{addr}"))
+            .interceptor(HeaderInterceptor::new().user_agent("platform/1.0"))
+            .interceptor(recorder.clone())
+            .build()
+            .unwrap();
+
+        docker.version().await.unwrap();
+        let err = docker
+            .containers()
+            .get("foo")
+            .stop(&Default::default())
+            .await;
+        assert!(err.unwrap_err().is_not_found());
+
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.starts_with("get /version "));
+        assert!(request.contains("user-agent: platform/1.0\r\n"));
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                ("GET".into(), "/version".into(), Some(200)),
+                ("POST".into(), "/containers/foo/stop".into(), Some(404)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn authenticates_with_registry() {
+        use crate::opts::RegistryAuth;
+
+        let (addr, requests) = serve(vec![
+            (
+                200,
+                r#"{"Status":"Login Succeeded","IdentityToken":"9cbaf023786cd7"}"#,
+            ),
+            (401, r#"{"message":"incorrect username or password"}"#),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let auth = RegistryAuth::builder()
+            .username("user")
+            .password("pass")
+            .server_address("ghcr.io")
+            .build();
+
+        let response = docker.auth(&auth).await.unwrap();
+        assert_eq!(response.status, "Login Succeeded");
+        assert!(matches!(
+            response.registry_auth(),
+            Some(RegistryAuth::Token { identity_token }) if identity_token == "9cbaf023786cd7"
+        ));
+        assert!(requests
+            .recv()
+            .unwrap()
+            .to_lowercase()
+            .starts_with("post /auth "));
+
+        let err = docker.auth(&auth).await.unwrap_err();
+        assert!(matches!(err, Error::Unauthorized { .. }));
+    }
+
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn attaches_over_websocket() {
+        use crate::{conn::Message, opts::ContainerAttachOpts};
+        use futures_util::{SinkExt, StreamExt};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_tungstenite::{
+            tungstenite::{handshake::derive_accept_key, protocol::Role},
+            WebSocketStream,
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            let request = String::from_utf8(request).unwrap();
+            let key = request
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("sec-websocket-key")
+                        .then(|| value.trim().to_string())
+                })
+                .unwrap();
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                        derive_accept_key(key.as_bytes())
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+
+            let mut ws = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+            ws.send(Message::Binary(b"hello".to_vec())).await.unwrap();
+            let input = ws.next().await.unwrap().unwrap();
+            (request, input)
+        });
+
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let opts = ContainerAttachOpts::builder()
+            .stream(true)
+            .stdin(true)
+            .build();
+        let mut ws = docker
+            .containers()
+            .get("id")
+            .attach_ws(&opts)
+            .await
+            .unwrap();
+        assert_eq!(
+            ws.next().await.unwrap().unwrap(),
+            Message::Binary(b"hello".to_vec())
+        );
+        ws.send(Message::Text("ls\n".into())).await.unwrap();
+
+        let (request, input) = server.await.unwrap();
+        assert!(request
+            .to_lowercase()
+            .starts_with("get /containers/id/attach/ws?stdin=true&stream=true "));
+        assert_eq!(input, Message::Text("ls\n".into()));
+    }
+
+    #[tokio::test]
+    async fn resolves_registry_credentials() {
+        use crate::{
+            credentials::Credentials,
+            opts::{PullOpts, RegistryAuth},
+        };
+        use futures_util::TryStreamExt;
+
+        let config =
+            serde_json::from_str(r#"{"auths": {"ghcr.io": {"auth": "dXNlcjpwYXNz"}}}"#).unwrap();
+        let pulled = (200, r#"{"status":"Downloaded newer image"}"#);
+        let (addr, requests) = serve(vec![pulled, pulled, pulled]);
+        let docker = Docker::builder(format!("tcp://{addr}"))
+            .credentials(Credentials::new(config))
+            .build()
+            .unwrap();
+        let auth_header = |request: String| {
+            request
+                .lines()
+                .find_map(|line| line.strip_prefix("x-registry-auth: ").map(String::from))
+        };
+
+        let opts = PullOpts::builder().image("ghcr.io/owner/app").build();
+        let _: Vec<_> = docker.images().pull(&opts).try_collect().await.unwrap();
+        let expected = RegistryAuth::builder()
+            .username("user")
+            .password("pass")
+            .server_address("ghcr.io")
+            .build()
+            .serialize();
+        assert_eq!(auth_header(requests.recv().unwrap()), Some(expected));
+
+        let opts = PullOpts::builder().image("ubuntu").build();
+        let _: Vec<_> = docker.images().pull(&opts).try_collect().await.unwrap();
+        assert_eq!(auth_header(requests.recv().unwrap()), None);
+
+        // explicit credentials take precedence
+        let opts = PullOpts::builder()
+            .image("ghcr.io/owner/app")
+            .auth(RegistryAuth::token("abc"))
+            .build();
+        let _: Vec<_> = docker.images().pull(&opts).try_collect().await.unwrap();
+        assert_eq!(
+            auth_header(requests.recv().unwrap()),
+            Some(RegistryAuth::token("abc").serialize())
+        );
+    }
+
+    #[tokio::test]
+    async fn times_out_requests() {
+        // connections are accepted by the kernel but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let timeout = Duration::from_millis(50);
+        let docker = Docker::builder(format!("tcp://{addr}"))
+            .timeout(timeout)
+            .build()
+            .unwrap();
+        match docker.version().await.unwrap_err() {
+            Error::Timeout(t) => assert_eq!(t, timeout),
+            e => panic!("Expected Error::Timeout, got {e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn doesnt_time_out_reading_the_body() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let body = r#"{"StatusCode":0}"#;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            // the body is finished after the timeout elapsed, like the one of `/wait`
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            stream.write_all(body.as_bytes()).await.unwrap();
+        });
+
+        let docker = Docker::builder(format!("tcp://{addr}"))
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let response: crate::models::ContainerWaitResponse = docker
+            .post_json("/containers/web/wait", Payload::empty(), Headers::none())
+            .await
+            .unwrap();
+        assert_eq!(response.status_code, 0);
+    }
+
+    #[test]
+    fn computes_backoff() {
+        let policy = RetryPolicy::new(5)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1));
+        for (attempt, max) in [
+            (0, 100),
+            (1, 200),
+            (2, 400),
+            (3, 800),
+            (4, 1000),
+            (30, 1000),
+        ] {
+            let backoff = policy.backoff(attempt);
+            assert!(backoff >= Duration::from_millis(max / 2), "{backoff:?}");
+            assert!(backoff <= Duration::from_millis(max), "{backoff:?}");
+        }
+    }
 }
@@ -1,5 +1,7 @@
 //! Representations of various client errors
 
+use crate::ApiVersion;
+
 use hyper::{self, StatusCode};
 use serde_json::Error as SerdeError;
 use thiserror::Error as ThisError;
@@ -24,6 +26,20 @@ pub enum Error {
     InvalidResponse(String),
     #[error("error {code} - {message}")]
     Fault { code: StatusCode, message: String },
+    #[error("Not found - {message}")]
+    NotFound { message: String },
+    #[error("Conflict - {message}")]
+    Conflict { message: String },
+    #[error("Not modified - {message}")]
+    NotModified { message: String },
+    #[error("Bad parameter - {message}")]
+    BadParameter { message: String },
+    #[error("Unauthorized - {message}")]
+    Unauthorized { message: String },
+    #[error("Server error {code} - {message}")]
+    ServerError { code: StatusCode, message: String },
+    #[error("Request timed out after {0:?}")]
+    Timeout(std::time::Duration),
     #[error("The HTTP connection was not upgraded by the docker host")]
     ConnectionNotUpgraded,
     #[error("Provided scheme `{0}` is not supported")]
@@ -38,6 +54,24 @@ pub enum Error {
     InvalidPort(String),
     #[error("Invalid protocol - {0}")]
     InvalidProtocol(String),
+    #[error("Failed to find a docker host, tried: {}", .0.join(", "))]
+    DockerHostNotFound(Vec<String>),
+    #[error("A TLS connection was requested but the `tls` feature is not enabled")]
+    TlsNotEnabled,
+    #[error("Credential helper `{helper}` failed - {message}")]
+    CredentialHelper { helper: String, message: String },
+    #[error(
+        "Container exited with status code {code}{}",
+        .message.as_ref().map(|m| format!(" - {m}")).unwrap_or_default()
+    )]
+    ContainerExited { code: i64, message: Option<String> },
+    #[error("Context `{0}` not found")]
+    ContextNotFound(String),
+    #[error("API version {required} is required but {negotiated} is used")]
+    UnsupportedApiVersion {
+        required: ApiVersion,
+        negotiated: ApiVersion,
+    },
     #[error(transparent)]
     MalformedVersion(#[from] containers_api::version::Error),
     #[error(transparent)]
@@ -48,6 +82,63 @@ pub enum Error {
     StringError(String),
 }
 
+impl Error {
+    /// Creates an error from a non-successful response of the docker daemon.
+    pub(crate) fn from_status(code: StatusCode, message: String) -> Self {
+        match code {
+            StatusCode::NOT_FOUND => Error::NotFound { message },
+            StatusCode::CONFLICT => Error::Conflict { message },
+            StatusCode::NOT_MODIFIED => Error::NotModified { message },
+            StatusCode::BAD_REQUEST => Error::BadParameter { message },
+            StatusCode::UNAUTHORIZED => Error::Unauthorized { message },
+            code if code.is_server_error() => Error::ServerError { code, message },
+            code => Error::Fault { code, message },
+        }
+    }
+
+    /// Returns the status code of the response if this error was returned by the docker daemon.
+    pub fn status_code(&self) -> Option<StatusCode> {
+        match self {
+            Error::Fault { code, .. } | Error::ServerError { code, .. } => Some(*code),
+            Error::NotFound { .. } => Some(StatusCode::NOT_FOUND),
+            Error::Conflict { .. } => Some(StatusCode::CONFLICT),
+            Error::NotModified { .. } => Some(StatusCode::NOT_MODIFIED),
+            Error::BadParameter { .. } => Some(StatusCode::BAD_REQUEST),
+            Error::Unauthorized { .. } => Some(StatusCode::UNAUTHORIZED),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the requested object doesn't exist.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::NotFound { .. })
+    }
+
+    /// Returns `true` if the request conflicts with the current state of an object, for example
+    /// when a container name is already in use.
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, Error::Conflict { .. })
+    }
+
+    /// Returns `true` if the object already was in the requested state, for example when starting
+    /// a running container.
+    pub fn is_not_modified(&self) -> bool {
+        matches!(self, Error::NotModified { .. })
+    }
+
+    /// Returns `true` if the request might succeed when sent again, that is when the daemon
+    /// couldn't be reached, timed out or failed with a server error.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::ServerError { .. } | Error::Timeout(_) => true,
+            Error::Hyper(e) | Error::Error(containers_api::conn::Error::Hyper(e)) => {
+                e.is_connect() || e.is_closed() || e.is_incomplete_message()
+            }
+            _ => false,
+        }
+    }
+}
+
 impl Clone for Error {
     fn clone(&self) -> Self {
         match self {
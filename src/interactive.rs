@@ -0,0 +1,239 @@
+//! Interactive terminal sessions with containers, like `docker attach` or `docker exec -it`.
+//! Enabled with the `interactive` feature, only available on unix.
+//!
+//! While a session is running the local terminal is put into raw mode, standard input is
+//! forwarded to the container and its output is written to standard output and error. Size
+//! changes of the terminal (`SIGWINCH`) are forwarded with
+//! [`Container::resize`](crate::Container::resize) or [`Exec::resize`](crate::Exec::resize).
+//! The session ends once the container or exec instance closes its output.
+//!
+//! ```no_run
+//! # async fn demo(docker: docker_api::Docker) -> docker_api::Result<()> {
+//! let container = docker.containers().get("my-shell");
+//! docker_api::interactive::attach(&container).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    conn::{tty, TtyChunk},
+    opts::{ContainerResizeOpts, ExecResizeOpts, ExecStartOpts},
+    Container, Error, Exec, Result,
+};
+
+use futures_util::{
+    future::{self, Either},
+    io::{AsyncWrite, AsyncWriteExt},
+    Future, Stream, StreamExt,
+};
+use nix::{
+    errno::Errno,
+    libc,
+    sys::termios::{self, SetArg, Termios},
+};
+use tokio::{
+    io::AsyncWriteExt as _,
+    signal::unix::{signal, SignalKind},
+};
+
+use std::{os::unix::io::RawFd, sync::OnceLock};
+
+nix::ioctl_read_bad!(tiocgwinsz, libc::TIOCGWINSZ, libc::winsize);
+
+/// Returns the size of the local terminal as `(height, width)`, `None` if standard output is not
+/// a terminal.
+pub fn terminal_size() -> Option<(u16, u16)> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: `size` is a valid `winsize` that the ioctl writes to
+    unsafe { tiocgwinsz(libc::STDOUT_FILENO, &mut size) }.ok()?;
+    (size.ws_row > 0 && size.ws_col > 0).then_some((size.ws_row, size.ws_col))
+}
+
+/// Puts a terminal into raw mode and restores the previous mode when dropped.
+struct RawMode {
+    fd: RawFd,
+    original: Termios,
+}
+
+impl RawMode {
+    /// Enables raw mode if `fd` is a terminal.
+    fn enable(fd: RawFd) -> Result<Option<Self>> {
+        let original = match termios::tcgetattr(fd) {
+            Ok(original) => original,
+            Err(Errno::ENOTTY) => return Ok(None),
+            Err(e) => return Err(io_error(e)),
+        };
+        let mut raw = original.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(fd, SetArg::TCSANOW, &raw).map_err(io_error)?;
+        Ok(Some(Self { fd, original }))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(self.fd, SetArg::TCSANOW, &self.original);
+    }
+}
+
+fn io_error(errno: Errno) -> Error {
+    Error::IO(errno.into())
+}
+
+/// Attaches the local terminal to a running container. The container should be created with
+/// `tty`, `attach_stdin` and `open_stdin` enabled.
+pub async fn attach(container: &Container) -> Result<()> {
+    let multiplexer = container.attach().await?;
+    run(multiplexer, |height, width| async move {
+        let opts = ContainerResizeOpts::builder()
+            .height(height.into())
+            .width(width.into())
+            .build();
+        container.resize(&opts).await
+    })
+    .await
+}
+
+/// Starts an exec instance with the local terminal attached to it. The exec instance should be
+/// created with `tty` and `attach_stdin` enabled.
+pub async fn exec(exec: &Exec, opts: &ExecStartOpts) -> Result<()> {
+    let multiplexer = exec.start(opts).await?;
+    run(multiplexer, |height, width| async move {
+        let opts = ExecResizeOpts::builder()
+            .height(height.into())
+            .width(width.into())
+            .build();
+        exec.resize(&opts).await
+    })
+    .await
+}
+
+async fn run<F, Fut>(multiplexer: tty::Multiplexer, resize: F) -> Result<()>
+where
+    F: Fn(u16, u16) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let _raw_mode = RawMode::enable(libc::STDIN_FILENO)?;
+    let (reader, writer) = multiplexer.split();
+
+    let output = Box::pin(forward_output(reader));
+    let background = Box::pin(future::try_join(
+        forward_input(writer),
+        forward_resize(resize),
+    ));
+
+    match future::select(output, background).await {
+        Either::Left((result, _)) => result,
+        Either::Right((result, _)) => result.map(|_| ()),
+    }
+}
+
+async fn forward_output(
+    reader: impl Stream<Item = containers_api::conn::Result<TtyChunk>>,
+) -> Result<()> {
+    let mut stdout = tokio::io::stdout();
+    let mut stderr = tokio::io::stderr();
+    futures_util::pin_mut!(reader);
+
+    while let Some(chunk) = reader.next().await {
+        match chunk? {
+            TtyChunk::StdOut(bytes) => {
+                stdout.write_all(&bytes).await?;
+                stdout.flush().await?;
+            }
+            TtyChunk::StdErr(bytes) => {
+                stderr.write_all(&bytes).await?;
+                stderr.flush().await?;
+            }
+            TtyChunk::StdIn(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Chunks read from standard input, an empty chunk marks its end.
+type Input = std::io::Result<Vec<u8>>;
+
+/// Returns the receiving end of the process-wide reader of standard input, which is shared by
+/// all sessions.
+///
+/// Standard input is read on a detached thread rather than with [`tokio::io::stdin`], whose
+/// blocking read would keep the runtime from shutting down until another line is entered. The
+/// thread is started with the first session and keeps running between sessions, so input
+/// entered after a session ended is read by the next session instead of being lost.
+fn stdin_receiver() -> &'static tokio::sync::Mutex<tokio::sync::mpsc::Receiver<Input>> {
+    static STDIN: OnceLock<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<Input>>> =
+        OnceLock::new();
+    STDIN.get_or_init(|| {
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        std::thread::spawn(move || {
+            let mut stdin = std::io::stdin().lock();
+            let mut buf = [0; 1024];
+            loop {
+                let read = match std::io::Read::read(&mut stdin, &mut buf) {
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    read => read.map(|n| buf[..n].to_vec()),
+                };
+                let done = !matches!(&read, Ok(input) if !input.is_empty());
+                if tx.blocking_send(read).is_err() || done {
+                    break;
+                }
+            }
+        });
+        tokio::sync::Mutex::new(rx)
+    })
+}
+
+/// Copies standard input to the container and closes the container's input at the end of
+/// standard input. Only returns early if writing fails.
+async fn forward_input(writer: impl AsyncWrite) -> Result<()> {
+    let mut rx = stdin_receiver().lock().await;
+    futures_util::pin_mut!(writer);
+
+    loop {
+        // once the reader ended every later session sees the end of input right away
+        let input = rx.recv().await.transpose()?.unwrap_or_default();
+        if input.is_empty() {
+            writer.close().await?;
+            break;
+        }
+        writer.write_all(&input).await?;
+        writer.flush().await?;
+    }
+    future::pending().await
+}
+
+/// Resizes the TTY to the size of the local terminal initially and on every `SIGWINCH`. Only
+/// returns early if resizing fails.
+async fn forward_resize<F, Fut>(resize: F) -> Result<()>
+where
+    F: Fn(u16, u16) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut window_changes = signal(SignalKind::window_change())?;
+    loop {
+        if let Some((height, width)) = terminal_size() {
+            resize(height, width).await?;
+        }
+        if window_changes.recv().await.is_none() {
+            return future::pending().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::{io::AsRawFd, net::UnixStream};
+
+    #[test]
+    fn skips_raw_mode_without_terminal() {
+        let (socket, _) = UnixStream::pair().unwrap();
+        assert!(RawMode::enable(socket.as_raw_fd()).unwrap().is_none());
+    }
+}
@@ -32,16 +32,25 @@ macro_rules! version {
 mod builder;
 
 pub mod api;
-pub mod models;
-mod stream;
-pub mod conn {
-    //! Connection related items
-    pub(crate) use containers_api::conn::*;
-    pub use containers_api::conn::{Error, Transport, TtyChunk};
-}
+#[cfg(feature = "blocking")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+pub mod blocking;
+pub mod config_file;
+pub mod conn;
+pub mod context;
+pub mod credentials;
 pub mod docker;
 pub mod errors;
+#[cfg(all(unix, feature = "interactive"))]
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "interactive"))))]
+pub mod interactive;
+#[cfg(feature = "mock")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+pub mod mock;
+pub mod models;
 pub mod opts;
+mod stream;
+mod trace;
 
 pub use containers_api::id::Id;
 pub use containers_api::version::ApiVersion;
@@ -54,7 +63,7 @@ pub use crate::{
         network::{self, Network, Networks},
         volume::{self, Volume, Volumes},
     },
-    docker::Docker,
+    docker::{Docker, DockerBuilder, RetryPolicy},
     errors::{Error, Result},
 };
 
@@ -0,0 +1,1266 @@
+//! In-memory docker daemon for testing code written against [`Docker`](crate::Docker) without a
+//! real daemon. Enabled with the `mock` feature.
+//!
+//! The mock answers the core endpoints from an in-memory state:
+//!  - `/_ping` and `/version`
+//!  - containers: create, list, inspect, stats, start, stop, restart, kill and delete
+//!  - images: list, inspect, pull and delete
+//!  - networks: list, inspect, create and delete
+//!  - volumes: list, inspect, create and delete
+//!
+//! Containers don't run anything, starting one only changes its state and their stats are
+//! always a single sample without any resource usage. Requests to other
+//! endpoints fail with a `501 Not Implemented` response that is returned as
+//! [`Error::ServerError`](crate::Error::ServerError).
+//!
+//! ```
+//! # async {
+//! use docker_api::{mock::MockDocker, opts::ContainerCreateOpts};
+//!
+//! let daemon = MockDocker::new().with_image("ubuntu:22.04");
+//! let docker = daemon.docker();
+//!
+//! let container = docker
+//!     .containers()
+//!     .create(&ContainerCreateOpts::builder().image("ubuntu:22.04").build())
+//!     .await
+//!     .unwrap();
+//! container.start().await.unwrap();
+//!
+//! let info = container.inspect().await.unwrap();
+//! assert_eq!(info.state.unwrap().running, Some(true));
+//! # };
+//! ```
+
+use crate::{
+    conn::Transport,
+    docker::{ClientOpts, Docker},
+};
+
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Map, Value};
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const API_VERSION: &str = "1.42";
+const PREDEFINED_NETWORKS: &[(&str, &str)] =
+    &[("bridge", "bridge"), ("host", "host"), ("none", "null")];
+
+#[derive(Clone, Debug)]
+/// In-memory docker daemon. All clones and all [`Docker`](crate::Docker) clients created with
+/// [`MockDocker::docker`](MockDocker::docker) share the same state.
+pub struct MockDocker {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for MockDocker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockDocker {
+    /// Creates a new daemon without any containers, images or volumes. Like a real daemon it
+    /// has the predefined `bridge`, `host` and `none` networks.
+    pub fn new() -> Self {
+        let mut state = State::default();
+        for (name, driver) in PREDEFINED_NETWORKS {
+            let id = state.make_id();
+            state.networks.push(MockNetwork {
+                id,
+                name: name.to_string(),
+                driver: driver.to_string(),
+                labels: Map::new(),
+                internal: false,
+                attachable: false,
+                created: now(),
+            });
+        }
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Adds an image tagged with `reference`, for example `ubuntu` or `ubuntu:22.04`, to the
+    /// daemon. Containers can only be created from existing images.
+    pub fn with_image(self, reference: impl AsRef<str>) -> Self {
+        self.state()
+            .add_image(&normalize_reference(reference.as_ref()));
+        self
+    }
+
+    /// Creates a client connected to this daemon.
+    pub fn docker(&self) -> Docker {
+        Docker::new_with_transport(
+            Transport::Mock {
+                daemon: self.clone(),
+            },
+            ClientOpts::default(),
+        )
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Answers a request sent to the daemon.
+    pub(crate) async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let (parts, body) = request.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return Failure::new(StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let query: HashMap<String, String> = parts
+            .uri
+            .query()
+            .map(|q| {
+                url::form_urlencoded::parse(q.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let segments: Vec<_> = parts
+            .uri
+            .path()
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        // versioned endpoints look like `/v1.42/containers/json`
+        let segments = match segments.split_first() {
+            Some((version, rest)) if version.starts_with("v1.") => rest,
+            _ => &segments[..],
+        };
+        log::trace!("mock daemon: {} {}", parts.method, parts.uri);
+
+        let request = MockRequest {
+            method: &parts.method,
+            query: &query,
+            body: &body,
+        };
+        self.state()
+            .handle(request, segments)
+            .unwrap_or_else(Failure::into_response)
+    }
+}
+
+struct MockRequest<'a> {
+    method: &'a Method,
+    query: &'a HashMap<String, String>,
+    body: &'a [u8],
+}
+
+impl MockRequest<'_> {
+    fn flag(&self, key: &str) -> bool {
+        matches!(self.query.get(key).map(String::as_str), Some("1" | "true"))
+    }
+
+    fn json(&self) -> Result<Map<String, Value>, Failure> {
+        if self.body.is_empty() {
+            return Ok(Map::new());
+        }
+        match serde_json::from_slice(self.body) {
+            Ok(Value::Object(body)) => Ok(body),
+            Ok(_) => Err(Failure::new(
+                StatusCode::BAD_REQUEST,
+                "request body must be a JSON object",
+            )),
+            Err(e) => Err(Failure::new(StatusCode::BAD_REQUEST, e)),
+        }
+    }
+
+    /// Values of a filter from the `filters` parameter, either `{"key": ["value"]}` or
+    /// `{"key": {"value": true}}`.
+    fn filter(&self, key: &str) -> Vec<String> {
+        let filters: Value = self
+            .query
+            .get("filters")
+            .and_then(|f| serde_json::from_str(f).ok())
+            .unwrap_or_default();
+        match &filters[key] {
+            Value::Array(values) => values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect(),
+            Value::Object(values) => values.keys().cloned().collect(),
+            _ => vec![],
+        }
+    }
+}
+
+struct Failure {
+    status: StatusCode,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: StatusCode, message: impl ToString) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+
+    fn not_found(message: impl ToString) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    fn conflict(message: impl ToString) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    fn into_response(self) -> Response<Body> {
+        json(self.status, json!({ "message": self.message }))
+    }
+}
+
+type Reply = Result<Response<Body>, Failure>;
+
+fn json(status: StatusCode, value: Value) -> Response<Body> {
+    response(status, "application/json", value.to_string())
+}
+
+fn response(status: StatusCode, content_type: &str, body: impl Into<Body>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .header("Api-Version", API_VERSION)
+        .header(header::SERVER, "Docker/mock (linux)")
+        .body(body.into())
+        .expect("valid response")
+}
+
+fn no_content() -> Response<Body> {
+    response(StatusCode::NO_CONTENT, "text/plain", Body::empty())
+}
+
+#[derive(Debug, Default)]
+struct State {
+    containers: Vec<MockContainer>,
+    images: Vec<MockImage>,
+    networks: Vec<MockNetwork>,
+    volumes: Vec<MockVolume>,
+    last_id: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerStatus {
+    Created,
+    Running,
+    Exited,
+}
+
+#[derive(Debug)]
+struct MockContainer {
+    id: String,
+    name: String,
+    image: String,
+    image_id: String,
+    config: Map<String, Value>,
+    host_config: Value,
+    status: ContainerStatus,
+    exit_code: i64,
+    created: u64,
+    started_at: Option<u64>,
+    finished_at: Option<u64>,
+}
+
+#[derive(Debug)]
+struct MockImage {
+    id: String,
+    tags: Vec<String>,
+    created: u64,
+}
+
+#[derive(Debug)]
+struct MockNetwork {
+    id: String,
+    name: String,
+    driver: String,
+    labels: Map<String, Value>,
+    internal: bool,
+    attachable: bool,
+    created: u64,
+}
+
+#[derive(Debug)]
+struct MockVolume {
+    name: String,
+    driver: String,
+    labels: Map<String, Value>,
+    options: Map<String, Value>,
+    created: u64,
+}
+
+impl State {
+    fn handle(&mut self, req: MockRequest<'_>, segments: &[&str]) -> Reply {
+        match (req.method.as_str(), segments) {
+            ("GET" | "HEAD", ["_ping"]) => Ok(ping()),
+            ("GET", ["version"]) => Ok(version()),
+
+            ("GET", ["containers", "json"]) => Ok(self.list_containers(&req)),
+            ("POST", ["containers", "create"]) => self.create_container(&req),
+            ("GET", ["containers", id, "json"]) => self.inspect_container(id),
+            ("GET", ["containers", id, "stats"]) => self.container_stats(id),
+            ("POST", ["containers", id, "start"]) => self.start_container(id),
+            ("POST", ["containers", id, "stop"]) => self.stop_container(id, 0),
+            ("POST", ["containers", id, "kill"]) => self.kill_container(id),
+            ("POST", ["containers", id, "restart"]) => self.restart_container(id),
+            ("DELETE", ["containers", id]) => self.delete_container(&req, id),
+
+            ("GET", ["images", "json"]) => Ok(self.list_images()),
+            ("POST", ["images", "create"]) => self.pull_image(&req),
+            ("GET", ["images", name @ .., "json"]) if !name.is_empty() => {
+                self.inspect_image(&name.join("/"))
+            }
+            ("DELETE", ["images", name @ ..]) if !name.is_empty() => {
+                self.delete_image(&req, &name.join("/"))
+            }
+
+            ("GET", ["networks"]) => Ok(self.list_networks()),
+            ("POST", ["networks", "create"]) => self.create_network(&req),
+            ("GET", ["networks", id]) => self.inspect_network(id),
+            ("DELETE", ["networks", id]) => self.delete_network(id),
+
+            ("GET", ["volumes"]) => Ok(self.list_volumes()),
+            ("POST", ["volumes", "create"]) => self.create_volume(&req),
+            ("GET", ["volumes", name]) => self.inspect_volume(name),
+            ("DELETE", ["volumes", name]) => self.delete_volume(name),
+
+            (method, _) => Err(Failure::new(
+                StatusCode::NOT_IMPLEMENTED,
+                format!(
+                    "mock daemon doesn't implement {method} /{}",
+                    segments.join("/")
+                ),
+            )),
+        }
+    }
+
+    fn make_id(&mut self) -> String {
+        self.last_id += 1;
+        (0..4u8)
+            .map(|i| {
+                let mut hasher = DefaultHasher::new();
+                (self.last_id, i).hash(&mut hasher);
+                format!("{:016x}", hasher.finish())
+            })
+            .collect()
+    }
+
+    //####################################################################################################
+    // Containers
+    //####################################################################################################
+
+    fn container(&self, id: &str) -> Result<usize, Failure> {
+        let name = id.trim_start_matches('/');
+        self.containers
+            .iter()
+            .position(|c| c.id == id || c.name == name)
+            .or_else(|| unique_prefix(self.containers.iter().map(|c| c.id.as_str()), id))
+            .ok_or_else(|| Failure::not_found(format!("No such container: {id}")))
+    }
+
+    fn list_containers(&self, req: &MockRequest<'_>) -> Response<Body> {
+        let all = req.flag("all");
+        let ids = req.filter("id");
+        let names = req.filter("name");
+        let statuses = req.filter("status");
+        let labels = req.filter("label");
+
+        let containers: Vec<_> = self
+            .containers
+            .iter()
+            .filter(|c| all || c.status == ContainerStatus::Running || !statuses.is_empty())
+            .filter(|c| ids.is_empty() || ids.iter().any(|id| c.id.starts_with(id)))
+            .filter(|c| names.is_empty() || names.iter().any(|n| c.name.contains(n.as_str())))
+            .filter(|c| statuses.is_empty() || statuses.iter().any(|s| s == c.state()))
+            .filter(|c| labels.iter().all(|l| matches_label(&c.labels(), l)))
+            .map(MockContainer::summary)
+            .collect();
+        json(StatusCode::OK, Value::Array(containers))
+    }
+
+    fn create_container(&mut self, req: &MockRequest<'_>) -> Reply {
+        let mut config = req.json()?;
+        let image = match config.get("Image").and_then(Value::as_str) {
+            Some(image) if !image.is_empty() => image.to_string(),
+            _ => {
+                return Err(Failure::new(
+                    StatusCode::BAD_REQUEST,
+                    "Config cannot be empty in order to create a container",
+                ))
+            }
+        };
+        let image_id = self
+            .image(&image)
+            .map(|i| self.images[i].id.clone())
+            .map_err(|_| Failure::not_found(format!("No such image: {image}")))?;
+
+        let id = self.make_id();
+        let name = match req.query.get("name") {
+            Some(name) => name.trim_start_matches('/').to_string(),
+            None => format!("mock_container_{}", self.last_id),
+        };
+        if let Some(other) = self.containers.iter().find(|c| c.name == name) {
+            return Err(Failure::conflict(format!(
+                "Conflict. The container name \"/{name}\" is already in use by container \"{}\". \
+                 You have to remove (or rename) that container to be able to reuse that name.",
+                other.id
+            )));
+        }
+
+        let host_config = config.remove("HostConfig").unwrap_or_else(|| json!({}));
+        config.remove("NetworkingConfig");
+        config
+            .entry("Hostname")
+            .or_insert_with(|| Value::String(id[..12].to_string()));
+
+        self.containers.push(MockContainer {
+            id: id.clone(),
+            name,
+            image,
+            image_id,
+            config,
+            host_config,
+            status: ContainerStatus::Created,
+            exit_code: 0,
+            created: now(),
+            started_at: None,
+            finished_at: None,
+        });
+        Ok(json(
+            StatusCode::CREATED,
+            json!({ "Id": id, "Warnings": [] }),
+        ))
+    }
+
+    fn inspect_container(&self, id: &str) -> Reply {
+        let container = &self.containers[self.container(id)?];
+        Ok(json(StatusCode::OK, container.inspect()))
+    }
+
+    fn container_stats(&self, id: &str) -> Reply {
+        let container = &self.containers[self.container(id)?];
+        // streamed like the daemon does it, every sample on its own line
+        Ok(response(
+            StatusCode::OK,
+            "application/json",
+            format!("{}\n", container.stats()),
+        ))
+    }
+
+    fn start_container(&mut self, id: &str) -> Reply {
+        let idx = self.container(id)?;
+        let container = &mut self.containers[idx];
+        if container.status == ContainerStatus::Running {
+            return Ok(response(
+                StatusCode::NOT_MODIFIED,
+                "text/plain",
+                Body::empty(),
+            ));
+        }
+        container.status = ContainerStatus::Running;
+        container.started_at = Some(now());
+        container.exit_code = 0;
+        Ok(no_content())
+    }
+
+    fn stop_container(&mut self, id: &str, exit_code: i64) -> Reply {
+        let idx = self.container(id)?;
+        let container = &mut self.containers[idx];
+        if container.status != ContainerStatus::Running {
+            return Ok(response(
+                StatusCode::NOT_MODIFIED,
+                "text/plain",
+                Body::empty(),
+            ));
+        }
+        container.status = ContainerStatus::Exited;
+        container.finished_at = Some(now());
+        container.exit_code = exit_code;
+        Ok(no_content())
+    }
+
+    fn kill_container(&mut self, id: &str) -> Reply {
+        let container = &self.containers[self.container(id)?];
+        if container.status != ContainerStatus::Running {
+            return Err(Failure::conflict(format!(
+                "Container {} is not running",
+                container.id
+            )));
+        }
+        // SIGKILL
+        self.stop_container(id, 137)
+    }
+
+    fn restart_container(&mut self, id: &str) -> Reply {
+        self.stop_container(id, 0)?;
+        self.start_container(id)
+    }
+
+    fn delete_container(&mut self, req: &MockRequest<'_>, id: &str) -> Reply {
+        let idx = self.container(id)?;
+        if self.containers[idx].status == ContainerStatus::Running && !req.flag("force") {
+            return Err(Failure::conflict(format!(
+                "You cannot remove a running container {}. Stop the container before attempting \
+                 removal or force remove",
+                self.containers[idx].id
+            )));
+        }
+        self.containers.remove(idx);
+        Ok(no_content())
+    }
+
+    //####################################################################################################
+    // Images
+    //####################################################################################################
+
+    fn image(&self, reference: &str) -> Result<usize, Failure> {
+        let tag = normalize_reference(reference);
+        let id = reference.trim_start_matches("sha256:");
+        self.images
+            .iter()
+            .position(|i| i.tags.contains(&tag) || i.id.trim_start_matches("sha256:") == id)
+            .or_else(|| {
+                unique_prefix(
+                    self.images
+                        .iter()
+                        .map(|i| i.id.trim_start_matches("sha256:")),
+                    id,
+                )
+            })
+            .ok_or_else(|| Failure::not_found(format!("No such image: {reference}")))
+    }
+
+    fn add_image(&mut self, tag: &str) -> String {
+        if let Ok(idx) = self.image(tag) {
+            return self.images[idx].id.clone();
+        }
+        let id = format!("sha256:{}", self.make_id());
+        self.images.push(MockImage {
+            id: id.clone(),
+            tags: vec![tag.to_string()],
+            created: now(),
+        });
+        id
+    }
+
+    fn list_images(&self) -> Response<Body> {
+        let images: Vec<_> = self
+            .images
+            .iter()
+            .map(|i| {
+                json!({
+                    "Id": i.id,
+                    "ParentId": "",
+                    "RepoTags": i.tags,
+                    "RepoDigests": [],
+                    "Created": i.created,
+                    "Size": 0,
+                    "SharedSize": -1,
+                    "VirtualSize": 0,
+                    "Labels": {},
+                    "Containers": -1,
+                })
+            })
+            .collect();
+        json(StatusCode::OK, Value::Array(images))
+    }
+
+    fn inspect_image(&self, name: &str) -> Reply {
+        let image = &self.images[self.image(name)?];
+        Ok(json(
+            StatusCode::OK,
+            json!({
+                "Id": image.id,
+                "RepoTags": image.tags,
+                "RepoDigests": [],
+                "Parent": "",
+                "Comment": "",
+                "Created": rfc3339(image.created),
+                "DockerVersion": "",
+                "Author": "",
+                "Architecture": arch(),
+                "Os": "linux",
+                "Size": 0,
+                "VirtualSize": 0,
+                "Config": { "Labels": {} },
+                "RootFS": { "Type": "layers", "Layers": [] },
+            }),
+        ))
+    }
+
+    fn pull_image(&mut self, req: &MockRequest<'_>) -> Reply {
+        let image = match req.query.get("fromImage") {
+            Some(image) if !image.is_empty() => image,
+            _ => {
+                return Err(Failure::new(
+                    StatusCode::NOT_IMPLEMENTED,
+                    "mock daemon only supports pulling images with `fromImage`",
+                ))
+            }
+        };
+        let reference = match req.query.get("tag").filter(|tag| !tag.is_empty()) {
+            Some(tag) => normalize_reference(&format!("{image}:{tag}")),
+            None => normalize_reference(image),
+        };
+        let (repo, tag) = reference.rsplit_once(':').unwrap_or((&reference, "latest"));
+
+        let status = if self.image(&reference).is_ok() {
+            format!("Status: Image is up to date for {reference}")
+        } else {
+            self.add_image(&reference);
+            format!("Status: Downloaded newer image for {reference}")
+        };
+        let body = [
+            json!({ "status": format!("Pulling from {repo}"), "id": tag }),
+            json!({ "status": status }),
+        ]
+        .iter()
+        .map(|chunk| format!("{chunk}\r\n"))
+        .collect::<String>();
+        Ok(response(StatusCode::OK, "application/json", body))
+    }
+
+    fn delete_image(&mut self, req: &MockRequest<'_>, name: &str) -> Reply {
+        let idx = self.image(name)?;
+        let image = &self.images[idx];
+        if let Some(container) = self.containers.iter().find(|c| c.image_id == image.id) {
+            if !req.flag("force") {
+                return Err(Failure::conflict(format!(
+                    "conflict: unable to remove repository reference \"{name}\" (must force) - \
+                     container {} is using its referenced image {}",
+                    &container.id[..12],
+                    &image.id.trim_start_matches("sha256:")[..12]
+                )));
+            }
+        }
+
+        // removing one of multiple tags only untags the image
+        let tag = normalize_reference(name);
+        if image.tags.len() > 1 && image.tags.contains(&tag) {
+            self.images[idx].tags.retain(|t| t != &tag);
+            return Ok(json(StatusCode::OK, json!([{ "Untagged": tag }])));
+        }
+
+        let image = self.images.remove(idx);
+        let mut items: Vec<_> = image
+            .tags
+            .iter()
+            .map(|tag| json!({ "Untagged": tag }))
+            .collect();
+        items.push(json!({ "Deleted": image.id }));
+        Ok(json(StatusCode::OK, Value::Array(items)))
+    }
+
+    //####################################################################################################
+    // Networks
+    //####################################################################################################
+
+    fn network(&self, id: &str) -> Result<usize, Failure> {
+        self.networks
+            .iter()
+            .position(|n| n.id == id || n.name == id)
+            .or_else(|| unique_prefix(self.networks.iter().map(|n| n.id.as_str()), id))
+            .ok_or_else(|| Failure::not_found(format!("network {id} not found")))
+    }
+
+    fn list_networks(&self) -> Response<Body> {
+        let networks: Vec<_> = self.networks.iter().map(MockNetwork::inspect).collect();
+        json(StatusCode::OK, Value::Array(networks))
+    }
+
+    fn create_network(&mut self, req: &MockRequest<'_>) -> Reply {
+        let body = req.json()?;
+        let name = match body.get("Name").and_then(Value::as_str) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => {
+                return Err(Failure::new(
+                    StatusCode::BAD_REQUEST,
+                    "network name must be specified",
+                ))
+            }
+        };
+        if self.networks.iter().any(|n| n.name == name) {
+            return Err(Failure::conflict(format!(
+                "network with name {name} already exists"
+            )));
+        }
+
+        let id = self.make_id();
+        self.networks.push(MockNetwork {
+            id: id.clone(),
+            name,
+            driver: body
+                .get("Driver")
+                .and_then(Value::as_str)
+                .unwrap_or("bridge")
+                .to_string(),
+            labels: object(body.get("Labels")),
+            internal: body
+                .get("Internal")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            attachable: body
+                .get("Attachable")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            created: now(),
+        });
+        Ok(json(
+            StatusCode::CREATED,
+            json!({ "Id": id, "Warning": "" }),
+        ))
+    }
+
+    fn inspect_network(&self, id: &str) -> Reply {
+        let network = &self.networks[self.network(id)?];
+        Ok(json(StatusCode::OK, network.inspect()))
+    }
+
+    fn delete_network(&mut self, id: &str) -> Reply {
+        let idx = self.network(id)?;
+        let name = &self.networks[idx].name;
+        if PREDEFINED_NETWORKS.iter().any(|(n, _)| n == name) {
+            return Err(Failure::new(
+                StatusCode::FORBIDDEN,
+                format!("{name} is a pre-defined network and cannot be removed"),
+            ));
+        }
+        self.networks.remove(idx);
+        Ok(no_content())
+    }
+
+    //####################################################################################################
+    // Volumes
+    //####################################################################################################
+
+    fn volume(&self, name: &str) -> Result<usize, Failure> {
+        self.volumes
+            .iter()
+            .position(|v| v.name == name)
+            .ok_or_else(|| Failure::not_found(format!("get {name}: no such volume")))
+    }
+
+    fn list_volumes(&self) -> Response<Body> {
+        let volumes: Vec<_> = self.volumes.iter().map(MockVolume::inspect).collect();
+        json(
+            StatusCode::OK,
+            json!({ "Volumes": volumes, "Warnings": [] }),
+        )
+    }
+
+    fn create_volume(&mut self, req: &MockRequest<'_>) -> Reply {
+        let body = req.json()?;
+        let name = match body.get("Name").and_then(Value::as_str) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => self.make_id(),
+        };
+        // creating an existing volume returns it, just like the daemon does
+        if let Ok(idx) = self.volume(&name) {
+            return Ok(json(StatusCode::CREATED, self.volumes[idx].inspect()));
+        }
+
+        let volume = MockVolume {
+            name,
+            driver: body
+                .get("Driver")
+                .and_then(Value::as_str)
+                .unwrap_or("local")
+                .to_string(),
+            labels: object(body.get("Labels")),
+            options: object(body.get("DriverOpts")),
+            created: now(),
+        };
+        let inspect = volume.inspect();
+        self.volumes.push(volume);
+        Ok(json(StatusCode::CREATED, inspect))
+    }
+
+    fn inspect_volume(&self, name: &str) -> Reply {
+        let volume = &self.volumes[self.volume(name)?];
+        Ok(json(StatusCode::OK, volume.inspect()))
+    }
+
+    fn delete_volume(&mut self, name: &str) -> Reply {
+        let idx = self.volume(name)?;
+        self.volumes.remove(idx);
+        Ok(no_content())
+    }
+}
+
+impl MockContainer {
+    fn state(&self) -> &'static str {
+        match self.status {
+            ContainerStatus::Created => "created",
+            ContainerStatus::Running => "running",
+            ContainerStatus::Exited => "exited",
+        }
+    }
+
+    fn labels(&self) -> Map<String, Value> {
+        object(self.config.get("Labels"))
+    }
+
+    /// Entrypoint and command of the container.
+    fn command(&self) -> Vec<String> {
+        ["Entrypoint", "Cmd"]
+            .iter()
+            .flat_map(|key| match self.config.get(*key) {
+                Some(Value::Array(args)) => args
+                    .iter()
+                    .filter_map(|a| a.as_str().map(String::from))
+                    .collect(),
+                Some(Value::String(arg)) => vec![arg.clone()],
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn summary(&self) -> Value {
+        let status = match self.status {
+            ContainerStatus::Created => "Created".to_string(),
+            ContainerStatus::Running => "Up".to_string(),
+            ContainerStatus::Exited => format!("Exited ({})", self.exit_code),
+        };
+        json!({
+            "Id": self.id,
+            "Names": [format!("/{}", self.name)],
+            "Image": self.image,
+            "ImageID": self.image_id,
+            "Command": self.command().join(" "),
+            "Created": self.created,
+            "State": self.state(),
+            "Status": status,
+            "Labels": self.labels(),
+            "Ports": [],
+            "Mounts": [],
+        })
+    }
+
+    fn stats(&self) -> Value {
+        let running = self.status == ContainerStatus::Running;
+        let read = if running {
+            rfc3339(now())
+        } else {
+            ZERO_TIME.into()
+        };
+        json!({
+            "read": read,
+            "preread": ZERO_TIME,
+            "name": format!("/{}", self.name),
+            "id": self.id,
+            "pids_stats": { "current": u64::from(running) },
+            "cpu_stats": { "cpu_usage": { "total_usage": 0 }, "online_cpus": 1 },
+            "precpu_stats": { "cpu_usage": { "total_usage": 0 } },
+            "memory_stats": {},
+            "networks": {},
+            "blkio_stats": {},
+        })
+    }
+
+    fn inspect(&self) -> Value {
+        let mut command = self.command().into_iter();
+        let time = |t: Option<u64>| t.map(rfc3339).unwrap_or_else(|| ZERO_TIME.into());
+        json!({
+            "Id": self.id,
+            "Created": rfc3339(self.created),
+            "Path": command.next().unwrap_or_default(),
+            "Args": command.collect::<Vec<_>>(),
+            "State": {
+                "Status": self.state(),
+                "Running": self.status == ContainerStatus::Running,
+                "Paused": false,
+                "Restarting": false,
+                "OOMKilled": false,
+                "Dead": false,
+                "Pid": if self.status == ContainerStatus::Running { 1 } else { 0 },
+                "ExitCode": self.exit_code,
+                "Error": "",
+                "StartedAt": time(self.started_at),
+                "FinishedAt": time(self.finished_at),
+            },
+            "Image": self.image_id,
+            "Name": format!("/{}", self.name),
+            "RestartCount": 0,
+            "Driver": "overlay2",
+            "Platform": "linux",
+            "Config": self.config,
+            "HostConfig": self.host_config,
+            "Mounts": [],
+            "NetworkSettings": { "Networks": {} },
+        })
+    }
+}
+
+impl MockNetwork {
+    fn inspect(&self) -> Value {
+        json!({
+            "Name": self.name,
+            "Id": self.id,
+            "Created": rfc3339(self.created),
+            "Scope": "local",
+            "Driver": self.driver,
+            "EnableIPv6": false,
+            "IPAM": { "Driver": "default", "Config": [], "Options": {} },
+            "Internal": self.internal,
+            "Attachable": self.attachable,
+            "Ingress": false,
+            "Containers": {},
+            "Options": {},
+            "Labels": self.labels,
+        })
+    }
+}
+
+impl MockVolume {
+    fn inspect(&self) -> Value {
+        json!({
+            "Name": self.name,
+            "Driver": self.driver,
+            "Mountpoint": format!("/var/lib/docker/volumes/{}/_data", self.name),
+            "CreatedAt": rfc3339(self.created),
+            "Labels": self.labels,
+            "Scope": "local",
+            "Options": self.options,
+        })
+    }
+}
+
+fn ping() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header("Api-Version", API_VERSION)
+        .header("Docker-Experimental", "false")
+        .header(header::CACHE_CONTROL, "no-cache, no-store, must-revalidate")
+        .header(header::PRAGMA, "no-cache")
+        .header("Ostype", "linux")
+        .header(header::SERVER, "Docker/mock (linux)")
+        .header(header::DATE, http_date(now()))
+        .body(Body::from("OK"))
+        .expect("valid response")
+}
+
+fn version() -> Response<Body> {
+    json(
+        StatusCode::OK,
+        json!({
+            "Platform": { "Name": "Docker Engine - Mock" },
+            "Version": "mock",
+            "ApiVersion": API_VERSION,
+            "MinAPIVersion": "1.12",
+            "GitCommit": "mock",
+            "GoVersion": "",
+            "Os": "linux",
+            "Arch": arch(),
+            "KernelVersion": "",
+            "BuildTime": rfc3339(0),
+        }),
+    )
+}
+
+/// Adds the default `latest` tag to references without a tag or digest.
+fn normalize_reference(reference: &str) -> String {
+    let name = reference.rsplit('/').next().unwrap_or(reference);
+    if name.contains(':') || name.contains('@') {
+        reference.to_string()
+    } else {
+        format!("{reference}:latest")
+    }
+}
+
+/// Index of the only id starting with `prefix`.
+fn unique_prefix<'a>(ids: impl Iterator<Item = &'a str>, prefix: &str) -> Option<usize> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let mut matches = ids
+        .enumerate()
+        .filter(|(_, id)| id.starts_with(prefix))
+        .map(|(idx, _)| idx);
+    match (matches.next(), matches.next()) {
+        (Some(idx), None) => Some(idx),
+        _ => None,
+    }
+}
+
+/// Checks a `key` or `key=value` label filter.
+fn matches_label(labels: &Map<String, Value>, filter: &str) -> bool {
+    match filter.split_once('=') {
+        Some((key, value)) => labels.get(key).and_then(Value::as_str) == Some(value),
+        None => labels.contains_key(filter),
+    }
+}
+
+fn object(value: Option<&Value>) -> Map<String, Value> {
+    value
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        arch => arch,
+    }
+}
+
+//####################################################################################################
+// Time
+//####################################################################################################
+
+const ZERO_TIME: &str = "0001-01-01T00:00:00Z";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn datetime(secs: u64) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::from_timestamp(secs as i64, 0).unwrap_or_default()
+}
+
+fn rfc3339(secs: u64) -> String {
+    datetime(secs).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+fn http_date(secs: u64) -> String {
+    datetime(secs)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        opts::{
+            ContainerCreateOpts, ContainerListOpts, ContainerRemoveOpts, NetworkCreateOpts,
+            PullOpts, VolumeCreateOpts,
+        },
+        Error,
+    };
+    use futures_util::TryStreamExt;
+
+    #[test]
+    fn formats_time() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_792_266_651), "2026-10-17T19:50:51Z");
+        assert_eq!(http_date(1_792_266_651), "Sat, 17 Oct 2026 19:50:51 GMT");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn manages_containers() {
+        let docker = MockDocker::new().with_image("ubuntu:22.04").docker();
+        docker.ping().await.unwrap();
+        assert_eq!(
+            docker.version().await.unwrap().api_version.as_deref(),
+            Some(API_VERSION)
+        );
+
+        let opts = ContainerCreateOpts::builder()
+            .image("ubuntu")
+            .name("web")
+            .build();
+        assert!(docker
+            .containers()
+            .create(&opts)
+            .await
+            .unwrap_err()
+            .is_not_found());
+
+        let opts = ContainerCreateOpts::builder()
+            .image("ubuntu:22.04")
+            .name("web")
+            .command(["sleep", "infinity"])
+            .labels([("app", "web")])
+            .build();
+        let container = docker.containers().create(&opts).await.unwrap();
+        assert!(docker
+            .containers()
+            .create(&opts)
+            .await
+            .unwrap_err()
+            .is_conflict());
+        assert!(docker
+            .containers()
+            .list(&Default::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        container.start().await.unwrap();
+        assert!(container.start().await.unwrap_err().is_not_modified());
+        let running = docker.containers().list(&Default::default()).await.unwrap();
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].names, Some(vec!["/web".into()]));
+        assert_eq!(running[0].command.as_deref(), Some("sleep infinity"));
+
+        let info = docker.containers().get("web").inspect().await.unwrap();
+        assert_eq!(info.id.as_deref(), Some(container.id().as_ref()));
+        assert_eq!(info.path.as_deref(), Some("sleep"));
+        let state = info.state.unwrap();
+        assert_eq!(state.running, Some(true));
+
+        assert!(container.delete().await.unwrap_err().is_conflict());
+        container.kill(None).await.unwrap();
+        let info = container.inspect().await.unwrap();
+        assert_eq!(info.state.unwrap().exit_code, Some(137));
+
+        let opts = ContainerListOpts::builder().all(true).build();
+        assert_eq!(docker.containers().list(&opts).await.unwrap().len(), 1);
+        container
+            .remove(&ContainerRemoveOpts::builder().force(true).build())
+            .await
+            .unwrap();
+        assert!(container.inspect().await.unwrap_err().is_not_found());
+    }
+
+    #[tokio::test]
+    async fn snapshots_container_stats() {
+        let docker = MockDocker::new().with_image("ubuntu:22.04").docker();
+        let opts = ContainerCreateOpts::builder().image("ubuntu:22.04").build();
+        let mut ids = vec![];
+        for i in 0..20 {
+            let container = docker.containers().create(&opts).await.unwrap();
+            if i > 0 {
+                container.start().await.unwrap();
+            }
+            ids.push(container.id().to_string());
+        }
+
+        let stats = docker
+            .containers()
+            .stats_snapshot(&Default::default())
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 19);
+        assert!(!stats.contains_key(&ids[0]));
+        assert_eq!(stats[&ids[1]].id.as_ref(), Some(&ids[1]));
+        assert_eq!(stats[&ids[1]].pids_stats.current, Some(1));
+
+        let opts = ContainerListOpts::builder().all(true).build();
+        let stats = docker.containers().stats_snapshot(&opts).await.unwrap();
+        assert_eq!(stats.len(), 20);
+        assert_eq!(stats[&ids[0]].pids_stats.current, Some(0));
+    }
+
+    #[tokio::test]
+    async fn skips_containers_removed_before_their_stats() {
+        use crate::conn::{InterceptedRequest, Interceptor};
+
+        /// Removes a container once its stats are requested, after it was listed.
+        struct RemoveOnStats(MockDocker, String);
+
+        impl Interceptor for RemoveOnStats {
+            fn before_request(&self, request: &mut InterceptedRequest<'_>) {
+                if request
+                    .endpoint
+                    .contains(&format!("/containers/{}/stats", self.1))
+                {
+                    self.0.state().containers.retain(|c| c.id != self.1);
+                }
+            }
+        }
+
+        let mock = MockDocker::new().with_image("ubuntu:22.04");
+        let opts = ContainerCreateOpts::builder().image("ubuntu:22.04").build();
+        let mut ids = vec![];
+        for _ in 0..2 {
+            let container = mock.docker().containers().create(&opts).await.unwrap();
+            container.start().await.unwrap();
+            ids.push(container.id().to_string());
+        }
+
+        let docker = mock
+            .docker()
+            .with_interceptor(RemoveOnStats(mock.clone(), ids[0].clone()));
+        let stats = docker
+            .containers()
+            .stats_snapshot(&Default::default())
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert!(stats.contains_key(&ids[1]));
+    }
+
+    #[tokio::test]
+    async fn manages_images_networks_and_volumes() {
+        let docker = MockDocker::new().docker();
+
+        let opts = PullOpts::builder().image("alpine").tag("3.18").build();
+        let chunks: Vec<_> = docker.images().pull(&opts).try_collect().await.unwrap();
+        assert_eq!(chunks.len(), 2);
+        let images = docker.images().list(&Default::default()).await.unwrap();
+        assert_eq!(images[0].repo_tags, ["alpine:3.18"]);
+        let image = docker.images().get("alpine:3.18").inspect().await.unwrap();
+        assert_eq!(image.id.as_deref(), Some(images[0].id.as_str()));
+        assert_eq!(
+            docker
+                .images()
+                .get("alpine:3.18")
+                .delete()
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(docker
+            .images()
+            .list(&Default::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let network = docker
+            .networks()
+            .create(&NetworkCreateOpts::builder("backend").build())
+            .await
+            .unwrap();
+        let networks = docker.networks().list(&Default::default()).await.unwrap();
+        assert_eq!(networks.len(), 4);
+        assert_eq!(
+            network.inspect().await.unwrap().name.as_deref(),
+            Some("backend")
+        );
+        network.delete().await.unwrap();
+        assert!(matches!(
+            docker.networks().get("bridge").delete().await.unwrap_err(),
+            Error::Fault { code, .. } if code == StatusCode::FORBIDDEN
+        ));
+
+        let volume = docker
+            .volumes()
+            .create(&VolumeCreateOpts::builder().name("data").build())
+            .await
+            .unwrap();
+        assert_eq!(volume.name, "data");
+        let volumes = docker.volumes().list(&Default::default()).await.unwrap();
+        assert_eq!(volumes.volumes.unwrap().len(), 1);
+        docker.volumes().get("data").delete().await.unwrap();
+        assert!(docker
+            .volumes()
+            .get("data")
+            .inspect()
+            .await
+            .unwrap_err()
+            .is_not_found());
+
+        assert!(matches!(
+            docker.info().await.unwrap_err(),
+            Error::ServerError { code, .. } if code == StatusCode::NOT_IMPLEMENTED
+        ));
+    }
+}
@@ -2,12 +2,16 @@
 
 pub use docker_api_stubs::models::*;
 
-use crate::errors::{Error, Result};
+use crate::{
+    conn::TtyChunk,
+    errors::{Error, Result},
+    opts::RegistryAuth,
+};
 
 use hyper::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 
-use std::convert::TryFrom;
+use std::{borrow::Cow, collections::HashMap, convert::TryFrom, str};
 
 #[derive(Serialize, Debug)]
 pub struct PingInfo {
@@ -60,6 +64,364 @@ impl TryFrom<&HeaderMap> for PingInfo {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+/// Response of the docker daemon to a registry login, see [`Docker::auth`](crate::Docker::auth).
+pub struct AuthResponse {
+    #[serde(rename = "Status")]
+    /// The status of the authentication, for example `Login Succeeded`.
+    pub status: String,
+    #[serde(rename = "IdentityToken")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// An opaque token used to authenticate instead of the password, only returned by some
+    /// registries.
+    pub identity_token: Option<String>,
+}
+
+impl AuthResponse {
+    /// Returns registry credentials using the returned identity token if there is one.
+    pub fn registry_auth(&self) -> Option<RegistryAuth> {
+        self.identity_token
+            .as_ref()
+            .filter(|token| !token.is_empty())
+            .map(RegistryAuth::token)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Stream a log line was written to.
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A line of the logs of a container, service or task.
+pub struct LogLine {
+    /// Stream the line was written to. Containers with a TTY only have stdout.
+    pub stream: LogStream,
+    #[cfg(feature = "chrono")]
+    /// Time the line was written at, only set if timestamps were requested with
+    /// [`LogsOptsBuilder::timestamps`](crate::opts::LogsOptsBuilder::timestamps).
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    #[cfg(not(feature = "chrono"))]
+    /// RFC 3339 time the line was written at, only set if timestamps were requested with
+    /// [`LogsOptsBuilder::timestamps`](crate::opts::LogsOptsBuilder::timestamps).
+    pub timestamp: Option<String>,
+    /// Contents of the line including the trailing line break.
+    pub message: Vec<u8>,
+}
+
+impl LogLine {
+    /// Creates a log line from a chunk of the logs, splitting off the timestamp prefix if
+    /// `timestamps` is set.
+    pub(crate) fn new(chunk: TtyChunk, timestamps: bool) -> Self {
+        let (stream, mut message) = match chunk {
+            TtyChunk::StdErr(bytes) => (LogStream::Stderr, bytes),
+            TtyChunk::StdOut(bytes) | TtyChunk::StdIn(bytes) => (LogStream::Stdout, bytes),
+        };
+        let timestamp = if timestamps {
+            Self::split_timestamp(&mut message)
+        } else {
+            None
+        };
+        Self {
+            stream,
+            timestamp,
+            message,
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    fn split_timestamp(message: &mut Vec<u8>) -> Option<chrono::DateTime<chrono::Utc>> {
+        let end = message.iter().position(|b| *b == b' ')?;
+        let timestamp = str::from_utf8(&message[..end]).ok()?;
+        let timestamp = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+        message.drain(..=end);
+        Some(timestamp.with_timezone(&chrono::Utc))
+    }
+
+    #[cfg(not(feature = "chrono"))]
+    fn split_timestamp(message: &mut Vec<u8>) -> Option<String> {
+        let end = message.iter().position(|b| *b == b' ')?;
+        let timestamp = str::from_utf8(&message[..end]).ok()?.to_owned();
+        message.drain(..=end);
+        Some(timestamp)
+    }
+
+    /// Returns the contents of the line as a string, replacing invalid UTF-8 sequences.
+    pub fn message_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.message)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Output of a container run with [`Containers::run`](crate::Containers::run).
+pub struct ContainerRunOutput {
+    /// Exit code of the container.
+    pub status_code: i64,
+    /// Everything the container wrote to stdout, or all of its output if it has a TTY.
+    pub stdout: Vec<u8>,
+    /// Everything the container wrote to stderr.
+    pub stderr: Vec<u8>,
+}
+
+impl ContainerRunOutput {
+    /// Returns `true` if the container exited with status code 0.
+    pub fn success(&self) -> bool {
+        self.status_code == 0
+    }
+
+    /// Returns stdout as a string, replacing invalid UTF-8 sequences.
+    pub fn stdout_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+
+    /// Returns stderr as a string, replacing invalid UTF-8 sequences.
+    pub fn stderr_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Output of a command executed with [`Container::exec_output`](crate::Container::exec_output).
+pub struct ExecOutput {
+    /// Everything the process wrote to stdout, or all of its output if it has a TTY.
+    pub stdout: Vec<u8>,
+    /// Everything the process wrote to stderr.
+    pub stderr: Vec<u8>,
+    /// Exit code of the process.
+    pub exit_code: i64,
+}
+
+impl ExecOutput {
+    /// Returns `true` if the process exited with exit code 0.
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Returns stdout as a string, replacing invalid UTF-8 sequences.
+    pub fn stdout_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+
+    /// Returns stderr as a string, replacing invalid UTF-8 sequences.
+    pub fn stderr_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+/// Resource usage statistics of a container, see [`Container::stats`](crate::Container::stats).
+///
+/// The helper methods compute the values printed by `docker stats` and work with daemons using
+/// cgroup v1 as well as cgroup v2.
+pub struct ContainerStats {
+    /// Time the stats were read at.
+    pub read: String,
+    /// Time the previous stats were read at, `precpu_stats` were read at this time.
+    pub preread: String,
+    pub name: Option<String>,
+    pub id: Option<String>,
+    pub pids_stats: ContainerPidsStats,
+    pub cpu_stats: ContainerCpuStats,
+    pub precpu_stats: ContainerCpuStats,
+    pub memory_stats: ContainerMemoryStats,
+    /// Network statistics per interface, empty if the container has no own network stack.
+    pub networks: HashMap<String, ContainerNetworkStats>,
+    pub blkio_stats: ContainerBlkioStats,
+}
+
+impl ContainerStats {
+    /// Returns the CPU usage since the previous read in percent, where 100% equals one fully
+    /// used CPU.
+    pub fn cpu_percent(&self) -> f64 {
+        let cpu_delta = self
+            .cpu_stats
+            .cpu_usage
+            .total_usage
+            .saturating_sub(self.precpu_stats.cpu_usage.total_usage);
+        let system_delta = self
+            .cpu_stats
+            .system_cpu_usage
+            .unwrap_or_default()
+            .saturating_sub(self.precpu_stats.system_cpu_usage.unwrap_or_default());
+        if cpu_delta == 0 || system_delta == 0 {
+            return 0.0;
+        }
+        (cpu_delta as f64 / system_delta as f64) * self.cpu_stats.online_cpus() as f64 * 100.0
+    }
+
+    /// Returns the used memory in bytes without the inactive page cache.
+    pub fn memory_usage(&self) -> u64 {
+        let usage = self.memory_stats.usage.unwrap_or_default();
+        // cgroup v1 reports the cache as `total_inactive_file`, cgroup v2 as `inactive_file`
+        let cache = ["total_inactive_file", "inactive_file"]
+            .iter()
+            .find_map(|key| self.memory_stats.stats.get(*key))
+            .copied()
+            .filter(|cache| *cache < usage)
+            .unwrap_or_default();
+        usage - cache
+    }
+
+    /// Returns the used memory without the inactive page cache in percent of the limit.
+    pub fn memory_percent(&self) -> f64 {
+        match self.memory_stats.limit {
+            Some(limit) if limit > 0 => self.memory_usage() as f64 / limit as f64 * 100.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the total received and transmitted bytes of all network interfaces.
+    pub fn network_io(&self) -> (u64, u64) {
+        self.networks.values().fold((0, 0), |(rx, tx), network| {
+            (rx + network.rx_bytes, tx + network.tx_bytes)
+        })
+    }
+
+    #[cfg(feature = "chrono")]
+    /// Returns the received and transmitted bytes per second of all network interfaces since
+    /// `previous` stats of the same container. `None` if the read times can't be compared.
+    pub fn network_rate(&self, previous: &ContainerStats) -> Option<(f64, f64)> {
+        let read = chrono::DateTime::parse_from_rfc3339(&self.read).ok()?;
+        let previous_read = chrono::DateTime::parse_from_rfc3339(&previous.read).ok()?;
+        let seconds = (read - previous_read).num_nanoseconds()? as f64 / 1e9;
+        if seconds <= 0.0 {
+            return None;
+        }
+        let (rx, tx) = self.network_io();
+        let (previous_rx, previous_tx) = previous.network_io();
+        Some((
+            rx.saturating_sub(previous_rx) as f64 / seconds,
+            tx.saturating_sub(previous_tx) as f64 / seconds,
+        ))
+    }
+
+    /// Returns the total read and written bytes of all block devices.
+    pub fn block_io(&self) -> (u64, u64) {
+        self.blkio_stats
+            .io_service_bytes_recursive
+            .iter()
+            .flatten()
+            .fold((0, 0), |(read, write), entry| {
+                match entry.op.as_bytes().first().map(u8::to_ascii_lowercase) {
+                    Some(b'r') => (read + entry.value, write),
+                    Some(b'w') => (read, write + entry.value),
+                    _ => (read, write),
+                }
+            })
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerPidsStats {
+    /// Number of processes and threads in the container.
+    pub current: Option<u64>,
+    /// Maximum number of processes and threads allowed in the container.
+    pub limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerCpuStats {
+    pub cpu_usage: ContainerCpuUsage,
+    /// Total CPU time of the host in nanoseconds.
+    pub system_cpu_usage: Option<u64>,
+    pub online_cpus: Option<u32>,
+    pub throttling_data: ContainerThrottlingData,
+}
+
+impl ContainerCpuStats {
+    /// Returns the number of CPUs available to the container. Older daemons don't report
+    /// `online_cpus`, the number of per CPU usages is used instead.
+    pub fn online_cpus(&self) -> u32 {
+        match self.online_cpus {
+            Some(cpus) if cpus > 0 => cpus,
+            _ => self
+                .cpu_usage
+                .percpu_usage
+                .as_ref()
+                .map(|usage| usage.len() as u32)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerCpuUsage {
+    /// Total CPU time used by the container in nanoseconds.
+    pub total_usage: u64,
+    /// CPU time used per CPU in nanoseconds, only reported with cgroup v1.
+    pub percpu_usage: Option<Vec<u64>>,
+    pub usage_in_kernelmode: u64,
+    pub usage_in_usermode: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerThrottlingData {
+    pub periods: u64,
+    pub throttled_periods: u64,
+    /// Time the container was throttled for in nanoseconds.
+    pub throttled_time: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerMemoryStats {
+    /// Used memory in bytes including the page cache.
+    pub usage: Option<u64>,
+    /// Maximum used memory in bytes, only reported with cgroup v1.
+    pub max_usage: Option<u64>,
+    /// Detailed memory statistics of the cgroup, the keys differ between cgroup v1 and v2.
+    pub stats: HashMap<String, u64>,
+    pub failcnt: Option<u64>,
+    /// Memory limit of the container in bytes.
+    pub limit: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerNetworkStats {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_dropped: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+/// Block IO statistics, cgroup v2 daemons only report `io_service_bytes_recursive` and
+/// `io_serviced_recursive`.
+pub struct ContainerBlkioStats {
+    pub io_service_bytes_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub io_serviced_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub io_queue_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub io_service_time_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub io_wait_time_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub io_merged_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub io_time_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+    pub sectors_recursive: Option<Vec<ContainerBlkioStatEntry>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContainerBlkioStatEntry {
+    pub major: u64,
+    pub minor: u64,
+    /// Operation of the entry, for example `Read` or `Write` with cgroup v1 and `read` or
+    /// `write` with cgroup v2.
+    pub op: String,
+    pub value: u64,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 /// Represents a response chunk from Docker api when building, pulling or importing an image.
@@ -101,4 +463,116 @@ pub struct ProgressDetail {
     pub total: Option<u64>,
 }
 
-pub type Labels = std::collections::HashMap<String, String>;
+pub type Labels = HashMap<String, String>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_cgroup_v1_stats() {
+        let stats: ContainerStats = serde_json::from_str(
+            r#"{
+                "read": "2023-01-01T00:00:02Z",
+                "preread": "2023-01-01T00:00:01Z",
+                "pids_stats": {"current": 3},
+                "blkio_stats": {
+                    "io_service_bytes_recursive": [
+                        {"major": 8, "minor": 0, "op": "Read", "value": 4096},
+                        {"major": 8, "minor": 0, "op": "Write", "value": 1024},
+                        {"major": 8, "minor": 0, "op": "Sync", "value": 5120},
+                        {"major": 8, "minor": 0, "op": "Total", "value": 5120}
+                    ],
+                    "io_queue_recursive": []
+                },
+                "cpu_stats": {
+                    "cpu_usage": {"total_usage": 300, "percpu_usage": [100, 200]},
+                    "system_cpu_usage": 2000
+                },
+                "precpu_stats": {
+                    "cpu_usage": {"total_usage": 100, "percpu_usage": [50, 50]},
+                    "system_cpu_usage": 1000
+                },
+                "memory_stats": {
+                    "usage": 1000,
+                    "max_usage": 1500,
+                    "stats": {"cache": 300, "total_inactive_file": 200},
+                    "limit": 4000
+                },
+                "networks": {
+                    "eth0": {"rx_bytes": 100, "tx_bytes": 50},
+                    "eth1": {"rx_bytes": 20, "tx_bytes": 10}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(stats.cpu_percent(), 40.0);
+        assert_eq!(stats.memory_usage(), 800);
+        assert_eq!(stats.memory_percent(), 20.0);
+        assert_eq!(stats.network_io(), (120, 60));
+        assert_eq!(stats.block_io(), (4096, 1024));
+        assert_eq!(stats.pids_stats.current, Some(3));
+    }
+
+    #[test]
+    fn computes_cgroup_v2_stats() {
+        let stats: ContainerStats = serde_json::from_str(
+            r#"{
+                "read": "2023-01-01T00:00:02.5Z",
+                "preread": "2023-01-01T00:00:01.5Z",
+                "pids_stats": {"current": 1, "limit": 100},
+                "blkio_stats": {
+                    "io_service_bytes_recursive": [
+                        {"major": 8, "minor": 0, "op": "read", "value": 10},
+                        {"major": 8, "minor": 0, "op": "write", "value": 20}
+                    ],
+                    "io_serviced_recursive": null,
+                    "io_queue_recursive": null
+                },
+                "cpu_stats": {
+                    "cpu_usage": {"total_usage": 500},
+                    "system_cpu_usage": 10000,
+                    "online_cpus": 4
+                },
+                "precpu_stats": {"cpu_usage": {"total_usage": 0}},
+                "memory_stats": {
+                    "usage": 1000,
+                    "stats": {"file": 400, "inactive_file": 250},
+                    "limit": 2000
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(stats.cpu_percent(), 20.0);
+        assert_eq!(stats.memory_usage(), 750);
+        assert_eq!(stats.memory_percent(), 37.5);
+        assert_eq!(stats.network_io(), (0, 0));
+        assert_eq!(stats.block_io(), (10, 20));
+        assert_eq!(stats.precpu_stats.online_cpus(), 0);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn computes_network_rate() {
+        let stats = |read: &str, rx_bytes, tx_bytes| ContainerStats {
+            read: read.into(),
+            networks: [(
+                "eth0".to_string(),
+                ContainerNetworkStats {
+                    rx_bytes,
+                    tx_bytes,
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let previous = stats("2023-01-01T00:00:00Z", 1000, 500);
+        let current = stats("2023-01-01T00:00:02Z", 3000, 600);
+
+        assert_eq!(current.network_rate(&previous), Some((1000.0, 50.0)));
+        assert_eq!(previous.network_rate(&current), None);
+    }
+}
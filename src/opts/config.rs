@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 
 impl_opts_builder!(url => ConfigList);
 
+impl_api_versions!(ConfigListOpts);
+
 pub enum ConfigFilter {
     /// The ID of the config.
     Id(String),
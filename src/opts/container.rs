@@ -1,9 +1,12 @@
-use crate::models::{DeviceRequest, Labels, NetworkingConfig};
-use crate::opts::ImageName;
+use crate::models::{
+    DeviceRequest, Labels, NetworkingConfig, ResourcesBlkioWeightDeviceInlineItem,
+    ResourcesUlimitsInlineItem, ThrottleDevice,
+};
+use crate::opts::{ImageName, PullOpts, RegistryAuth};
 use containers_api::opts::{Filter, FilterItem};
 use containers_api::{
     impl_field, impl_filter_func, impl_map_field, impl_opts_builder, impl_str_enum_field,
-    impl_str_field, impl_url_bool_field, impl_url_str_field, impl_vec_field,
+    impl_str_field, impl_url_bool_field, impl_url_field, impl_url_str_field, impl_vec_field,
 };
 
 use std::net::SocketAddr;
@@ -142,6 +145,8 @@ impl Filter for ContainerFilter {
 
 impl_opts_builder!(url => ContainerList);
 
+impl_api_versions!(ContainerListOpts);
+
 impl ContainerListOptsBuilder {
     impl_filter_func!(
         /// Filter the list of containers by one of the enum variants.
@@ -170,6 +175,14 @@ pub struct ContainerCreateOpts {
     params: HashMap<&'static str, Value>,
 }
 
+impl_api_versions!(ContainerCreateOpts params {
+    "HostConfig.AutoRemove" => (1, 25),
+    "HostConfig.Init" => (1, 25),
+    "HostConfig.NanoCpus" => (1, 25),
+    "StopTimeout" => (1, 25),
+    "HostConfig.DeviceRequests" => (1, 40),
+});
+
 /// Function to insert a JSON value into a tree where the desired
 /// location of the value is given as a path of JSON keys.
 fn insert<'a, I, V>(key_path: &mut Peekable<I>, value: &V, parent_node: &mut Value)
@@ -236,6 +249,11 @@ impl ContainerCreateOpts {
     pub(crate) fn name(&self) -> Option<&str> {
         self.name.as_deref()
     }
+
+    /// Returns the image of the container.
+    pub(crate) fn image(&self) -> Option<&str> {
+        self.params.get("Image").and_then(Value::as_str)
+    }
 }
 
 #[derive(Default)]
@@ -775,6 +793,8 @@ impl ContainerCommitOptsBuilder {
 
 impl_opts_builder!(url => ContainerStop);
 
+impl_api_versions!(ContainerStopOpts params { "signal" => (1, 42) });
+
 impl ContainerStopOptsBuilder {
     impl_url_str_field!(
         /// Signal to send to the container as an integer or string (e.g. `SIGINT`).
@@ -790,6 +810,8 @@ impl ContainerStopOptsBuilder {
 
 impl_opts_builder!(url => ContainerRestart);
 
+impl_api_versions!(ContainerRestartOpts params { "signal" => (1, 42) });
+
 impl ContainerRestartOptsBuilder {
     impl_url_str_field!(
         /// Signal to send to the container as an integer or string (e.g. `SIGINT`).
@@ -803,6 +825,333 @@ impl ContainerRestartOptsBuilder {
     }
 }
 
+impl_opts_builder!(url => ContainerResize);
+
+impl ContainerResizeOptsBuilder {
+    impl_url_field!(
+        /// Height of the TTY session in characters.
+        height: u64 => "h"
+    );
+
+    impl_url_field!(
+        /// Width of the TTY session in characters.
+        width: u64 => "w"
+    );
+}
+
+impl_opts_builder!(url => ContainerAttach);
+
+impl ContainerAttachOpts {
+    /// Returns the bytes of the detach sequence if stdin is attached. Uses the default sequence
+    /// of the daemon, `ctrl-p,ctrl-q`, if no detach keys were set.
+    pub(crate) fn detach_sequence(&self) -> Result<Option<Vec<u8>>> {
+        if self.params.get("stdin").map(String::as_str) != Some("true") {
+            return Ok(None);
+        }
+        detach_sequence(self.params.get("detachKeys").map(String::as_str)).map(Some)
+    }
+}
+
+/// Returns the bytes of a detach key sequence like `ctrl-p,ctrl-q`, the default sequence of the
+/// daemon if `keys` is `None`.
+pub(crate) fn detach_sequence(keys: Option<&str>) -> Result<Vec<u8>> {
+    let keys = keys.unwrap_or("ctrl-p,ctrl-q");
+    let invalid = || Error::BadParameter {
+        message: format!("invalid detach keys `{keys}`"),
+    };
+
+    keys.split(',')
+        .map(|key| match key.strip_prefix("ctrl-") {
+            Some(ctrl) if ctrl.len() == 1 => match ctrl.as_bytes()[0] {
+                c @ b'a'..=b'z' => Ok(c - b'a' + 1),
+                c @ b'@'..=b'_' if !c.is_ascii_uppercase() => Ok(c - b'@'),
+                _ => Err(invalid()),
+            },
+            _ if key.len() == 1 => Ok(key.as_bytes()[0]),
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+impl ContainerAttachOptsBuilder {
+    impl_url_str_field!(
+        /// Override the key sequence for detaching a container. Format is a single character
+        /// `[a-Z]` or `ctrl-<value>` where `<value>` is one of: `a-z`, `@`, `^`, `[`, `,` or `_`.
+        /// Multiple keys are separated by commas, for example `ctrl-x,x`.
+        detach_keys => "detachKeys"
+    );
+
+    impl_url_bool_field!(
+        /// Replay previous logs from the container.
+        logs => "logs"
+    );
+
+    impl_url_bool_field!(
+        /// Stream attached streams from the time the request was made onwards.
+        stream => "stream"
+    );
+
+    impl_url_bool_field!(
+        /// Attach to stdin.
+        stdin => "stdin"
+    );
+
+    impl_url_bool_field!(
+        /// Attach to stdout.
+        stdout => "stdout"
+    );
+
+    impl_url_bool_field!(
+        /// Attach to stderr.
+        stderr => "stderr"
+    );
+}
+
+impl_opts_builder!(url => ContainerStats);
+
+impl_api_versions!(ContainerStatsOpts params { "one-shot" => (1, 41) });
+
+impl ContainerStatsOptsBuilder {
+    impl_url_bool_field!(
+        /// Stream the stats. If false, the stats are returned once and the connection closed.
+        stream => "stream"
+    );
+
+    impl_url_bool_field!(
+        /// Only get a single stat instead of waiting for 2 cycles, so `precpu_stats` are empty.
+        /// Must be used with `stream` set to false.
+        one_shot => "one-shot"
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Condition to wait for with [`Container::wait_with`](crate::Container::wait_with).
+pub enum WaitCondition {
+    /// Wait until the container isn't running, returns immediately if it is stopped already.
+    NotRunning,
+    /// Wait for the next exit of the container, even if it isn't running yet.
+    NextExit,
+    /// Wait until the container is removed.
+    Removed,
+}
+
+impl AsRef<str> for WaitCondition {
+    fn as_ref(&self) -> &str {
+        match &self {
+            WaitCondition::NotRunning => "not-running",
+            WaitCondition::NextExit => "next-exit",
+            WaitCondition::Removed => "removed",
+        }
+    }
+}
+
+impl_opts_builder!(url => ContainerWait);
+
+impl_api_versions!(ContainerWaitOpts params { "condition" => (1, 30) });
+
+impl ContainerWaitOptsBuilder {
+    /// Condition to wait for, defaults to [`WaitCondition::NotRunning`](WaitCondition::NotRunning).
+    pub fn condition(mut self, condition: WaitCondition) -> Self {
+        self.params
+            .insert("condition", condition.as_ref().to_owned());
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Options for running a container with [`Containers::run`](crate::Containers::run).
+pub struct RunOpts {
+    pull: bool,
+    remove: bool,
+    auth: Option<RegistryAuth>,
+}
+
+impl Default for RunOpts {
+    fn default() -> Self {
+        Self {
+            pull: true,
+            remove: true,
+            auth: None,
+        }
+    }
+}
+
+impl RunOpts {
+    /// Returns a builder of options that by default pull a missing image and remove the
+    /// container once it exited.
+    pub fn builder() -> RunOptsBuilder {
+        RunOptsBuilder::default()
+    }
+
+    pub(crate) fn pulls(&self) -> bool {
+        self.pull
+    }
+
+    pub(crate) fn removes(&self) -> bool {
+        self.remove
+    }
+
+    /// Returns the options to pull `image`, which may include a tag or digest.
+    pub(crate) fn pull_opts(&self, image: &str) -> PullOpts {
+        let (name, tag) = if let Some((name, digest)) = image.split_once('@') {
+            (name, digest)
+        } else {
+            match image.rsplit_once(':') {
+                Some((name, tag)) if !tag.contains('/') => (name, tag),
+                _ => (image, "latest"),
+            }
+        };
+        let mut opts = PullOpts::builder().image(name).tag(tag);
+        if let Some(auth) = self.auth.clone() {
+            opts = opts.auth(auth);
+        }
+        opts.build()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Builder of [`RunOpts`](RunOpts).
+pub struct RunOptsBuilder {
+    opts: RunOpts,
+}
+
+impl RunOptsBuilder {
+    /// Whether to pull the image if it doesn't exist locally. (Default: true)
+    pub fn pull(mut self, pull: bool) -> Self {
+        self.opts.pull = pull;
+        self
+    }
+
+    /// Whether to remove the container once it exited. The container is always removed if
+    /// running it fails or is cancelled. (Default: true)
+    pub fn remove(mut self, remove: bool) -> Self {
+        self.opts.remove = remove;
+        self
+    }
+
+    /// Credentials used to pull the image.
+    pub fn auth(mut self, auth: RegistryAuth) -> Self {
+        self.opts.auth = Some(auth);
+        self
+    }
+
+    /// Creates the options.
+    pub fn build(self) -> RunOpts {
+        self.opts
+    }
+}
+
+impl_opts_builder!(json => ContainerUpdate);
+
+impl ContainerUpdateOptsBuilder {
+    impl_field!(
+        /// Memory limit in bytes.
+        memory: u64 => "Memory"
+    );
+
+    impl_field!(
+        /// Total memory limit (memory + swap) in bytes. Set to `-1` to enable unlimited swap.
+        memory_swap: i64 => "MemorySwap"
+    );
+
+    impl_field!(
+        /// Memory soft limit in bytes.
+        memory_reservation: u64 => "MemoryReservation"
+    );
+
+    impl_field!(
+        /// CPU quota in units of 10<sup>-9</sup> CPUs.
+        nano_cpus: u64 => "NanoCpus"
+    );
+
+    /// CPU quota in units of CPUs. This is a wrapper around `nano_cpus` to do the unit conversion.
+    ///
+    /// See [`nano_cpus`](#method.nano_cpus).
+    pub fn cpus(self, cpus: f64) -> Self {
+        self.nano_cpus((1_000_000_000.0 * cpus) as u64)
+    }
+
+    impl_field!(
+        /// An integer value representing this container's relative CPU weight versus other
+        /// containers.
+        cpu_shares: u32 => "CpuShares"
+    );
+
+    impl_field!(
+        /// The length of a CPU period in microseconds.
+        cpu_period: u64 => "CpuPeriod"
+    );
+
+    impl_field!(
+        /// Microseconds of CPU time that the container can get in a CPU period.
+        cpu_quota: i64 => "CpuQuota"
+    );
+
+    impl_str_field!(
+        /// CPUs in which to allow execution (e.g., `0-3`, `0,1`).
+        cpuset_cpus => "CpusetCpus"
+    );
+
+    impl_str_field!(
+        /// Memory nodes (MEMs) in which to allow execution (`0-3`, `0,1`). Only effective on NUMA
+        /// systems.
+        cpuset_mems => "CpusetMems"
+    );
+
+    impl_field!(
+        /// Tune the container's PIDs limit. Set `0` or `-1` for unlimited.
+        pids_limit: i64 => "PidsLimit"
+    );
+
+    impl_field!(
+        /// Block IO weight (relative weight), between 10 and 1000.
+        blkio_weight: u16 => "BlkioWeight"
+    );
+
+    impl_field!(
+        /// Block IO weight of specific devices (relative device weight).
+        blkio_weight_device: Vec<ResourcesBlkioWeightDeviceInlineItem> => "BlkioWeightDevice"
+    );
+
+    impl_field!(
+        /// Limit read rate (bytes per second) from devices.
+        blkio_device_read_bps: Vec<ThrottleDevice> => "BlkioDeviceReadBps"
+    );
+
+    impl_field!(
+        /// Limit write rate (bytes per second) to devices.
+        blkio_device_write_bps: Vec<ThrottleDevice> => "BlkioDeviceWriteBps"
+    );
+
+    impl_field!(
+        /// Limit read rate (IO per second) from devices.
+        blkio_device_read_iops: Vec<ThrottleDevice> => "BlkioDeviceReadIOps"
+    );
+
+    impl_field!(
+        /// Limit write rate (IO per second) to devices.
+        blkio_device_write_iops: Vec<ThrottleDevice> => "BlkioDeviceWriteIOps"
+    );
+
+    impl_field!(
+        /// A list of resource limits to set in the container, for example
+        /// `{"Name": "nofile", "Soft": 1024, "Hard": 2048}`.
+        ulimits: Vec<ResourcesUlimitsInlineItem> => "Ulimits"
+    );
+
+    /// The behavior to apply when the container exits. `maximum_retry_count` is only used with
+    /// the `on-failure` policy.
+    pub fn restart_policy(mut self, name: &str, maximum_retry_count: u64) -> Self {
+        let policy = if name == "on-failure" {
+            json!({ "Name": name, "MaximumRetryCount": maximum_retry_count })
+        } else {
+            json!({ "Name": name })
+        };
+        self.params.insert("RestartPolicy", policy);
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -928,4 +1277,122 @@ mod tests {
             r#"{"HostConfig":{"RestartPolicy":{"Name":"always"}},"Image":"test_image"}"#
         );
     }
+
+    #[test]
+    fn update_container_opts() {
+        let opts = ContainerUpdateOpts::builder()
+            .memory(512 * 1024 * 1024)
+            .memory_swap(-1)
+            .cpus(1.5)
+            .pids_limit(100)
+            .ulimits(vec![ResourcesUlimitsInlineItem {
+                name: Some("nofile".into()),
+                soft: Some(1024),
+                hard: Some(2048),
+            }])
+            .restart_policy("on-failure", 3)
+            .build();
+
+        let want = json!({
+            "Memory": 536870912,
+            "MemorySwap": -1,
+            "NanoCpus": 1500000000,
+            "PidsLimit": 100,
+            "Ulimits": [{"Name": "nofile", "Soft": 1024, "Hard": 2048}],
+            "RestartPolicy": {"Name": "on-failure", "MaximumRetryCount": 3},
+        });
+        let got: Value = serde_json::from_str(&opts.serialize().unwrap()).unwrap();
+        pretty_assertions::assert_eq!(want, got);
+
+        let opts = ContainerUpdateOpts::builder()
+            .restart_policy("always", 3)
+            .build();
+        pretty_assertions::assert_eq!(
+            r#"{"RestartPolicy":{"Name":"always"}}"#,
+            opts.serialize().unwrap()
+        );
+    }
+
+    #[test]
+    fn resize_container_opts() {
+        let opts = ContainerResizeOpts::builder().height(40).build();
+        assert_eq!(opts.serialize().as_deref(), Some("h=40"));
+    }
+
+    #[test]
+    fn stats_container_opts() {
+        let opts = ContainerStatsOpts::builder()
+            .stream(false)
+            .one_shot(true)
+            .build();
+        assert_eq!(
+            opts.serialize().as_deref(),
+            Some("one-shot=true&stream=false")
+        );
+        assert_eq!(opts.required_api_version(), Some((1, 41).into()));
+    }
+
+    #[test]
+    fn run_opts_pull_image_reference() {
+        let opts = RunOpts::builder().pull(true).build();
+        let params = |image| {
+            let params = opts.pull_opts(image).serialize().unwrap();
+            let mut params: Vec<_> = params.split('&').map(str::to_owned).collect();
+            params.sort();
+            params.join("&")
+        };
+        assert_eq!(params("alpine"), "fromImage=alpine&tag=latest");
+        assert_eq!(params("alpine:3.18"), "fromImage=alpine&tag=3.18");
+        assert_eq!(
+            params("localhost:5000/app"),
+            "fromImage=localhost%3A5000%2Fapp&tag=latest"
+        );
+        assert_eq!(
+            params("alpine@sha256:abc"),
+            "fromImage=alpine&tag=sha256%3Aabc"
+        );
+    }
+
+    #[test]
+    fn wait_container_opts() {
+        let opts = ContainerWaitOpts::builder()
+            .condition(WaitCondition::NextExit)
+            .build();
+        assert_eq!(opts.serialize().as_deref(), Some("condition=next-exit"));
+        assert_eq!(ContainerWaitOpts::builder().build().serialize(), None);
+    }
+
+    #[test]
+    fn attach_container_opts() {
+        let opts = ContainerAttachOpts::builder()
+            .stream(true)
+            .stdout(true)
+            .logs(true)
+            .build();
+        assert_eq!(
+            opts.serialize().as_deref(),
+            Some("logs=true&stdout=true&stream=true")
+        );
+        assert_eq!(opts.detach_sequence().unwrap(), None);
+
+        let opts = ContainerAttachOpts::builder().stdin(true).build();
+        assert_eq!(opts.detach_sequence().unwrap(), Some(vec![16, 17]));
+
+        let opts = ContainerAttachOpts::builder()
+            .stdin(true)
+            .detach_keys("ctrl-@,ctrl-[,ctrl-_,x")
+            .build();
+        assert_eq!(opts.detach_sequence().unwrap(), Some(vec![0, 27, 31, b'x']));
+
+        for keys in ["ctrl-A", "ctrl-", "xy", "a,,b"] {
+            let opts = ContainerAttachOpts::builder()
+                .stdin(true)
+                .detach_keys(keys)
+                .build();
+            assert!(matches!(
+                opts.detach_sequence(),
+                Err(Error::BadParameter { .. })
+            ));
+        }
+    }
 }
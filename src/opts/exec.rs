@@ -1,8 +1,13 @@
 use containers_api::{impl_field, impl_opts_builder, impl_str_field, impl_vec_field};
 use serde::Serialize;
+use serde_json::Value;
+
+use std::time::Duration;
 
 impl_opts_builder!(json => ExecCreate);
 
+impl_api_versions!(ExecCreateOpts params { "ConsoleSize" => (1, 42) });
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 /// Initial size of the console
 pub struct ConsoleSize {
@@ -19,6 +24,13 @@ impl Serialize for ConsoleSize {
     }
 }
 
+impl ExecCreateOpts {
+    /// Returns the configured detach keys, `None` if the default sequence is used.
+    pub(crate) fn detach_keys(&self) -> Option<&str> {
+        self.params.get("DetachKeys").and_then(Value::as_str)
+    }
+}
+
 impl ExecCreateOptsBuilder {
     impl_vec_field!(
         /// Command to run, as an array of strings.
@@ -78,6 +90,75 @@ impl ExecCreateOptsBuilder {
     );
 }
 
+impl ExecCreateOpts {
+    /// Returns a copy of these options attached to stdout and stderr, and to stdin if `stdin` is
+    /// set, with `tag` added to the environment of the process.
+    pub(crate) fn for_output(&self, stdin: bool, tag: &str) -> Self {
+        let mut opts = self.clone();
+        opts.params.insert("AttachStdout", Value::Bool(true));
+        opts.params.insert("AttachStderr", Value::Bool(true));
+        opts.params.insert("AttachStdin", Value::Bool(stdin));
+        let mut env = match opts.params.remove("Env") {
+            Some(Value::Array(env)) => env,
+            _ => vec![],
+        };
+        env.push(Value::String(tag.to_owned()));
+        opts.params.insert("Env", Value::Array(env));
+        opts
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Options for collecting the output of a command with
+/// [`Container::exec_output_with`](crate::Container::exec_output_with).
+pub struct ExecOutputOpts {
+    timeout: Option<Duration>,
+    stdin: Option<Vec<u8>>,
+}
+
+impl ExecOutputOpts {
+    /// Returns a builder of options that by default neither time out nor attach to stdin.
+    pub fn builder() -> ExecOutputOptsBuilder {
+        ExecOutputOptsBuilder::default()
+    }
+
+    pub(crate) fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn get_stdin(&self) -> Option<&[u8]> {
+        self.stdin.as_deref()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Builder of [`ExecOutputOpts`](ExecOutputOpts).
+pub struct ExecOutputOptsBuilder {
+    opts: ExecOutputOpts,
+}
+
+impl ExecOutputOptsBuilder {
+    /// Kills the process if it didn't exit within `timeout`, in which case
+    /// [`Error::Timeout`](crate::Error::Timeout) is returned. See
+    /// [`Container::exec_output_with`](crate::Container::exec_output_with) for the requirements
+    /// of killing the process.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.opts.timeout = Some(timeout);
+        self
+    }
+
+    /// Bytes written to stdin of the process, which is closed afterwards.
+    pub fn stdin(mut self, stdin: impl Into<Vec<u8>>) -> Self {
+        self.opts.stdin = Some(stdin.into());
+        self
+    }
+
+    /// Creates the options.
+    pub fn build(self) -> ExecOutputOpts {
+        self.opts
+    }
+}
+
 impl_opts_builder!(json => ExecResize);
 
 impl ExecResizeOptsBuilder {
@@ -87,6 +168,8 @@ impl ExecResizeOptsBuilder {
 
 impl_opts_builder!(json => ExecStart);
 
+impl_api_versions!(ExecStartOpts params { "ConsoleSize" => (1, 42) });
+
 impl ExecStartOptsBuilder {
     impl_field!(
         /// Detach from the command.
@@ -103,3 +186,28 @@ impl ExecStartOptsBuilder {
         console_size: ConsoleSize => "ConsoleSize"
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_create_opts_for_output() {
+        let opts = ExecCreateOpts::builder()
+            .command(["cat"])
+            .env(["A=1"])
+            .attach_stdin(true)
+            .build()
+            .for_output(false, "TAG=1");
+        assert_eq!(
+            opts.serialize().unwrap(),
+            r#"{"AttachStderr":true,"AttachStdin":false,"AttachStdout":true,"Cmd":["cat"],"Env":["A=1","TAG=1"]}"#
+        );
+
+        let opts = ExecCreateOpts::default().for_output(true, "TAG=1");
+        assert_eq!(
+            opts.serialize().unwrap(),
+            r#"{"AttachStderr":true,"AttachStdin":true,"AttachStdout":true,"Env":["TAG=1"]}"#
+        );
+    }
+}
@@ -149,6 +149,13 @@ impl PullOpts {
     pub(crate) fn auth_header(&self) -> Option<String> {
         self.auth.clone().map(|a| a.serialize())
     }
+
+    /// Returns the name of the pulled image, `None` when importing an image.
+    pub(crate) fn image(&self) -> Option<&str> {
+        self.params
+            .get("fromImage")
+            .and_then(|image| image.as_str())
+    }
 }
 
 pub struct PullOptsBuilder {
@@ -229,6 +236,11 @@ impl ImageBuildOpts {
     }
 }
 
+impl_api_versions!(ImageBuildOpts params {
+    "platform" => (1, 32),
+    "outputs" => (1, 40),
+});
+
 #[derive(Default)]
 pub struct ImageBuildOptsBuilder {
     path: PathBuf,
@@ -453,6 +465,8 @@ impl Filter for ImageFilter {
 
 impl_opts_builder!(url => ImageList);
 
+impl_api_versions!(ImageListOpts params { "shared-size" => (1, 42) });
+
 impl ImageListOptsBuilder {
     impl_url_bool_field!(
         /// Show all images. Only images from a final layer (no children) are shown by default.
@@ -525,6 +539,8 @@ impl ImagePruneOptsBuilder {
 
 impl_opts_builder!(url => ClearCache);
 
+impl_api_versions!(ClearCacheOpts params { "keep-storage" => (1, 39), "all" => (1, 39) });
+
 pub enum CacheFilter {
     /// Duration relative to daemon's time, during which build cache was not used,
     /// in Go's duration format (e.g., '24h').
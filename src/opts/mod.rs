@@ -1,4 +1,31 @@
 //! Options used for configuring the behavior of certain API endpoints
+
+/// Implements `required_api_version` for opts that have parameters introduced in API versions
+/// newer than the oldest one supported by the daemon. Every parameter is mapped to the API version
+/// that introduced it.
+macro_rules! impl_api_versions {
+    ($opts:ident) => {
+        impl $opts {
+            /// Returns the highest API version required by the parameters set in these options.
+            pub(crate) fn required_api_version(&self) -> Option<crate::ApiVersion> {
+                None
+            }
+        }
+    };
+    ($opts:ident $params:ident { $($key:literal => ($major:literal, $minor:literal)),+ $(,)? }) => {
+        impl $opts {
+            /// Returns the highest API version required by the parameters set in these options.
+            pub(crate) fn required_api_version(&self) -> Option<crate::ApiVersion> {
+                [$(($key, ($major, $minor))),+]
+                    .into_iter()
+                    .filter(|(key, _)| self.$params.contains_key(key))
+                    .map(|(_, version)| crate::ApiVersion::from(version))
+                    .max()
+            }
+        }
+    };
+}
+
 mod container;
 mod exec;
 mod image;
@@ -54,6 +81,36 @@ use containers_api::{impl_opts_builder, impl_url_bool_field, impl_url_field};
 
 impl_opts_builder!(url => Logs);
 
+impl_api_versions!(LogsOpts params { "until" => (1, 35) });
+
+impl LogsOpts {
+    /// Whether the log lines are prefixed with timestamps.
+    pub(crate) fn timestamps(&self) -> bool {
+        self.params.get("timestamps").map(String::as_str) == Some("true")
+    }
+
+    #[cfg(feature = "chrono")]
+    /// Returns options following the logs with timestamps, starting at `since` instead of the
+    /// last `tail` lines if set.
+    pub(crate) fn follow_since(&self, since: Option<&chrono::DateTime<chrono::Utc>>) -> Self {
+        let mut opts = self.clone();
+        opts.params.insert("follow", true.to_string());
+        opts.params.insert("timestamps", true.to_string());
+        if let Some(since) = since {
+            opts.params.remove("tail");
+            opts.params.insert(
+                "since",
+                format!(
+                    "{}.{:09}",
+                    since.timestamp(),
+                    since.timestamp_subsec_nanos()
+                ),
+            );
+        }
+        opts
+    }
+}
+
 impl LogsOptsBuilder {
     impl_url_bool_field!(
         /// Keep connection after returning logs.
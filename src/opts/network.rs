@@ -14,6 +14,8 @@ impl_opts_builder!(url =>
     NetworkList
 );
 
+impl_api_versions!(NetworkListOpts);
+
 /// Used for [`NetworkFilter::Scope`](NetworkFilter::Scope).
 pub enum Scope {
     Swarm,
@@ -94,6 +96,8 @@ pub struct NetworkCreateOpts {
     params: HashMap<&'static str, Value>,
 }
 
+impl_api_versions!(NetworkCreateOpts params { "Attachable" => (1, 25), "Ingress" => (1, 29) });
+
 impl NetworkCreateOpts {
     /// Return a new instance of a opts-builder for creating a network.
     pub fn builder<N>(name: N) -> NetworkCreateOptsBuilder
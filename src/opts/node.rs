@@ -112,6 +112,8 @@ impl Filter for NodeFilter {
 
 impl_opts_builder!(url => NodeList);
 
+impl_api_versions!(NodeListOpts);
+
 impl NodeListOptsBuilder {
     impl_filter_func!(NodeFilter);
 }
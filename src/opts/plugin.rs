@@ -3,6 +3,8 @@ use containers_api::{impl_filter_func, impl_opts_builder};
 
 impl_opts_builder!(url => PluginList);
 
+impl_api_versions!(PluginListOpts);
+
 pub enum PluginFilter {
     Capability(String),
     Enable,
@@ -6,6 +6,8 @@ use serde::{Deserialize, Serialize};
 
 impl_opts_builder!(url => SecretList);
 
+impl_api_versions!(SecretListOpts);
+
 pub enum SecretFilter {
     /// The ID of the secret.
     Id(String),
@@ -31,6 +31,8 @@ impl Filter for ServiceFilter {
 
 impl_opts_builder!(url => ServiceList);
 
+impl_api_versions!(ServiceListOpts params { "status" => (1, 41) });
+
 impl ServiceListOptsBuilder {
     impl_filter_func!(ServiceFilter);
 
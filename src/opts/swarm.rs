@@ -3,6 +3,8 @@ use containers_api::{impl_field, impl_opts_builder, impl_str_field, impl_vec_fie
 
 impl_opts_builder!(json => SwarmJoin);
 
+impl_api_versions!(SwarmJoinOpts params { "DataPathAddr" => (1, 30) });
+
 impl SwarmJoinOptsBuilder {
     impl_str_field!(
         /// Listen address used for inter-manager communication if the node gets promoted to manager,
@@ -36,6 +38,13 @@ impl SwarmJoinOptsBuilder {
 
 impl_opts_builder!(json => SwarmInit);
 
+impl_api_versions!(SwarmInitOpts params {
+    "DataPathAddr" => (1, 30),
+    "DefaultAddrPool" => (1, 39),
+    "SubnetSize" => (1, 39),
+    "DataPathPort" => (1, 40),
+});
+
 impl SwarmInitOptsBuilder {
     impl_str_field!(
         // Listen address used for inter-manager communication if the node gets promoted to manager,
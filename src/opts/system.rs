@@ -181,6 +181,8 @@ impl AsRef<str> for DataUsageType {
 }
 
 impl_opts_builder!(url => SystemDataUsage);
+
+impl_api_versions!(SystemDataUsageOpts vec_params { "type" => (1, 42) });
 impl SystemDataUsageOptsBuilder {
     pub fn types(mut self, types: impl IntoIterator<Item = DataUsageType>) -> Self {
         self.vec_params.insert(
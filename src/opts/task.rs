@@ -3,6 +3,8 @@ use containers_api::{impl_filter_func, impl_opts_builder};
 
 impl_opts_builder!(url => TaskList);
 
+impl_api_versions!(TaskListOpts);
+
 #[derive(Clone, Copy, Debug)]
 pub enum TaskStateFilter {
     Running,
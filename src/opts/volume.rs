@@ -6,6 +6,8 @@ use containers_api::{
 
 impl_opts_builder!(json => VolumeCreate);
 
+impl_api_versions!(VolumeCreateOpts params { "ClusterVolumeSpec" => (1, 42) });
+
 impl VolumeCreateOptsBuilder {
     impl_str_field!(
         /// The new volume's name. If not specified, Docker generates a name.
@@ -1,9 +1,20 @@
 use containers_api::conn::tty;
 use containers_api::conn::Payload;
-use futures_util::{AsyncRead, AsyncWrite};
-use hyper::Body;
+use futures_util::{
+    future::Either,
+    io::{AsyncBufReadExt, BufReader},
+    stream::{self, Stream, TryStreamExt},
+    AsyncRead, AsyncWrite,
+};
+use hyper::{body::Bytes, Body};
 
-use crate::{Docker, Result};
+use crate::{conn::TtyChunk, models::LogLine, Docker, Error, Result};
+
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 /// Attaches a multiplexed TCP stream to the container that can be used to read Stdout, Stderr and write Stdin.
 async fn attach_raw(
@@ -14,13 +25,17 @@ async fn attach_raw(
     docker.post_upgrade_stream(endpoint, payload).await
 }
 
+/// Attaches to the container, if `detach_keys` are set the returned stream ends once they were
+/// written to stdin.
 pub async fn attach(
     docker: Docker,
     endpoint: String,
     payload: Payload<Body>,
     is_tty: bool,
+    detach_keys: Option<Vec<u8>>,
 ) -> Result<tty::Multiplexer> {
     attach_raw(docker, endpoint, payload).await.map(|s| {
+        let s = DetachStream::new(s, detach_keys.unwrap_or_default());
         if is_tty {
             tty::Multiplexer::new(s, tty::decode_raw)
         } else {
@@ -28,3 +43,270 @@ pub async fn attach(
         }
     })
 }
+
+/// Decodes a logs response into log lines. The output of containers with a TTY is not
+/// multiplexed, so it's split at line breaks instead.
+pub(crate) fn decode_logs<S>(
+    stream: S,
+    is_tty: bool,
+    timestamps: bool,
+) -> impl Stream<Item = Result<LogLine>>
+where
+    S: Stream<Item = Result<Bytes>> + Unpin,
+{
+    let chunks = if is_tty {
+        let reader = BufReader::new(stream.map_err(io::Error::other).into_async_read());
+        Either::Left(stream::try_unfold(reader, |mut reader| async move {
+            let mut line = vec![];
+            if reader.read_until(b'\n', &mut line).await? == 0 {
+                return Ok(None);
+            }
+            Ok(Some((TtyChunk::StdOut(line), reader)))
+        }))
+    } else {
+        Either::Right(
+            tty::decode(stream.map_err(|e| containers_api::conn::Error::Any(Box::new(e))))
+                .map_err(Error::Error),
+        )
+    };
+    chunks.map_ok(move |chunk| LogLine::new(chunk, timestamps))
+}
+
+/// Connection that watches the data written to it for the detach sequence. Once the sequence was
+/// written the daemon closes the connection, the output sent until then is still read and the
+/// error of the closed connection is returned as EOF.
+struct DetachStream<S> {
+    inner: S,
+    keys: Vec<u8>,
+    /// Length of the longest proper prefix of `keys[..=i]` that is also its suffix, used to fall
+    /// back to a shorter partial match on a mismatch.
+    fallback: Vec<usize>,
+    matched: usize,
+    detached: bool,
+}
+
+impl<S> DetachStream<S> {
+    fn new(inner: S, keys: Vec<u8>) -> Self {
+        let mut fallback = vec![0; keys.len()];
+        let mut len = 0;
+        for i in 1..keys.len() {
+            while len > 0 && keys[i] != keys[len] {
+                len = fallback[len - 1];
+            }
+            if keys[i] == keys[len] {
+                len += 1;
+            }
+            fallback[i] = len;
+        }
+        Self {
+            inner,
+            keys,
+            fallback,
+            matched: 0,
+            detached: false,
+        }
+    }
+
+    fn scan(&mut self, buf: &[u8]) {
+        if self.keys.is_empty() {
+            return;
+        }
+        for byte in buf {
+            while self.matched > 0 && *byte != self.keys[self.matched] {
+                self.matched = self.fallback[self.matched - 1];
+            }
+            if *byte == self.keys[self.matched] {
+                self.matched += 1;
+            }
+            if self.matched == self.keys.len() {
+                self.detached = true;
+                self.matched = 0;
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DetachStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Err(_)) if this.detached => Poll::Ready(Ok(0)),
+            poll => poll,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DetachStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = &poll {
+            this.scan(&buf[..*n]);
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LogStream;
+    use futures_util::{
+        io::{AsyncWriteExt, Cursor},
+        StreamExt,
+    };
+
+    fn body(chunks: Vec<&'static [u8]>) -> impl Stream<Item = Result<Bytes>> + Unpin {
+        stream::iter(
+            chunks
+                .into_iter()
+                .map(|chunk| Ok(Bytes::from_static(chunk))),
+        )
+    }
+
+    #[tokio::test]
+    async fn decodes_multiplexed_logs() {
+        let lines: Vec<_> = decode_logs(
+            body(vec![
+                b"\x01\0\0\0\0\0\0\x23",
+                b"2023-01-02T03:04:05.123456789Z out\n",
+                b"\x02\0\0\0\0\0\0\x04err\n",
+            ]),
+            false,
+            true,
+        )
+        .try_collect()
+        .await
+        .unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].stream, LogStream::Stdout);
+        assert_eq!(lines[0].message_lossy(), "out\n");
+        #[cfg(feature = "chrono")]
+        assert_eq!(
+            lines[0].timestamp.unwrap().to_rfc3339(),
+            "2023-01-02T03:04:05.123456789+00:00"
+        );
+        #[cfg(not(feature = "chrono"))]
+        assert_eq!(
+            lines[0].timestamp.as_deref(),
+            Some("2023-01-02T03:04:05.123456789Z")
+        );
+        // no timestamp prefix
+        assert_eq!(lines[1].stream, LogStream::Stderr);
+        assert_eq!(lines[1].timestamp, None);
+        assert_eq!(lines[1].message_lossy(), "err\n");
+    }
+
+    #[tokio::test]
+    async fn splits_tty_logs_into_lines() {
+        let lines: Vec<_> = decode_logs(body(vec![b"first\nsec", b"ond\nlast"]), true, false)
+            .map_ok(|line| (line.stream, line.message_lossy().into_owned()))
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            [
+                (LogStream::Stdout, "first\n".to_string()),
+                (LogStream::Stdout, "second\n".to_string()),
+                (LogStream::Stdout, "last".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_detach_sequence() {
+        let mut stream = DetachStream::new(Cursor::new(Vec::<u8>::new()), vec![16, 17]);
+        stream.scan(&[1, 16, 16]);
+        assert!(!stream.detached);
+        stream.scan(&[17]);
+        assert!(stream.detached);
+
+        let mut stream = DetachStream::new(Cursor::new(Vec::<u8>::new()), vec![16, 16, 17]);
+        stream.scan(&[16, 16, 16]);
+        assert!(!stream.detached);
+        stream.scan(&[17]);
+        assert!(stream.detached);
+
+        let mut stream = DetachStream::new(Cursor::new(Vec::<u8>::new()), vec![]);
+        stream.scan(&[16, 17]);
+        assert!(!stream.detached);
+    }
+
+    /// Connection that is reset by the daemon once all of its output was read.
+    struct Connection(Cursor<Vec<u8>>);
+
+    impl AsyncRead for Connection {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            match Pin::new(&mut this.0).poll_read(cx, buf) {
+                Poll::Ready(Ok(0)) => Poll::Ready(Err(io::ErrorKind::ConnectionReset.into())),
+                poll => poll,
+            }
+        }
+    }
+
+    impl AsyncWrite for Connection {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn ends_stream_after_detaching() {
+        let output = b"\x01\0\0\0\0\0\0\x02hi".repeat(3);
+        let stream = DetachStream::new(Connection(Cursor::new(output)), vec![16, 17]);
+        let mut multiplexer = tty::Multiplexer::new(stream, tty::decode_chunk);
+
+        assert!(multiplexer.next().await.unwrap().is_ok());
+        multiplexer.write_all(&[b'a', 16, 17]).await.unwrap();
+        // the output sent before the daemon closed the connection is still read
+        assert!(multiplexer.next().await.unwrap().is_ok());
+        assert!(multiplexer.next().await.unwrap().is_ok());
+        assert!(multiplexer.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn fails_on_reset_without_detaching() {
+        let output = b"\x01\0\0\0\0\0\0\x02hi".to_vec();
+        let stream = DetachStream::new(Connection(Cursor::new(output)), vec![16, 17]);
+        let mut multiplexer = tty::Multiplexer::new(stream, tty::decode_chunk);
+
+        assert!(multiplexer.next().await.unwrap().is_ok());
+        assert!(multiplexer.next().await.unwrap().is_err());
+    }
+}
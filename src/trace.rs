@@ -0,0 +1,279 @@
+//! Optional [`tracing`](https://docs.rs/tracing) instrumentation of API calls enabled with the
+//! `tracing` feature.
+//!
+//! Every API call runs inside of a `docker` span. The span is named after the API method
+//! (`otel.name`, for example `container.start` or `image.pull`) and records the id of the
+//! object, the endpoint, the HTTP status, the number of received bytes and the error if the call
+//! failed. Without the feature all functions in this module are no-ops.
+
+use crate::Result;
+
+use futures_util::{Future, Stream};
+use hyper::{body::Bytes, Method};
+
+#[cfg(feature = "tracing")]
+pub(crate) use imp::*;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use noop::*;
+
+#[cfg(feature = "tracing")]
+mod imp {
+    use super::*;
+    use crate::Error;
+
+    use futures_util::{stream, StreamExt, TryStreamExt};
+    use hyper::StatusCode;
+    use tracing::{field::Empty, Instrument, Span};
+
+    fn span(operation: &'static str, id: Option<&str>) -> Span {
+        tracing::info_span!(
+            "docker",
+            otel.name = operation,
+            otel.status_code = Empty,
+            docker.operation = operation,
+            docker.id = id,
+            docker.endpoint = Empty,
+            http.method = Empty,
+            http.status_code = Empty,
+            docker.bytes = Empty,
+            error = Empty,
+        )
+    }
+
+    fn record_error(span: &Span, error: &Error) {
+        span.record("otel.status_code", "ERROR");
+        span.record("error", tracing::field::display(error));
+    }
+
+    /// Runs `future` in a span of the API method `operation` called on the object `id`.
+    pub(crate) async fn future<T>(
+        operation: &'static str,
+        id: Option<&str>,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let span = span(operation, id);
+        let result = future.instrument(span.clone()).await;
+        if let Err(e) = &result {
+            record_error(&span, e);
+        }
+        result
+    }
+
+    /// Polls `stream` in a span of the API method `operation` called on the object `id`.
+    pub(crate) fn stream<'a, T: 'a>(
+        operation: &'static str,
+        id: Option<&str>,
+        stream: impl Stream<Item = Result<T>> + 'a,
+    ) -> impl Stream<Item = Result<T>> + 'a {
+        let span = span(operation, id);
+        let mut stream = Box::pin(stream.inspect_err({
+            let span = span.clone();
+            move |e| record_error(&span, e)
+        }));
+        stream::poll_fn(move |cx| {
+            let _enter = span.enter();
+            stream.poll_next_unpin(cx)
+        })
+    }
+
+    /// Records the method and the endpoint of a request in the current span.
+    pub(crate) fn record_request(method: &Method, endpoint: &str) {
+        let span = Span::current();
+        span.record("http.method", tracing::field::display(method));
+        span.record("docker.endpoint", endpoint);
+    }
+
+    /// Records the status of the response in the current span.
+    pub(crate) fn record_status(status: StatusCode) {
+        Span::current().record("http.status_code", status.as_u16());
+    }
+
+    /// Records the size of a response body read at once in the current span.
+    pub(crate) fn record_bytes(bytes: usize) {
+        Span::current().record("docker.bytes", bytes as u64);
+    }
+
+    /// Records the total number of bytes received from `stream` in the current span.
+    pub(crate) fn count_bytes(
+        stream: impl Stream<Item = Result<Bytes>>,
+    ) -> impl Stream<Item = Result<Bytes>> {
+        let mut total = 0;
+        stream.inspect_ok(move |chunk| {
+            total += chunk.len();
+            record_bytes(total);
+        })
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod noop {
+    use super::*;
+    use hyper::StatusCode;
+
+    pub(crate) async fn future<T>(
+        _: &'static str,
+        _: Option<&str>,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        future.await
+    }
+
+    pub(crate) fn stream<'a, T: 'a>(
+        _: &'static str,
+        _: Option<&str>,
+        stream: impl Stream<Item = Result<T>> + 'a,
+    ) -> impl Stream<Item = Result<T>> + 'a {
+        stream
+    }
+
+    pub(crate) fn record_request(_: &Method, _: &str) {}
+
+    pub(crate) fn record_status(_: StatusCode) {}
+
+    pub(crate) fn record_bytes(_: usize) {}
+
+    pub(crate) fn count_bytes(
+        stream: impl Stream<Item = Result<Bytes>>,
+    ) -> impl Stream<Item = Result<Bytes>> {
+        stream
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::{docker::tests::serve, opts::ContainerCommitOpts, Docker};
+
+    use futures_util::TryStreamExt;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    type Fields = HashMap<&'static str, String>;
+
+    /// Records the fields of every span, ids are indexes into `spans` offset by one.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<(&'static Metadata<'static>, Fields)>>>,
+        entered: Arc<Mutex<Vec<span::Id>>>,
+    }
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    impl Recorder {
+        /// Returns the fields of the spans of API calls.
+        fn operations(&self) -> Vec<Fields> {
+            let spans = self.spans.lock().unwrap();
+            spans
+                .iter()
+                .filter(|(metadata, _)| metadata.name() == "docker")
+                .map(|(_, fields)| fields.clone())
+                .collect()
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut fields = Fields::new();
+            span.record(&mut Visitor(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((span.metadata(), fields));
+            span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let (_, fields) = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut Visitor(fields));
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, span: &span::Id) {
+            self.entered.lock().unwrap().push(span.clone());
+        }
+
+        fn exit(&self, _: &span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> tracing_core::span::Current {
+            match self.entered.lock().unwrap().last() {
+                Some(id) => {
+                    let spans = self.spans.lock().unwrap();
+                    tracing_core::span::Current::new(
+                        id.clone(),
+                        spans[id.into_u64() as usize - 1].0,
+                    )
+                }
+                None => tracing_core::span::Current::none(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn names_spans_after_api_methods() {
+        let (addr, _) = serve(vec![
+            (201, r#"{"Id":"sha256:abc"}"#),
+            (200, "tarball"),
+            (200, r#"[{"Deleted":"sha256:abc"}]"#),
+        ]);
+        let docker = Docker::new(format!("tcp://{addr}")).unwrap();
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+
+        let container = docker.containers().get("web");
+        container
+            .commit(&ContainerCommitOpts::builder().build(), None)
+            .await
+            .unwrap();
+        let archive: Vec<_> = container.copy_from("/etc").try_collect().await.unwrap();
+        assert_eq!(archive.concat(), b"tarball");
+        docker.images().get("acme/push").delete().await.unwrap();
+
+        let operations = recorder.operations();
+        let names: Vec<_> = operations
+            .iter()
+            .map(|fields| (fields["otel.name"].as_str(), fields["docker.id"].as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("container.commit", "web"),
+                ("container.copy_from", "web"),
+                ("image.delete", "acme/push"),
+            ]
+        );
+        assert_eq!(operations[0]["http.method"], "POST");
+        assert!(operations[0]["docker.endpoint"].starts_with("/commit?"));
+        assert_eq!(operations[1]["http.method"], "GET");
+        assert_eq!(
+            operations[1]["docker.endpoint"],
+            "/containers/web/archive?path=%2Fetc"
+        );
+        assert_eq!(operations[2]["http.method"], "DELETE");
+        assert_eq!(operations[2]["docker.endpoint"], "/images/acme/push");
+        assert_eq!(operations[2]["http.status_code"], "200");
+    }
+}
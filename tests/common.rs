@@ -1,7 +1,6 @@
 #![allow(dead_code)]
 
 use std::env;
-use std::path::PathBuf;
 
 pub use docker_api::{api, conn, models, models::ImageBuildChunk, opts, Docker};
 pub use futures_util::{StreamExt, TryStreamExt};
@@ -13,36 +12,68 @@ pub const DEFAULT_CMD_ARRAY: &[&str] = &["sleep", "inf"];
 pub const TEST_IMAGE_PATH: &str = "/var/test123";
 
 const URI_ENV_VAR: &str = "DOCKER_API_URI";
+/// Set to `record` to record the exchanges of each test to `tests/cassettes/<crate>/<test>.json`
+/// or to `replay` to run the tests against the recorded cassettes without a daemon.
+#[cfg(feature = "cassette")]
+const CASSETTE_ENV_VAR: &str = "DOCKER_API_CASSETTE";
 
 pub fn init_runtime() -> Docker {
     let _ = env_logger::try_init();
+
+    #[cfg(feature = "cassette")]
+    match env::var(CASSETTE_ENV_VAR).as_deref() {
+        Ok("record") => {
+            let transport = conn::Transport::record(connect().transport().clone(), cassette_path());
+            return Docker::from_transport(transport);
+        }
+        Ok("replay") => {
+            let path = cassette_path();
+            let transport = conn::Transport::replay(&path)
+                .unwrap_or_else(|e| panic!("failed to load cassette {}: {e}", path.display()));
+            return Docker::from_transport(transport);
+        }
+        _ => {}
+    }
+
+    connect()
+}
+
+fn connect() -> Docker {
     if let Ok(uri) = env::var(URI_ENV_VAR) {
         Docker::new(uri).unwrap()
     } else {
-        #[cfg(unix)]
-        {
-            let uid = nix::unistd::Uid::effective();
-            let docker_dir = PathBuf::from(format!("/run/user/{uid}/docker"));
-            let docker_root_dir = PathBuf::from("/var/run");
-            if docker_dir.exists() {
-                Docker::unix(docker_dir.join("docker.sock"))
-            } else if docker_root_dir.exists() {
-                Docker::unix(docker_root_dir.join("docker.sock"))
-            } else {
-                panic!(
-                    "Docker socket not found. Tried {URI_ENV_VAR} env variable, {} and {}",
-                    docker_dir.display(),
-                    docker_root_dir.display()
-                );
-            }
-        }
-        #[cfg(not(unix))]
-        {
-            panic!("Docker socket not found. Try setting the {URI_ENV_VAR} env variable",);
-        }
+        Docker::from_env()
+            .unwrap_or_else(|e| panic!("{e}. Try setting the {URI_ENV_VAR} env variable"))
     }
 }
 
+/// Cassette of the current call of `init_runtime`, named after the test binary and the test
+/// thread. Every call after the first one in a test gets a numbered suffix, so the cassettes
+/// of a test with several clients don't overwrite each other.
+#[cfg(feature = "cassette")]
+fn cassette_path() -> std::path::PathBuf {
+    use std::{
+        collections::BTreeMap,
+        sync::{Mutex, PoisonError},
+    };
+    static CALLS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+    let thread = std::thread::current();
+    let test = thread.name().unwrap_or("unknown").replace("::", "__");
+    let mut calls = CALLS.lock().unwrap_or_else(PoisonError::into_inner);
+    let call = calls.entry(test.clone()).or_default();
+    *call += 1;
+    let name = match *call {
+        1 => test,
+        n => format!("{test}-{n}"),
+    };
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cassettes")
+        .join(env!("CARGO_CRATE_NAME"))
+        .join(format!("{name}.json"))
+}
+
 pub async fn create_base_container(
     docker: &Docker,
     name: &str,
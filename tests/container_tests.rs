@@ -4,7 +4,7 @@ use common::{
     cleanup_container,
     conn::TtyChunk,
     create_base_container, get_container_full_id, init_runtime,
-    opts::{ContainerCreateOpts, ExecCreateOpts},
+    opts::{ContainerCreateOpts, ContainerUpdateOpts, ExecCreateOpts},
     StreamExt, TryStreamExt, DEFAULT_CMD, DEFAULT_CMD_ARRAY, DEFAULT_IMAGE,
 };
 
@@ -154,6 +154,36 @@ async fn container_pause_unpause() {
     cleanup_container(&docker, container_name).await;
 }
 
+#[tokio::test]
+async fn container_update() {
+    let docker = init_runtime();
+
+    let container_name = "test-update-container";
+    let container = create_base_container(&docker, container_name, None).await;
+
+    let opts = ContainerUpdateOpts::builder()
+        .memory(64 * 1024 * 1024)
+        .memory_swap(128 * 1024 * 1024)
+        .cpu_shares(512)
+        .restart_policy("on-failure", 3)
+        .build();
+    let update_result = container.update(&opts).await;
+    assert!(update_result.is_ok());
+
+    let host_config = container
+        .inspect()
+        .await
+        .expect("updated container inspect data")
+        .host_config
+        .unwrap();
+    assert_eq!(host_config["Memory"], 64 * 1024 * 1024);
+    assert_eq!(host_config["CpuShares"], 512);
+    assert_eq!(host_config["RestartPolicy"]["Name"], "on-failure");
+    assert_eq!(host_config["RestartPolicy"]["MaximumRetryCount"], 3);
+
+    cleanup_container(&docker, container_name).await;
+}
+
 #[tokio::test]
 async fn container_kill() {
     let docker = init_runtime();
@@ -210,6 +240,97 @@ async fn container_stop() {
     cleanup_container(&docker, container_name).await;
 }
 
+#[tokio::test]
+async fn container_wait() {
+    use docker_api::{
+        api::check_exit,
+        opts::{ContainerWaitOpts, WaitCondition},
+        Error,
+    };
+
+    let docker = init_runtime();
+
+    let container_name = "test-wait-container";
+    let opts = ContainerCreateOpts::builder()
+        .image(DEFAULT_IMAGE)
+        .name(container_name)
+        .command(["sh", "-c", "exit 3"])
+        .build();
+    let container = create_base_container(&docker, container_name, Some(opts)).await;
+
+    let opts = ContainerWaitOpts::builder()
+        .condition(WaitCondition::NextExit)
+        .build();
+    let wait = container
+        .register_wait(&opts)
+        .await
+        .expect("registered wait");
+    container.start().await.expect("started container");
+
+    let response = wait.wait().await.expect("container exit");
+    assert_eq!(response.status_code, 3);
+    assert!(matches!(
+        check_exit(response),
+        Err(Error::ContainerExited { code: 3, .. })
+    ));
+
+    let response = container
+        .wait_with(&Default::default())
+        .await
+        .expect("stopped container wait");
+    assert_eq!(response.status_code, 3);
+
+    cleanup_container(&docker, container_name).await;
+}
+
+#[tokio::test]
+async fn containers_run() {
+    use docker_api::opts::RunOpts;
+
+    let docker = init_runtime();
+
+    let container_name = "test-run-container";
+    cleanup_container(&docker, container_name).await;
+    let opts = ContainerCreateOpts::builder()
+        .image(DEFAULT_IMAGE)
+        .name(container_name)
+        .command(["sh", "-c", "echo out; echo err >&2; exit 3"])
+        .build();
+
+    let output = docker
+        .containers()
+        .run(&opts, &RunOpts::default())
+        .await
+        .expect("ran container");
+    assert_eq!(output.status_code, 3);
+    assert!(!output.success());
+    assert_eq!(output.stdout_lossy(), "out\n");
+    assert_eq!(output.stderr_lossy(), "err\n");
+    assert!(docker
+        .containers()
+        .get(container_name)
+        .inspect()
+        .await
+        .unwrap_err()
+        .is_not_found());
+
+    let run_opts = RunOpts::builder().remove(false).build();
+    let output = docker
+        .containers()
+        .run(&opts, &run_opts)
+        .await
+        .expect("ran container");
+    assert_eq!(output.status_code, 3);
+    assert!(docker
+        .containers()
+        .get(container_name)
+        .inspect()
+        .await
+        .is_ok());
+
+    cleanup_container(&docker, container_name).await;
+}
+
 #[tokio::test]
 async fn container_commit() {
     use docker_api::opts::ContainerCommitOpts;
@@ -307,6 +428,49 @@ async fn container_exec() {
     cleanup_container(&docker, container_name).await;
 }
 
+#[tokio::test]
+async fn container_exec_output() {
+    use docker_api::{opts::ExecOutputOpts, Error};
+    use std::time::Duration;
+
+    let docker = init_runtime();
+
+    let container_name = "test-exec-output-container";
+    let container = create_base_container(&docker, container_name, None).await;
+    let _ = container.start().await;
+
+    let opts = ExecCreateOpts::builder()
+        .command(["sh", "-c", "cat; echo err >&2; exit 4"])
+        .build();
+    let output = container
+        .exec_output_with(&opts, &ExecOutputOpts::builder().stdin("input").build())
+        .await
+        .expect("exec output");
+    assert_eq!(output.exit_code, 4);
+    assert_eq!(output.stdout_lossy(), "input");
+    assert_eq!(output.stderr_lossy(), "err\n");
+
+    let opts = ExecCreateOpts::builder().command(["sleep", "60"]).build();
+    let timeout = Duration::from_millis(500);
+    let result = container
+        .exec_output_with(&opts, &ExecOutputOpts::builder().timeout(timeout).build())
+        .await;
+    assert!(matches!(result, Err(Error::Timeout(t)) if t == timeout));
+
+    // the timed out process was killed
+    let opts = ExecCreateOpts::builder()
+        .command([
+            "sh",
+            "-c",
+            r#"for p in /proc/[0-9]*; do tr '\0' ' ' < "$p/cmdline"; echo; done"#,
+        ])
+        .build();
+    let output = container.exec_output(&opts).await.expect("exec output");
+    assert!(!output.stdout_lossy().contains("sleep 60"));
+
+    cleanup_container(&docker, container_name).await;
+}
+
 #[tokio::test]
 async fn container_copy_from() {
     let docker = init_runtime();
@@ -454,17 +618,17 @@ async fn container_logs() {
 
     let _ = container.start().await;
 
-    use docker_api::opts::LogsOpts;
+    use docker_api::{models::LogStream, opts::LogsOpts};
 
     let mut logs_stream = container.logs(&LogsOpts::builder().stdout(true).stderr(true).build());
-    let chunk = logs_stream.next().await;
-    match chunk {
-        Some(Ok(TtyChunk::StdOut(chunk))) => {
-            let logs = String::from_utf8_lossy(&chunk);
-            assert_eq!(logs, "123456\n");
+    let line = logs_stream.next().await;
+    match line {
+        Some(Ok(line)) => {
+            assert_eq!(line.stream, LogStream::Stdout);
+            assert_eq!(line.message_lossy(), "123456\n");
         }
-        chunk => {
-            eprintln!("invalid chunk {chunk:?}");
+        line => {
+            eprintln!("invalid line {line:?}");
             std::process::exit(1);
         }
     }